@@ -0,0 +1,153 @@
+use crate::backend::encapsulation::{self, AtFlags};
+use bitflags::bitflags;
+use std::{io::Result, os::unix::io::AsFd, path::Path};
+
+bitflags! {
+    /// The accesses checked by [`access`], `R_OK`, `W_OK` and `X_OK` of
+    /// `access(2)`.
+    ///
+    /// An empty mode only checks that the file exists, like `F_OK`.
+    pub struct AccessMode: i32 {
+        /// The file can be read.
+        const READ = libc::R_OK;
+        /// The file can be written.
+        const WRITE = libc::W_OK;
+        /// The file can be executed, or the directory searched.
+        const EXECUTE = libc::X_OK;
+    }
+}
+
+bitflags! {
+    /// Flags of [`access_at`].
+    pub struct AccessFlags: i32 {
+        /// Check with the effective user and group IDs instead of the real
+        /// ones, `AT_EACCESS`.
+        const EFFECTIVE_IDS = libc::AT_EACCESS;
+        /// Do not dereference the path if it is a symbolic link,
+        /// `AT_SYMLINK_NOFOLLOW`.
+        const SYMLINK_NOFOLLOW = libc::AT_SYMLINK_NOFOLLOW;
+    }
+}
+
+/// Checks whether the real user and group IDs of the process can access
+/// `path` with `mode`, as `access(2)` does.
+///
+/// Unlike [`Metadata::permission`](crate::Metadata::permission), this takes ACLs,
+/// capabilities and read-only mounts into account. The error tells why an
+/// access is denied, e.g., `EACCES` or `EROFS`.
+#[inline]
+pub fn access<P: AsRef<Path>>(path: P, mode: AccessMode) -> Result<()> {
+    encapsulation::faccessat(None, path, mode.bits(), AtFlags::empty())
+}
+
+/// Same as [`access`], with `path` relative to the directory `dirfd` and
+/// `flags` to change how the check is done.
+///
+/// On kernels older than Linux 5.8, which lack `faccessat2(2)`, checking a
+/// file that is not a symbolic link with the effective IDs, when they differ
+/// from the real ones, is done with the permission bits only.
+#[inline]
+pub fn access_at<Fd: AsFd, P: AsRef<Path>>(
+    dirfd: Fd,
+    path: P,
+    mode: AccessMode,
+    flags: AccessFlags,
+) -> Result<()> {
+    encapsulation::faccessat(
+        Some(dirfd.as_fd()),
+        path,
+        mode.bits(),
+        AtFlags::from_bits_truncate(flags.bits()),
+    )
+}
+
+/// Returns whether the process can write to `path` right now, checked with
+/// its effective IDs.
+///
+/// Unlike [`Permissions::readonly`](crate::Permissions::readonly), which only
+/// looks at the write bits, this is right for root, with ACLs, on read-only
+/// mounts and for executables being run.
+pub fn is_writable<P: AsRef<Path>>(path: P) -> Result<bool> {
+    match encapsulation::faccessat(None, path, libc::W_OK, AtFlags::AT_EACCESS) {
+        Ok(()) => Ok(true),
+        Err(e)
+            if matches!(
+                e.raw_os_error(),
+                Some(libc::EACCES | libc::EPERM | libc::EROFS | libc::ETXTBSY)
+            ) =>
+        {
+            Ok(false)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        functions::{create_dir_all, remove_dir_all, set_permissions, symlink, write},
+        File, Permissions,
+    };
+    use std::{io::ErrorKind, os::unix::fs::PermissionsExt};
+
+    #[test]
+    fn access_modes() {
+        let dir = "/tmp/test_access_modes";
+        create_dir_all(dir).unwrap();
+        let file = format!("{}/file", dir);
+        write(&file, "").unwrap();
+        set_permissions(&file, Permissions::from_mode(0o444)).unwrap();
+
+        access(&file, AccessMode::empty()).unwrap();
+        access(&file, AccessMode::READ).unwrap();
+        // Even root needs an execute bit.
+        assert_eq!(
+            access(&file, AccessMode::EXECUTE).unwrap_err().kind(),
+            ErrorKind::PermissionDenied
+        );
+        assert_eq!(
+            access(format!("{}/missing", dir), AccessMode::empty())
+                .unwrap_err()
+                .kind(),
+            ErrorKind::NotFound
+        );
+
+        // Root ignores the write bits, which `readonly()` does not know.
+        let root = nix::unistd::geteuid().is_root();
+        assert!(crate::functions::metadata(&file)
+            .unwrap()
+            .permission()
+            .readonly());
+        assert_eq!(is_writable(&file).unwrap(), root);
+        assert_eq!(access(&file, AccessMode::WRITE).is_ok(), root);
+
+        remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn access_at_flags() {
+        let dir = "/tmp/test_access_at_flags";
+        create_dir_all(dir).unwrap();
+        symlink("missing", format!("{}/dangling", dir)).unwrap();
+        let dirfd = File::open(dir).unwrap();
+
+        let mode = AccessMode::READ | AccessMode::WRITE;
+        access_at(&dirfd, "dangling", mode, AccessFlags::SYMLINK_NOFOLLOW).unwrap();
+        access_at(
+            &dirfd,
+            "dangling",
+            mode,
+            AccessFlags::SYMLINK_NOFOLLOW | AccessFlags::EFFECTIVE_IDS,
+        )
+        .unwrap();
+        assert_eq!(
+            access_at(&dirfd, "dangling", mode, AccessFlags::empty())
+                .unwrap_err()
+                .kind(),
+            ErrorKind::NotFound
+        );
+
+        remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,545 @@
+//! POSIX Access Control Lists, stored in the `system.posix_acl_access` and
+//! `system.posix_acl_default` extended attributes.
+
+use crate::{
+    functions::metadata,
+    xattr::{get_xattr, remove_xattr, set_xattr, SetXattrFlags},
+};
+use bitflags::bitflags;
+use std::{
+    fmt::Write as _,
+    io::{Error, ErrorKind, Result},
+    os::linux::fs::MetadataExt,
+    path::Path,
+};
+
+/// Version of the `posix_acl_xattr` format.
+const POSIX_ACL_XATTR_VERSION: u32 = 0x0002;
+/// Id of entries that are not named, i.e., not `User` or `Group`.
+const ACL_UNDEFINED_ID: u32 = u32::MAX;
+
+const ACL_USER_OBJ: u16 = 0x01;
+const ACL_USER: u16 = 0x02;
+const ACL_GROUP_OBJ: u16 = 0x04;
+const ACL_GROUP: u16 = 0x08;
+const ACL_MASK: u16 = 0x10;
+const ACL_OTHER: u16 = 0x20;
+
+/// Size of the header and of each entry in the `posix_acl_xattr` format.
+const HEADER_SIZE: usize = 4;
+const ENTRY_SIZE: usize = 8;
+
+bitflags! {
+    /// Permissions granted by an [`AclEntry`].
+    pub struct AclPerm: u16 {
+        const READ = 0x4;
+        const WRITE = 0x2;
+        const EXECUTE = 0x1;
+    }
+}
+
+impl AclPerm {
+    /// Returns the permissions in the `rwx` form.
+    fn to_text(self) -> String {
+        [
+            (AclPerm::READ, 'r'),
+            (AclPerm::WRITE, 'w'),
+            (AclPerm::EXECUTE, 'x'),
+        ]
+        .iter()
+        .map(|(perm, c)| if self.contains(*perm) { *c } else { '-' })
+        .collect()
+    }
+
+    /// Parses permissions in the `rwx` form, `-` and the order are ignored.
+    fn from_text(text: &str) -> Result<Self> {
+        let mut perm = AclPerm::empty();
+        for c in text.chars() {
+            perm |= match c {
+                'r' => AclPerm::READ,
+                'w' => AclPerm::WRITE,
+                'x' => AclPerm::EXECUTE,
+                '-' => AclPerm::empty(),
+                _ => return Err(invalid(format!("invalid ACL permissions '{}'", text))),
+            };
+        }
+
+        Ok(perm)
+    }
+}
+
+/// Whom an [`AclEntry`] applies to.
+///
+/// The ordering is the canonical order of entries in an ACL.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AclTag {
+    /// The owner of the file.
+    UserObj,
+    /// The user with the given UID.
+    User(u32),
+    /// The group of the file.
+    GroupObj,
+    /// The group with the given GID.
+    Group(u32),
+    /// The maximum permissions granted to `User`, `GroupObj` and `Group`
+    /// entries.
+    Mask,
+    /// Everyone else.
+    Other,
+}
+
+/// An entry of an [`Acl`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct AclEntry {
+    pub tag: AclTag,
+    pub perm: AclPerm,
+}
+
+impl AclEntry {
+    /// Creates a new entry.
+    #[inline]
+    pub fn new(tag: AclTag, perm: AclPerm) -> Self {
+        Self { tag, perm }
+    }
+}
+
+/// The kind of an ACL.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AclType {
+    /// The ACL used for access checks, `system.posix_acl_access`.
+    Access,
+    /// The ACL inherited by entries created in a directory,
+    /// `system.posix_acl_default`.
+    Default,
+}
+
+impl AclType {
+    pub(crate) fn xattr_name(self) -> &'static str {
+        match self {
+            AclType::Access => "system.posix_acl_access",
+            AclType::Default => "system.posix_acl_default",
+        }
+    }
+}
+
+/// A POSIX Access Control List.
+///
+/// Entries are kept in the canonical order, there is at most one entry per
+/// [`AclTag`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Acl {
+    entries: Vec<AclEntry>,
+}
+
+impl Acl {
+    /// Creates an empty ACL.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates the minimal ACL equivalent to the permission bits of `mode`.
+    pub fn from_mode(mode: u32) -> Self {
+        let perm = |shift: u32| AclPerm::from_bits_truncate(((mode >> shift) & 0o7) as u16);
+        Self {
+            entries: vec![
+                AclEntry::new(AclTag::UserObj, perm(6)),
+                AclEntry::new(AclTag::GroupObj, perm(3)),
+                AclEntry::new(AclTag::Other, perm(0)),
+            ],
+        }
+    }
+
+    /// Returns the entries in the canonical order.
+    #[inline]
+    pub fn entries(&self) -> &[AclEntry] {
+        &self.entries
+    }
+
+    /// Returns the permissions granted by the entry with `tag`.
+    pub fn get(&self, tag: AclTag) -> Option<AclPerm> {
+        self.entries
+            .iter()
+            .find(|entry| entry.tag == tag)
+            .map(|entry| entry.perm)
+    }
+
+    /// Adds an entry, replacing the existing entry with the same tag.
+    pub fn set(&mut self, tag: AclTag, perm: AclPerm) {
+        match self.entries.binary_search_by(|entry| entry.tag.cmp(&tag)) {
+            Ok(idx) => self.entries[idx].perm = perm,
+            Err(idx) => self.entries.insert(idx, AclEntry::new(tag, perm)),
+        }
+    }
+
+    /// Adds an entry, unless there is one with the same tag already, returns
+    /// whether it was added.
+    fn insert_new(&mut self, tag: AclTag, perm: AclPerm) -> bool {
+        match self.entries.binary_search_by(|entry| entry.tag.cmp(&tag)) {
+            Ok(_) => false,
+            Err(idx) => {
+                self.entries.insert(idx, AclEntry::new(tag, perm));
+                true
+            }
+        }
+    }
+
+    /// Removes the entry with `tag`, returning its permissions.
+    pub fn remove(&mut self, tag: AclTag) -> Option<AclPerm> {
+        let idx = self
+            .entries
+            .binary_search_by(|entry| entry.tag.cmp(&tag))
+            .ok()?;
+        Some(self.entries.remove(idx).perm)
+    }
+
+    /// Returns true if the ACL has no entries.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Parses an ACL in the kernel's `posix_acl_xattr` format.
+    ///
+    /// The ACL is rejected if two entries have the same [`AclTag`], as the
+    /// kernel does.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < HEADER_SIZE || !(bytes.len() - HEADER_SIZE).is_multiple_of(ENTRY_SIZE) {
+            return Err(invalid("invalid ACL size"));
+        }
+        let version = u32::from_le_bytes(bytes[..HEADER_SIZE].try_into().unwrap());
+        if version != POSIX_ACL_XATTR_VERSION {
+            return Err(invalid(format!("unsupported ACL version {}", version)));
+        }
+
+        let mut acl = Acl::new();
+        for entry in bytes[HEADER_SIZE..].chunks_exact(ENTRY_SIZE) {
+            let tag = u16::from_le_bytes([entry[0], entry[1]]);
+            let perm = u16::from_le_bytes([entry[2], entry[3]]);
+            let id = u32::from_le_bytes([entry[4], entry[5], entry[6], entry[7]]);
+
+            let tag = match tag {
+                ACL_USER_OBJ => AclTag::UserObj,
+                ACL_USER => AclTag::User(id),
+                ACL_GROUP_OBJ => AclTag::GroupObj,
+                ACL_GROUP => AclTag::Group(id),
+                ACL_MASK => AclTag::Mask,
+                ACL_OTHER => AclTag::Other,
+                _ => return Err(invalid(format!("invalid ACL tag {:#x}", tag))),
+            };
+            if !acl.insert_new(tag, AclPerm::from_bits_truncate(perm)) {
+                return Err(invalid(format!("duplicate ACL entry {:?}", tag)));
+            }
+        }
+
+        Ok(acl)
+    }
+
+    /// Serializes the ACL in the kernel's `posix_acl_xattr` format.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_SIZE + ENTRY_SIZE * self.entries.len());
+        bytes.extend_from_slice(&POSIX_ACL_XATTR_VERSION.to_le_bytes());
+
+        for entry in self.entries.iter() {
+            let (tag, id) = match entry.tag {
+                AclTag::UserObj => (ACL_USER_OBJ, ACL_UNDEFINED_ID),
+                AclTag::User(uid) => (ACL_USER, uid),
+                AclTag::GroupObj => (ACL_GROUP_OBJ, ACL_UNDEFINED_ID),
+                AclTag::Group(gid) => (ACL_GROUP, gid),
+                AclTag::Mask => (ACL_MASK, ACL_UNDEFINED_ID),
+                AclTag::Other => (ACL_OTHER, ACL_UNDEFINED_ID),
+            };
+            bytes.extend_from_slice(&tag.to_le_bytes());
+            bytes.extend_from_slice(&entry.perm.bits().to_le_bytes());
+            bytes.extend_from_slice(&id.to_le_bytes());
+        }
+
+        bytes
+    }
+
+    /// Parses an ACL in the text form printed by `getfacl -n`, e.g.,
+    /// `user::rw-`, `user:1000:r--`, `group::r--`, `mask::r--`, `other::---`.
+    ///
+    /// Entries are separated by newlines or commas, the short tags `u`, `g`,
+    /// `m` and `o` are accepted. Comments starting with `#` are ignored, as
+    /// are entries prefixed with `default:`, use [`Acl::default_from_text`]
+    /// to parse them. Like `setfacl(1)`, an entry given twice is rejected with
+    /// [`ErrorKind::InvalidInput`].
+    ///
+    /// Only numeric qualifiers are accepted: user and group names, as printed
+    /// by `getfacl` without `-n`, are not resolved and make this fail with
+    /// [`ErrorKind::InvalidData`].
+    pub fn from_text(text: &str) -> Result<Self> {
+        Self::parse_text(text, false)
+    }
+
+    /// Parses the entries prefixed with `default:` (or `d:`) in `text`, which
+    /// is in the form accepted by [`Acl::from_text`].
+    ///
+    /// As with [`Acl::from_text`], only the numeric qualifiers printed by
+    /// `getfacl -n` are accepted, not user and group names.
+    pub fn default_from_text(text: &str) -> Result<Self> {
+        Self::parse_text(text, true)
+    }
+
+    fn parse_text(text: &str, default: bool) -> Result<Self> {
+        let mut acl = Acl::new();
+
+        for entry in text
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or_default())
+            .flat_map(|line| line.split(','))
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+        {
+            let (is_default, entry) = match entry.split_once(':') {
+                Some(("default" | "d", rest)) => (true, rest),
+                _ => (false, entry),
+            };
+            if is_default != default {
+                continue;
+            }
+
+            let mut fields = entry.split(':');
+            let (tag, qualifier, perm) = match (fields.next(), fields.next(), fields.next()) {
+                (Some(tag), Some(qualifier), Some(perm)) if fields.next().is_none() => {
+                    (tag, qualifier, perm)
+                }
+                _ => return Err(invalid(format!("invalid ACL entry '{}'", entry))),
+            };
+            let id = || {
+                qualifier
+                    .parse::<u32>()
+                    .map_err(|_| invalid(format!("invalid ACL qualifier '{}'", qualifier)))
+            };
+
+            let tag = match (tag, qualifier.is_empty()) {
+                ("user" | "u", true) => AclTag::UserObj,
+                ("user" | "u", false) => AclTag::User(id()?),
+                ("group" | "g", true) => AclTag::GroupObj,
+                ("group" | "g", false) => AclTag::Group(id()?),
+                ("mask" | "m", true) => AclTag::Mask,
+                ("other" | "o", true) => AclTag::Other,
+                _ => return Err(invalid(format!("invalid ACL entry '{}'", entry))),
+            };
+            if !acl.insert_new(tag, AclPerm::from_text(perm)?) {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("duplicate ACL entry '{}'", entry),
+                ));
+            }
+        }
+
+        Ok(acl)
+    }
+
+    /// Returns the ACL in the text form printed by `getfacl -n`, one entry
+    /// per line.
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+
+        for entry in self.entries.iter() {
+            let _ = match entry.tag {
+                AclTag::UserObj => write!(text, "user::"),
+                AclTag::User(uid) => write!(text, "user:{}:", uid),
+                AclTag::GroupObj => write!(text, "group::"),
+                AclTag::Group(gid) => write!(text, "group:{}:", gid),
+                AclTag::Mask => write!(text, "mask::"),
+                AclTag::Other => write!(text, "other::"),
+            };
+            text.push_str(&entry.perm.to_text());
+            text.push('\n');
+        }
+
+        text
+    }
+}
+
+fn invalid<E: Into<Box<dyn std::error::Error + Send + Sync>>>(error: E) -> Error {
+    Error::new(ErrorKind::InvalidData, error)
+}
+
+/// Turns the result of reading an ACL xattr into an ACL.
+///
+/// A file without the access ACL xattr still has an ACL, the one equivalent
+/// to its mode, `mode` is only called in that case. A file without the default
+/// ACL xattr has an empty default ACL.
+pub(crate) fn acl_from_xattr<F>(value: Result<Vec<u8>>, acl_type: AclType, mode: F) -> Result<Acl>
+where
+    F: FnOnce() -> Result<u32>,
+{
+    match value {
+        Ok(bytes) => Acl::from_bytes(&bytes),
+        Err(e) if e.raw_os_error() == Some(libc::ENODATA) => match acl_type {
+            AclType::Access => mode().map(Acl::from_mode),
+            AclType::Default => Ok(Acl::new()),
+        },
+        Err(e) => Err(e),
+    }
+}
+
+/// Retrieves the ACL of type `acl_type` of a file, following symlinks.
+pub fn get_acl<P: AsRef<Path>>(path: P, acl_type: AclType) -> Result<Acl> {
+    let path = path.as_ref();
+    acl_from_xattr(get_xattr(path, acl_type.xattr_name()), acl_type, || {
+        metadata(path).map(|metadata| metadata.st_mode())
+    })
+}
+
+/// Sets the ACL of type `acl_type` of a file, following symlinks.
+///
+/// Setting an access ACL also updates the permission bits of the file. An
+/// ACL with named user or group entries needs a `Mask` entry. Setting an empty
+/// default ACL removes it.
+pub fn set_acl<P: AsRef<Path>>(path: P, acl_type: AclType, acl: &Acl) -> Result<()> {
+    let path = path.as_ref();
+    if acl_type == AclType::Default && acl.is_empty() {
+        return match remove_xattr(path, acl_type.xattr_name()) {
+            Err(e) if e.raw_os_error() == Some(libc::ENODATA) => Ok(()),
+            res => res,
+        };
+    }
+
+    set_xattr(
+        path,
+        acl_type.xattr_name(),
+        acl.to_bytes(),
+        SetXattrFlags::empty(),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{file::File, functions::set_permissions, permissions::Permissions, temp::TempDir};
+    use std::os::unix::fs::PermissionsExt;
+
+    fn named_acl() -> Acl {
+        let mut acl = Acl::from_mode(0o640);
+        acl.set(AclTag::User(1000), AclPerm::READ | AclPerm::WRITE);
+        acl.set(AclTag::Group(100), AclPerm::READ);
+        acl.set(AclTag::Mask, AclPerm::READ | AclPerm::WRITE);
+        acl
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let acl = named_acl();
+        let bytes = acl.to_bytes();
+
+        assert_eq!(bytes.len(), HEADER_SIZE + 6 * ENTRY_SIZE);
+        assert_eq!(&bytes[..4], &[2, 0, 0, 0]);
+        // user::rw-
+        assert_eq!(&bytes[4..12], &[1, 0, 6, 0, 0xff, 0xff, 0xff, 0xff]);
+        // user:1000:rw-
+        assert_eq!(&bytes[12..20], &[2, 0, 6, 0, 0xe8, 0x03, 0, 0]);
+        assert_eq!(Acl::from_bytes(&bytes).unwrap(), acl);
+
+        assert!(Acl::from_bytes(&bytes[..7]).is_err());
+        assert!(Acl::from_bytes(&[1, 0, 0, 0]).is_err());
+
+        // user:1000:rw- twice
+        let mut duplicate = bytes.clone();
+        duplicate.extend_from_slice(&bytes[12..20]);
+        assert_eq!(
+            Acl::from_bytes(&duplicate).unwrap_err().kind(),
+            ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn text() {
+        let text = "# file: foo\n\
+                    # owner: root\n\
+                    # group: root\n\
+                    user::rw-\n\
+                    user:1000:rw-\n\
+                    group::r--\n\
+                    group:100:r--\n\
+                    mask::rw-\n\
+                    other::---\n\
+                    default:user::rwx\n\
+                    default:group::r-x\n\
+                    default:other::---\n";
+        let acl = Acl::from_text(text).unwrap();
+        assert_eq!(acl, named_acl());
+        assert_eq!(
+            acl.to_text(),
+            "user::rw-\nuser:1000:rw-\ngroup::r--\ngroup:100:r--\nmask::rw-\nother::---\n"
+        );
+
+        let default = Acl::default_from_text(text).unwrap();
+        assert_eq!(default, Acl::from_mode(0o750));
+
+        assert_eq!(
+            Acl::from_text("u::rw,g::r,o::").unwrap(),
+            Acl::from_mode(0o640)
+        );
+        assert_eq!(
+            Acl::from_text("group::r-x\t#effective:r--")
+                .unwrap()
+                .get(AclTag::GroupObj),
+            Some(AclPerm::READ | AclPerm::EXECUTE)
+        );
+        assert_eq!(
+            Acl::from_text("user:root:rw-").unwrap_err().kind(),
+            ErrorKind::InvalidData
+        );
+        assert!(Acl::from_text("mask:1:rw-").is_err());
+        assert_eq!(
+            Acl::from_text("user:1000:r--,user:1000:rw-")
+                .unwrap_err()
+                .kind(),
+            ErrorKind::InvalidInput
+        );
+        assert_eq!(
+            Acl::default_from_text("d:u::rw-,default:user::r--")
+                .unwrap_err()
+                .kind(),
+            ErrorKind::InvalidInput
+        );
+        assert!(Acl::from_text("user::rwz").is_err());
+    }
+
+    #[test]
+    fn get_set_acl() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("file");
+        File::create(&path).unwrap();
+        set_permissions(&path, Permissions::from_mode(0o640)).unwrap();
+
+        assert_eq!(
+            get_acl(&path, AclType::Access).unwrap(),
+            Acl::from_mode(0o640)
+        );
+
+        let acl = named_acl();
+        set_acl(&path, AclType::Access, &acl).unwrap();
+        assert_eq!(get_acl(&path, AclType::Access).unwrap(), acl);
+        // the group class bits reflect the mask
+        assert_eq!(metadata(&path).unwrap().st_mode() & 0o777, 0o660);
+
+        let file = File::open(&path).unwrap();
+        assert_eq!(file.get_acl(AclType::Access).unwrap(), acl);
+    }
+
+    #[test]
+    fn default_acl() {
+        let dir = TempDir::new().unwrap();
+        assert!(get_acl(dir.path(), AclType::Default).unwrap().is_empty());
+
+        let default = Acl::from_mode(0o750);
+        set_acl(dir.path(), AclType::Default, &default).unwrap();
+        assert_eq!(get_acl(dir.path(), AclType::Default).unwrap(), default);
+
+        let path = dir.path().join("file");
+        File::create(&path).unwrap();
+        // inherited from the default ACL, without the execute bits as `creat`
+        // uses mode 0o666
+        assert_eq!(metadata(&path).unwrap().st_mode() & 0o777, 0o640);
+
+        let file = File::open(dir.path()).unwrap();
+        file.set_acl(AclType::Default, &Acl::new()).unwrap();
+        assert!(file.get_acl(AclType::Default).unwrap().is_empty());
+    }
+}
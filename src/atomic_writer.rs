@@ -0,0 +1,340 @@
+use crate::{
+    file::File,
+    functions::{fchown, metadata},
+    permissions::Permissions,
+    temp::{Builder, TempFile},
+};
+use std::{
+    ffi::OsString,
+    io::{Error, ErrorKind, Result, Write},
+    os::{linux::fs::MetadataExt, unix::fs::PermissionsExt},
+    path::{Path, PathBuf},
+};
+
+/// Options which can be used to configure how a file is atomically replaced.
+///
+/// This builder is used to create an [`AtomicWriter`], the `write_atomic`
+/// function is an alias for the default options.
+#[derive(Debug, Clone)]
+pub struct AtomicWriteOptions {
+    sync: bool,
+    no_replace: bool,
+}
+
+impl AtomicWriteOptions {
+    /// Creates a blank new set of options.
+    ///
+    /// By default, data is synced to disk and the target file will be replaced
+    /// if it exists.
+    pub fn new() -> Self {
+        Self {
+            sync: true,
+            no_replace: false,
+        }
+    }
+
+    /// Sets the option to sync the file and its parent directory to disk.
+    ///
+    /// Disabling this is faster, but the new contents may be lost (though they
+    /// will never be torn) on a system crash.
+    pub fn sync(&mut self, sync: bool) -> &mut Self {
+        self.sync = sync;
+        self
+    }
+
+    /// Sets the option to only create the target file, committing fails with
+    /// `ErrorKind::AlreadyExists` if the target file exists.
+    pub fn no_replace(&mut self, no_replace: bool) -> &mut Self {
+        self.no_replace = no_replace;
+        self
+    }
+
+    /// Creates an [`AtomicWriter`] that will replace `path` with the options
+    /// specified by self.
+    ///
+    /// A temporary file is created in the same directory as `path`. If `path`
+    /// exists, its permissions and ownership are copied to the temporary file.
+    ///
+    /// Copying the ownership is best effort: without the `CAP_CHOWN`
+    /// capability, a user replacing a file it can write but does not own
+    /// becomes its owner, and only keeps its group if it is a member of it.
+    ///
+    /// If `path` is a symbolic link, the link itself is replaced by a regular
+    /// file, the file it points to is left untouched. Pass the result of
+    /// [`canonicalize`](crate::canonicalize) to replace that file instead.
+    pub fn open<P: AsRef<Path>>(&self, path: P) -> Result<AtomicWriter> {
+        let path = path.as_ref();
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "path has no file name"))?;
+        let dir = match path.parent() {
+            Some(parent) if parent != Path::new("") => parent,
+            _ => Path::new("."),
+        };
+
+        let original = match metadata(path) {
+            Ok(metadata) => Some(metadata),
+            Err(e) if e.kind() == ErrorKind::NotFound => None,
+            Err(e) => return Err(e),
+        };
+
+        let mut prefix = OsString::from(".");
+        prefix.push(file_name);
+        prefix.push(".");
+        let temp = Builder::new()
+            .dir(dir)
+            .prefix(prefix)
+            .suffix(".tmp")
+            .mode(original.as_ref().map_or(0o666, |m| m.st_mode() & 0o777))
+            .tempfile()?;
+
+        if let Some(original) = original {
+            let temp_metadata = temp.as_file().metadata()?;
+            if temp_metadata.st_uid() != original.st_uid()
+                || temp_metadata.st_gid() != original.st_gid()
+            {
+                copy_ownership(&temp, original.st_uid(), original.st_gid())?;
+            }
+
+            // The mode used in creation is subject to umask, set it explicitly.
+            // This comes after `fchown`, which clears the set-user-ID and
+            // set-group-ID bits.
+            temp.as_file()
+                .set_permissions(Permissions::from_mode(original.st_mode() & 0o7777))?;
+        }
+
+        Ok(AtomicWriter {
+            temp,
+            path: path.to_owned(),
+            dir: dir.to_owned(),
+            sync: self.sync,
+            no_replace: self.no_replace,
+        })
+    }
+
+    /// Atomically replaces the contents of `path` with `contents`.
+    pub fn write<P: AsRef<Path>, C: AsRef<[u8]>>(&self, path: P, contents: C) -> Result<()> {
+        let mut writer = self.open(path)?;
+        writer.write_all(contents.as_ref())?;
+        writer.commit()
+    }
+}
+
+/// Gives `temp` the owner `uid` and the group `gid`, keeping the current
+/// owner or both when not permitted to change them.
+fn copy_ownership(temp: &TempFile, uid: u32, gid: u32) -> Result<()> {
+    let not_permitted = |res: &Result<()>| {
+        res.as_ref()
+            .is_err_and(|e| e.raw_os_error() == Some(libc::EPERM))
+    };
+
+    let res = fchown(temp, Some(uid), Some(gid));
+    if !not_permitted(&res) {
+        return res;
+    }
+    let res = fchown(temp, None, Some(gid));
+    if !not_permitted(&res) {
+        return res;
+    }
+    Ok(())
+}
+
+impl Default for AtomicWriteOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A writer that atomically replaces a file.
+///
+/// Data is written to a temporary file next to the target file, which will be
+/// renamed over the target file by [`commit`]. Readers either see the old
+/// contents or the new ones, never a torn file. If the writer is dropped
+/// without being committed, the temporary file is removed and the target file
+/// is left untouched.
+///
+/// [`commit`]: AtomicWriter::commit
+#[derive(Debug)]
+pub struct AtomicWriter {
+    temp: TempFile,
+    path: PathBuf,
+    dir: PathBuf,
+    sync: bool,
+    no_replace: bool,
+}
+
+impl AtomicWriter {
+    /// Creates a writer that will replace `path` with the default options.
+    #[inline]
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<AtomicWriter> {
+        AtomicWriteOptions::new().open(path)
+    }
+
+    /// Returns the path of the file that will be replaced.
+    #[inline]
+    pub fn path(&self) -> &Path {
+        self.path.as_path()
+    }
+
+    /// Makes the written data visible under the target path.
+    pub fn commit(self) -> Result<()> {
+        if self.sync {
+            self.temp.as_file().sync_all()?;
+        }
+
+        if self.no_replace {
+            self.temp.persist_noclobber(&self.path)?;
+        } else {
+            self.temp.persist(&self.path)?;
+        }
+
+        if self.sync {
+            // Make the rename itself durable.
+            File::open(&self.dir)?.sync_all()?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Write for AtomicWriter {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.temp.write(buf)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.temp.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        functions::{read, read_dir, set_permissions, symlink, symlink_metadata, write},
+        temp::TempDir,
+    };
+
+    #[test]
+    fn replace() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("config");
+        write(&path, b"old").unwrap();
+        set_permissions(&path, Permissions::from_mode(0o640)).unwrap();
+
+        AtomicWriteOptions::new().write(&path, b"new").unwrap();
+
+        assert_eq!(read(&path).unwrap(), b"new");
+        assert_eq!(metadata(&path).unwrap().st_mode() & 0o7777, 0o640);
+        assert_eq!(read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn create() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("config");
+
+        AtomicWriteOptions::new()
+            .sync(false)
+            .write(&path, b"new")
+            .unwrap();
+
+        assert_eq!(read(&path).unwrap(), b"new");
+    }
+
+    #[test]
+    fn no_replace() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("config");
+        write(&path, b"old").unwrap();
+
+        let err = AtomicWriteOptions::new()
+            .no_replace(true)
+            .write(&path, b"new")
+            .unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+        assert_eq!(read(&path).unwrap(), b"old");
+        assert_eq!(read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn drop_without_commit() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("config");
+        write(&path, b"old").unwrap();
+
+        let mut writer = AtomicWriter::new(&path).unwrap();
+        writer.write_all(b"new").unwrap();
+        drop(writer);
+
+        assert_eq!(read(&path).unwrap(), b"old");
+        assert_eq!(read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn replace_not_owned() {
+        // Giving the file away needs CAP_CHOWN.
+        if !nix::unistd::geteuid().is_root() {
+            return;
+        }
+
+        let dir = TempDir::new().unwrap();
+        set_permissions(dir.path(), Permissions::from_mode(0o777)).unwrap();
+        let path = dir.path().join("config");
+        write(&path, b"old").unwrap();
+        set_permissions(&path, Permissions::from_mode(0o666)).unwrap();
+        fchown(File::open(&path).unwrap(), Some(1234), Some(5678)).unwrap();
+
+        // Without `CAP_CHOWN`, which is dropped along with the filesystem UID
+        // of this thread only, the ownership can not be copied.
+        let thread_path = path.clone();
+        std::thread::spawn(move || {
+            nix::unistd::setfsuid(nix::unistd::Uid::from_raw(65534));
+            AtomicWriteOptions::new()
+                .write(&thread_path, b"new")
+                .unwrap();
+        })
+        .join()
+        .unwrap();
+
+        assert_eq!(read(&path).unwrap(), b"new");
+        assert_eq!(metadata(&path).unwrap().st_uid(), 65534);
+        assert_eq!(metadata(&path).unwrap().st_mode() & 0o7777, 0o666);
+    }
+
+    #[test]
+    fn replace_setuid_not_owned() {
+        // Giving the file away needs CAP_CHOWN.
+        if !nix::unistd::geteuid().is_root() {
+            return;
+        }
+
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("program");
+        write(&path, b"old").unwrap();
+        fchown(File::open(&path).unwrap(), Some(1234), Some(5678)).unwrap();
+        set_permissions(&path, Permissions::from_mode(0o6755)).unwrap();
+
+        AtomicWriteOptions::new().write(&path, b"new").unwrap();
+
+        let metadata = metadata(&path).unwrap();
+        assert_eq!((metadata.st_uid(), metadata.st_gid()), (1234, 5678));
+        assert_eq!(metadata.st_mode() & 0o7777, 0o6755);
+    }
+
+    #[test]
+    fn replace_symlink() {
+        let dir = TempDir::new().unwrap();
+        let target = dir.path().join("target");
+        let link = dir.path().join("link");
+        write(&target, b"old").unwrap();
+        symlink("target", &link).unwrap();
+
+        AtomicWriteOptions::new().write(&link, b"new").unwrap();
+
+        assert!(!symlink_metadata(&link).unwrap().is_symlink());
+        assert_eq!(read(&link).unwrap(), b"new");
+        assert_eq!(read(&target).unwrap(), b"old");
+    }
+}
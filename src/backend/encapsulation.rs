@@ -12,10 +12,11 @@ use std::{
     io::{Error, ErrorKind, Result},
     os::unix::{
         ffi::{OsStrExt, OsStringExt},
-        io::{AsFd, AsRawFd, FromRawFd, OwnedFd},
+        io::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd},
     },
     path::{Path, PathBuf},
-    ptr::null_mut,
+    ptr::{self, null_mut},
+    sync::atomic::{AtomicU8, Ordering},
 };
 
 bitflags! {
@@ -103,6 +104,57 @@ bitflags! {
     }
 }
 
+bitflags! {
+    /// Flags accepted by the `*at()` family syscalls.
+    pub(crate) struct AtFlags: libc::c_int {
+        /// Operate on the file referred to by the directory file descriptor
+        /// itself when the path is empty.
+        const AT_EMPTY_PATH = libc::AT_EMPTY_PATH;
+        /// Dereference the path if it is a symbolic link.
+        const AT_SYMLINK_FOLLOW = libc::AT_SYMLINK_FOLLOW;
+        /// Do not dereference the path if it is a symbolic link.
+        const AT_SYMLINK_NOFOLLOW = libc::AT_SYMLINK_NOFOLLOW;
+        /// `faccessat(2)` only: check with the effective user and group IDs
+        /// instead of the real ones.
+        const AT_EACCESS = libc::AT_EACCESS;
+        /// `statx(2)` only: force the attributes to be synchronized with the
+        /// server (for network filesystems).
+        const AT_STATX_FORCE_SYNC = libc::AT_STATX_FORCE_SYNC;
+        /// `statx(2)` only: use whatever is cached locally, do not
+        /// synchronize with the server (for network filesystems).
+        const AT_STATX_DONT_SYNC = libc::AT_STATX_DONT_SYNC;
+    }
+}
+
+bitflags! {
+    /// Flags of `setxattr(2)`.
+    pub(crate) struct XattrFlags: libc::c_int {
+        /// Fail if the attribute already exists.
+        const XATTR_CREATE = libc::XATTR_CREATE;
+        /// Fail if the attribute does not exist.
+        const XATTR_REPLACE = libc::XATTR_REPLACE;
+    }
+}
+
+bitflags! {
+    /// Flags of `renameat2(2)`.
+    pub(crate) struct RenameFlags: libc::c_uint {
+        /// Atomically exchange the old path and the new path.
+        const RENAME_EXCHANGE = libc::RENAME_EXCHANGE;
+        /// Don't overwrite the new path, fail with `EEXIST` if it exists.
+        const RENAME_NOREPLACE = libc::RENAME_NOREPLACE;
+        /// Create a "whiteout" object at the source of the rename.
+        const RENAME_WHITEOUT = libc::RENAME_WHITEOUT;
+    }
+}
+
+/// Maps an optional directory file descriptor to the `dirfd` argument of the
+/// `*at()` syscalls, `None` stands for the current working directory.
+#[inline]
+fn dirfd_or_cwd(dirfd: Option<BorrowedFd<'_>>) -> libc::c_int {
+    dirfd.map_or(libc::AT_FDCWD, |fd| fd.as_raw_fd())
+}
+
 /// Opens a file
 ///
 /// Note: `path` should not contain byte 0, or this function will panic.
@@ -185,6 +237,31 @@ pub(crate) fn link<P: AsRef<Path>, Q: AsRef<Path>>(old_path: P, new_path: Q) ->
     libc_like_syscall::link(old_path.as_ptr(), new_path.as_ptr()).map_err(Error::from_raw_os_error)
 }
 
+/// Makes a new name for a file, paths are resolved relative to the given
+/// directory file descriptors.
+///
+/// Note: `old_path` and `new_path` should not contain byte 0, or this function
+/// will panic.
+pub(crate) fn linkat<P: AsRef<Path>, Q: AsRef<Path>>(
+    old_dirfd: Option<BorrowedFd<'_>>,
+    old_path: P,
+    new_dirfd: Option<BorrowedFd<'_>>,
+    new_path: Q,
+    flags: AtFlags,
+) -> Result<()> {
+    let old_path = CString::new(old_path.as_ref().as_os_str().as_bytes()).unwrap();
+    let new_path = CString::new(new_path.as_ref().as_os_str().as_bytes()).unwrap();
+
+    libc_like_syscall::linkat(
+        dirfd_or_cwd(old_dirfd),
+        old_path.as_ptr(),
+        dirfd_or_cwd(new_dirfd),
+        new_path.as_ptr(),
+        flags.bits(),
+    )
+    .map_err(Error::from_raw_os_error)
+}
+
 /// Deletes a name or possibly a file it refers to
 ///
 /// Note: `path_name` should not contain byte 0, or this function will panic.
@@ -236,6 +313,32 @@ pub(crate) fn rename<P: AsRef<Path>, Q: AsRef<Path>>(old_path: P, new_path: Q) -
         .map_err(Error::from_raw_os_error)
 }
 
+/// Changes the name or location of a file, paths are resolved relative to the
+/// given directory file descriptors.
+///
+/// Note: `old_path` and `new_path` should not contain byte 0, or this function
+/// will panic.
+pub(crate) fn renameat2<P: AsRef<Path>, Q: AsRef<Path>>(
+    old_dirfd: Option<BorrowedFd<'_>>,
+    old_path: P,
+    new_dirfd: Option<BorrowedFd<'_>>,
+    new_path: Q,
+    flags: RenameFlags,
+) -> Result<()> {
+    let old_path = CString::new(old_path.as_ref().as_os_str().as_bytes()).unwrap();
+    let new_path = CString::new(new_path.as_ref().as_os_str().as_bytes()).unwrap();
+
+    libc_like_syscall::renameat2(
+        dirfd_or_cwd(old_dirfd),
+        old_path.as_ptr(),
+        dirfd_or_cwd(new_dirfd),
+        new_path.as_ptr(),
+        flags.bits(),
+    )
+    .map_err(Error::from_raw_os_error)
+}
+
+#[derive(Clone)]
 pub(crate) struct Stat(libc_like_syscall::Stat);
 
 impl Stat {
@@ -383,6 +486,26 @@ pub(crate) fn fstat<Fd: AsFd>(fd: Fd) -> Result<Stat> {
     }
 }
 
+/// Get file status relative to a directory file descriptor
+pub(crate) fn newfstatat<P: AsRef<Path>>(
+    dirfd: Option<BorrowedFd<'_>>,
+    path: P,
+    flags: AtFlags,
+) -> Result<Stat> {
+    let pathname = CString::new(path.as_ref().as_os_str().as_bytes()).unwrap();
+    let mut stat_buf = libc_like_syscall::Stat::default();
+
+    match libc_like_syscall::newfstatat(
+        dirfd_or_cwd(dirfd),
+        pathname.as_ptr(),
+        &mut stat_buf as *mut libc_like_syscall::Stat,
+        flags.bits(),
+    ) {
+        Ok(()) => Ok(Stat::from(stat_buf)),
+        Err(errno) => Err(Error::from_raw_os_error(errno)),
+    }
+}
+
 #[derive(Clone)]
 pub(crate) struct Statx(libc_like_syscall::Statx);
 
@@ -399,12 +522,24 @@ impl Statx {
         self.0.stx_blksize
     }
 
+    /// Returns a mask indicating which fields were filled in.
+    #[inline]
+    pub(crate) fn mask(&self) -> u32 {
+        self.0.stx_mask
+    }
+
     /// Returns extra file attribute indicators.
     #[inline]
     pub(crate) fn attributes(&self) -> u64 {
         self.0.stx_attributes
     }
 
+    /// Returns a mask indicating which bits in `attributes()` are supported.
+    #[inline]
+    pub(crate) fn attributes_mask(&self) -> u64 {
+        self.0.stx_attributes_mask
+    }
+
     /// Returns the number of hard links.
     #[inline]
     pub(crate) fn nlink(&self) -> u32 {
@@ -506,15 +641,25 @@ impl Statx {
     }
 }
 
-pub(crate) fn statx<P: AsRef<Path>>(path: P) -> Result<Statx> {
+/// Fields requested by [`statx`], [`lstatx`] and [`fstatx`].
+const STATX_DEFAULT_MASK: u32 = libc::STATX_ALL | libc::STATX_MNT_ID;
+
+/// Gets file status, only fields in `mask` are requested, check
+/// [`Statx::mask()`] for the fields that are actually filled in.
+pub(crate) fn statx_at<P: AsRef<Path>>(
+    dirfd: Option<BorrowedFd<'_>>,
+    path: P,
+    flags: AtFlags,
+    mask: u32,
+) -> Result<Statx> {
     let pathname = CString::new(path.as_ref().as_os_str().as_bytes()).unwrap();
     let mut statx_buf = libc_like_syscall::Statx::default();
 
     match libc_like_syscall::statx(
-        libc::AT_FDCWD,
+        dirfd_or_cwd(dirfd),
         pathname.as_ptr(),
-        0,
-        libc::STATX_ALL,
+        flags.bits(),
+        mask,
         &mut statx_buf as *mut libc_like_syscall::Statx,
     ) {
         Ok(()) => Ok(Statx::from(statx_buf)),
@@ -522,37 +667,385 @@ pub(crate) fn statx<P: AsRef<Path>>(path: P) -> Result<Statx> {
     }
 }
 
+pub(crate) fn statx<P: AsRef<Path>>(path: P) -> Result<Statx> {
+    statx_at(None, path, AtFlags::empty(), STATX_DEFAULT_MASK)
+}
+
 pub(crate) fn lstatx<P: AsRef<Path>>(path: P) -> Result<Statx> {
+    statx_at(None, path, AtFlags::AT_SYMLINK_NOFOLLOW, STATX_DEFAULT_MASK)
+}
+
+pub(crate) fn fstatx<Fd: AsFd>(fd: Fd) -> Result<Statx> {
+    statx_at(
+        Some(fd.as_fd()),
+        "",
+        AtFlags::AT_EMPTY_PATH,
+        STATX_DEFAULT_MASK,
+    )
+}
+
+#[derive(Clone)]
+pub(crate) struct Statfs(libc_like_syscall::Statfs);
+
+impl Statfs {
+    /// Returns the type of filesystem, a magic number.
+    #[inline]
+    pub(crate) fn fs_type(&self) -> libc::c_long {
+        self.0.f_type
+    }
+
+    /// Returns the optimal transfer block size.
+    #[inline]
+    pub(crate) fn bsize(&self) -> u64 {
+        self.0.f_bsize as u64
+    }
+
+    /// Returns the total data blocks in filesystem, in units of `frsize()`.
+    #[inline]
+    pub(crate) fn blocks(&self) -> u64 {
+        self.0.f_blocks
+    }
+
+    /// Returns the free blocks in filesystem.
+    #[inline]
+    pub(crate) fn bfree(&self) -> u64 {
+        self.0.f_bfree
+    }
+
+    /// Returns the free blocks available to unprivileged user.
+    #[inline]
+    pub(crate) fn bavail(&self) -> u64 {
+        self.0.f_bavail
+    }
+
+    /// Returns the total inodes in filesystem.
+    #[inline]
+    pub(crate) fn files(&self) -> u64 {
+        self.0.f_files
+    }
+
+    /// Returns the free inodes in filesystem.
+    #[inline]
+    pub(crate) fn ffree(&self) -> u64 {
+        self.0.f_ffree
+    }
+
+    /// Returns the filesystem ID.
+    #[inline]
+    pub(crate) fn fsid(&self) -> (i32, i32) {
+        (self.0.f_fsid[0], self.0.f_fsid[1])
+    }
+
+    /// Returns the maximum length of filenames.
+    #[inline]
+    pub(crate) fn namelen(&self) -> u64 {
+        self.0.f_namelen as u64
+    }
+
+    /// Returns the fragment size.
+    #[inline]
+    pub(crate) fn frsize(&self) -> u64 {
+        self.0.f_frsize as u64
+    }
+
+    /// Returns the mount flags of filesystem, `ST_*` constants.
+    #[inline]
+    pub(crate) fn flags(&self) -> u64 {
+        self.0.f_flags as u64
+    }
+}
+
+/// Get filesystem statistics
+pub(crate) fn statfs<P: AsRef<Path>>(path: P) -> Result<Statfs> {
     let pathname = CString::new(path.as_ref().as_os_str().as_bytes()).unwrap();
-    let mut statx_buf = libc_like_syscall::Statx::default();
+    let mut statfs_buf = libc_like_syscall::Statfs::default();
 
-    match libc_like_syscall::statx(
-        libc::AT_FDCWD,
+    match libc_like_syscall::statfs(
         pathname.as_ptr(),
-        libc::AT_SYMLINK_NOFOLLOW,
-        libc::STATX_ALL,
-        &mut statx_buf as *mut libc_like_syscall::Statx,
+        &mut statfs_buf as *mut libc_like_syscall::Statfs,
     ) {
-        Ok(()) => Ok(Statx::from(statx_buf)),
+        Ok(()) => Ok(Statfs(statfs_buf)),
         Err(errno) => Err(Error::from_raw_os_error(errno)),
     }
 }
 
-pub(crate) fn fstatx<Fd: AsFd>(fd: Fd) -> Result<Statx> {
-    let mut statx_buf = libc_like_syscall::Statx::default();
+/// Get filesystem statistics of the file referred to by `fd`
+pub(crate) fn fstatfs<Fd: AsFd>(fd: Fd) -> Result<Statfs> {
+    let mut statfs_buf = libc_like_syscall::Statfs::default();
 
-    match libc_like_syscall::statx(
+    match libc_like_syscall::fstatfs(
         fd.as_fd().as_raw_fd(),
-        "\0".as_ptr().cast(),
-        libc::AT_EMPTY_PATH,
-        libc::STATX_ALL,
-        &mut statx_buf as *mut libc_like_syscall::Statx,
+        &mut statfs_buf as *mut libc_like_syscall::Statfs,
     ) {
-        Ok(()) => Ok(Statx::from(statx_buf)),
+        Ok(()) => Ok(Statfs(statfs_buf)),
         Err(errno) => Err(Error::from_raw_os_error(errno)),
     }
 }
 
+/// `statx(2)` availability: not probed yet.
+const STATX_UNKNOWN: u8 = 0;
+/// `statx(2)` availability: available.
+const STATX_AVAILABLE: u8 = 1;
+/// `statx(2)` availability: unavailable, fall back to `newfstatat(2)`.
+const STATX_UNAVAILABLE: u8 = 2;
+
+/// Cached `statx(2)` availability.
+static STATX_STATE: AtomicU8 = AtomicU8::new(STATX_UNKNOWN);
+
+/// Returns whether `statx(2)` is available, given that a call to it failed
+/// with `errno`, the result is cached.
+///
+/// `ENOSYS` is returned by old kernels, and some seccomp sandboxes return
+/// `EPERM`, but a filesystem can return `EPERM` as well. Calling `statx(2)`
+/// with null pointers distinguishes them: it fails with `EFAULT` if the
+/// syscall is actually available.
+fn probe_statx(errno: libc::c_int) -> bool {
+    if errno != libc::ENOSYS && errno != libc::EPERM {
+        return true;
+    }
+
+    let available = matches!(
+        libc_like_syscall::statx(0, ptr::null(), 0, libc::STATX_ALL, ptr::null_mut()),
+        Err(libc::EFAULT)
+    );
+    STATX_STATE.store(
+        if available {
+            STATX_AVAILABLE
+        } else {
+            STATX_UNAVAILABLE
+        },
+        Ordering::Relaxed,
+    );
+
+    available
+}
+
+/// File status returned by either `statx(2)` or its fallback `newfstatat(2)`.
+#[derive(Clone)]
+pub(crate) enum FileStat {
+    Statx(Statx),
+    Stat(Stat),
+}
+
+impl FileStat {
+    /// Returns a mask indicating which fields were filled in, in the form of
+    /// `STATX_*` constants.
+    #[inline]
+    pub(crate) fn mask(&self) -> u32 {
+        match self {
+            FileStat::Statx(statx) => statx.mask(),
+            FileStat::Stat(_) => libc::STATX_BASIC_STATS,
+        }
+    }
+
+    /// Returns extra file attribute indicators, 0 if not supported.
+    #[inline]
+    pub(crate) fn attributes(&self) -> u64 {
+        match self {
+            FileStat::Statx(statx) => statx.attributes(),
+            FileStat::Stat(_) => 0,
+        }
+    }
+
+    /// Returns a mask indicating which bits in `attributes()` are supported.
+    #[inline]
+    pub(crate) fn attributes_mask(&self) -> u64 {
+        match self {
+            FileStat::Statx(statx) => statx.attributes_mask(),
+            FileStat::Stat(_) => 0,
+        }
+    }
+
+    /// Returns the block size for the file system I/O.
+    #[inline]
+    pub(crate) fn blksize(&self) -> u32 {
+        match self {
+            FileStat::Statx(statx) => statx.blksize(),
+            FileStat::Stat(stat) => stat.blksize() as u32,
+        }
+    }
+
+    /// Returns the number of hard links.
+    #[inline]
+    pub(crate) fn nlink(&self) -> u32 {
+        match self {
+            FileStat::Statx(statx) => statx.nlink(),
+            FileStat::Stat(stat) => stat.nlink() as u32,
+        }
+    }
+
+    /// Returns UID of the file owner.
+    #[inline]
+    pub(crate) fn uid(&self) -> u32 {
+        match self {
+            FileStat::Statx(statx) => statx.uid(),
+            FileStat::Stat(stat) => stat.uid(),
+        }
+    }
+
+    /// Returns GID of the file owner.
+    #[inline]
+    pub(crate) fn gid(&self) -> u32 {
+        match self {
+            FileStat::Statx(statx) => statx.gid(),
+            FileStat::Stat(stat) => stat.gid(),
+        }
+    }
+
+    /// Returns a number encoding file type and permission.
+    #[inline]
+    pub(crate) fn mode(&self) -> u32 {
+        match self {
+            FileStat::Statx(statx) => statx.mode(),
+            FileStat::Stat(stat) => stat.mode(),
+        }
+    }
+
+    /// Returns a [`FileType`] representing the file type.
+    #[inline]
+    pub(crate) fn file_type(&self) -> FileType {
+        match self {
+            FileStat::Statx(statx) => statx.file_type(),
+            FileStat::Stat(stat) => stat.file_type(),
+        }
+    }
+
+    /// Returns I-node number
+    #[inline]
+    pub(crate) fn ino(&self) -> u64 {
+        match self {
+            FileStat::Statx(statx) => statx.ino(),
+            FileStat::Stat(stat) => stat.ino(),
+        }
+    }
+
+    /// Returns file size (in bytes).
+    #[inline]
+    pub(crate) fn size(&self) -> u64 {
+        match self {
+            FileStat::Statx(statx) => statx.size(),
+            FileStat::Stat(stat) => stat.size() as u64,
+        }
+    }
+
+    /// Returns the number of blocks allocated for this file.
+    #[inline]
+    pub(crate) fn blocks(&self) -> u64 {
+        match self {
+            FileStat::Statx(statx) => statx.blocks(),
+            FileStat::Stat(stat) => stat.blocks() as u64,
+        }
+    }
+
+    /// Returns the time of last access.
+    #[inline]
+    pub(crate) fn atime(&self) -> (i64, u32) {
+        match self {
+            FileStat::Statx(statx) => statx.atime(),
+            FileStat::Stat(stat) => (stat.atime().0, stat.atime().1 as u32),
+        }
+    }
+
+    /// Returns the time of creation, `(0, 0)` if not supported.
+    #[inline]
+    pub(crate) fn btime(&self) -> (i64, u32) {
+        match self {
+            FileStat::Statx(statx) => statx.btime(),
+            FileStat::Stat(_) => (0, 0),
+        }
+    }
+
+    /// Returns the time of last status (metadata) change.
+    #[inline]
+    pub(crate) fn ctime(&self) -> (i64, u32) {
+        match self {
+            FileStat::Statx(statx) => statx.ctime(),
+            FileStat::Stat(stat) => (stat.ctime().0, stat.ctime().1 as u32),
+        }
+    }
+
+    /// Returns the time of last modification.
+    #[inline]
+    pub(crate) fn mtime(&self) -> (i64, u32) {
+        match self {
+            FileStat::Statx(statx) => statx.mtime(),
+            FileStat::Stat(stat) => (stat.mtime().0, stat.mtime().1 as u32),
+        }
+    }
+
+    /// Returns a tuple (major_rdev_id, minor_rdev_id).
+    #[inline]
+    pub(crate) fn rdev(&self) -> (u32, u32) {
+        match self {
+            FileStat::Statx(statx) => statx.rdev(),
+            FileStat::Stat(stat) => stat.rdev(),
+        }
+    }
+
+    /// Returns a tuple (major_dev_id, minor_dev_id).
+    #[inline]
+    pub(crate) fn dev(&self) -> (u32, u32) {
+        match self {
+            FileStat::Statx(statx) => statx.dev(),
+            FileStat::Stat(stat) => stat.dev(),
+        }
+    }
+
+    /// Returns mount id, 0 if not supported.
+    #[inline]
+    pub(crate) fn mnt_id(&self) -> u64 {
+        match self {
+            FileStat::Statx(statx) => statx.mnt_id(),
+            FileStat::Stat(_) => 0,
+        }
+    }
+}
+
+/// Gets file status with `statx(2)`, falls back to `newfstatat(2)` if
+/// `statx(2)` is unavailable.
+///
+/// `mask` is ignored by the fallback, which always fills in the basic stats.
+pub(crate) fn file_stat_at<P: AsRef<Path>>(
+    dirfd: Option<BorrowedFd<'_>>,
+    path: P,
+    flags: AtFlags,
+    mask: u32,
+) -> Result<FileStat> {
+    let path = path.as_ref();
+
+    if STATX_STATE.load(Ordering::Relaxed) != STATX_UNAVAILABLE {
+        match statx_at(dirfd, path, flags, mask) {
+            Ok(statx) => return Ok(FileStat::Statx(statx)),
+            Err(e) if probe_statx(e.raw_os_error().unwrap_or_default()) => return Err(e),
+            Err(_) => {}
+        }
+    }
+
+    // `newfstatat(2)` rejects the `AT_STATX_*` flags.
+    let flags = flags - (AtFlags::AT_STATX_FORCE_SYNC | AtFlags::AT_STATX_DONT_SYNC);
+    newfstatat(dirfd, path, flags).map(FileStat::Stat)
+}
+
+/// Gets file status, following symlinks.
+pub(crate) fn file_stat<P: AsRef<Path>>(path: P) -> Result<FileStat> {
+    file_stat_at(None, path, AtFlags::empty(), STATX_DEFAULT_MASK)
+}
+
+/// Gets file status, without following symlinks.
+pub(crate) fn file_lstat<P: AsRef<Path>>(path: P) -> Result<FileStat> {
+    file_stat_at(None, path, AtFlags::AT_SYMLINK_NOFOLLOW, STATX_DEFAULT_MASK)
+}
+
+/// Gets file status of the file referred to by `fd`.
+pub(crate) fn file_fstat<Fd: AsFd>(fd: Fd) -> Result<FileStat> {
+    file_stat_at(
+        Some(fd.as_fd()),
+        "",
+        AtFlags::AT_EMPTY_PATH,
+        STATX_DEFAULT_MASK,
+    )
+}
+
 /// Gets directory entries
 pub(crate) fn getdents64<Fd: AsFd>(fd: Fd, dirp: &mut [u8]) -> Result<usize> {
     libc_like_syscall::getdents64(
@@ -578,21 +1071,115 @@ struct LinuxDirent64 {
     // We don't have this in Rust.
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
-pub(crate) enum FileType {
-    RegularFile,
-    Directory,
-    Socket,
-    Fifo,
-    CharDev,
-    BlkDev,
-    Symlink,
+/// Create an inotify instance, the returned fd has `IN_CLOEXEC` and
+/// `IN_NONBLOCK` set.
+pub(crate) fn inotify_init1() -> Result<OwnedFd> {
+    libc_like_syscall::inotify_init1(libc::IN_CLOEXEC | libc::IN_NONBLOCK)
+        .map(|raw_fd| unsafe { OwnedFd::from_raw_fd(raw_fd) })
+        .map_err(Error::from_raw_os_error)
 }
 
-impl From<libc::c_uchar> for FileType {
-    fn from(file_type_char: libc::c_uchar) -> Self {
-        if file_type_char == libc::DT_REG {
-            FileType::RegularFile
+/// Add a watch for `path` to the inotify instance `fd`, or modify the existing
+/// one, and return its watch descriptor.
+///
+/// Note: `path` should not contain byte 0, or this function will panic.
+pub(crate) fn inotify_add_watch<Fd: AsFd, P: AsRef<Path>>(
+    fd: Fd,
+    path: P,
+    mask: u32,
+) -> Result<libc::c_int> {
+    let path = CString::new(path.as_ref().as_os_str().as_bytes()).unwrap();
+    libc_like_syscall::inotify_add_watch(fd.as_fd().as_raw_fd(), path.as_ptr(), mask)
+        .map_err(Error::from_raw_os_error)
+}
+
+/// Remove the watch `wd` from the inotify instance `fd`.
+pub(crate) fn inotify_rm_watch<Fd: AsFd>(fd: Fd, wd: libc::c_int) -> Result<()> {
+    libc_like_syscall::inotify_rm_watch(fd.as_fd().as_raw_fd(), wd)
+        .map_err(Error::from_raw_os_error)
+}
+
+#[repr(C)]
+struct InotifyEventHeader {
+    /// Watch descriptor, -1 for `IN_Q_OVERFLOW`
+    wd: libc::c_int,
+    /// Event mask
+    mask: u32,
+    /// Cookie associating the two events of a rename
+    cookie: u32,
+    /// Size of the name, including its padding NUL bytes
+    len: u32,
+    // The name is a flexible array member, like in `LinuxDirent64`.
+}
+
+/// An event read from an inotify instance.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct InotifyEvent {
+    pub(crate) wd: libc::c_int,
+    pub(crate) mask: u32,
+    pub(crate) cookie: u32,
+    /// Name of the file in the watched directory, if the event is about one.
+    pub(crate) name: Option<OsString>,
+}
+
+/// Parse the `struct inotify_event` records in `buf`, as read from an inotify
+/// instance.
+pub(crate) fn parse_inotify_events(buf: &[u8]) -> Vec<InotifyEvent> {
+    let header_len = std::mem::size_of::<InotifyEventHeader>();
+    let mut events = Vec::new();
+
+    let mut cursor = 0_usize;
+    while cursor + header_len <= buf.len() {
+        // The buffer of the caller may not be aligned.
+        let header =
+            unsafe { ptr::read_unaligned(buf.as_ptr().add(cursor) as *const InotifyEventHeader) };
+        let name_start = cursor + header_len;
+        let name_end = (name_start + header.len as usize).min(buf.len());
+        let name = &buf[name_start..name_end];
+        let name = &name[..name.iter().position(|&b| b == 0).unwrap_or(name.len())];
+
+        events.push(InotifyEvent {
+            wd: header.wd,
+            mask: header.mask,
+            cookie: header.cookie,
+            name: (!name.is_empty()).then(|| OsStr::from_bytes(name).to_owned()),
+        });
+
+        cursor = name_end;
+    }
+
+    events
+}
+
+/// Wait until `fd` is readable, for at most `timeout_ms` milliseconds, or
+/// forever if it is negative. Return false on timeout.
+pub(crate) fn poll_readable<Fd: AsFd>(fd: Fd, timeout_ms: libc::c_int) -> Result<bool> {
+    let mut pollfd = libc::pollfd {
+        fd: fd.as_fd().as_raw_fd(),
+        events: libc::POLLIN,
+        revents: 0,
+    };
+
+    libc_like_syscall::poll(&mut pollfd, 1, timeout_ms)
+        .map(|num_ready| num_ready > 0)
+        .map_err(Error::from_raw_os_error)
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum FileType {
+    RegularFile,
+    Directory,
+    Socket,
+    Fifo,
+    CharDev,
+    BlkDev,
+    Symlink,
+}
+
+impl From<libc::c_uchar> for FileType {
+    fn from(file_type_char: libc::c_uchar) -> Self {
+        if file_type_char == libc::DT_REG {
+            FileType::RegularFile
         } else if file_type_char == libc::DT_DIR {
             FileType::Directory
         } else if file_type_char == libc::DT_SOCK {
@@ -727,6 +1314,176 @@ impl Dir {
     }
 }
 
+/// Create a special or ordinary file, `mode` holds both the file type
+/// (`S_IF*`) and the permission bits.
+///
+/// Note: `path` should not contain byte 0, or this function will panic.
+pub(crate) fn mknodat<P: AsRef<Path>>(
+    dirfd: Option<BorrowedFd<'_>>,
+    path: P,
+    mode: libc::mode_t,
+    dev: libc::dev_t,
+) -> Result<()> {
+    let path = CString::new(path.as_ref().as_os_str().as_bytes()).unwrap();
+    libc_like_syscall::mknodat(dirfd_or_cwd(dirfd), path.as_ptr(), mode, dev)
+        .map_err(Error::from_raw_os_error)
+}
+
+/// Returns the supplementary group IDs of the calling process.
+pub(crate) fn getgroups() -> Result<Vec<libc::gid_t>> {
+    loop {
+        let len = libc_like_syscall::getgroups(0, null_mut()).map_err(Error::from_raw_os_error)?;
+        let mut groups = vec![0; len];
+        match libc_like_syscall::getgroups(len as libc::c_int, groups.as_mut_ptr()) {
+            Ok(len) => {
+                groups.truncate(len);
+                return Ok(groups);
+            }
+            // Groups have been added in between.
+            Err(libc::EINVAL) => continue,
+            Err(errno) => return Err(Error::from_raw_os_error(errno)),
+        }
+    }
+}
+
+/// Checks whether the calling process can access `path` with `mode`, which
+/// is `F_OK` or a mask of `R_OK`, `W_OK` and `X_OK`.
+///
+/// `faccessat2(2)` is used. On kernels older than Linux 5.8, or in sandboxes
+/// denying it, it falls back to `faccessat(2)`, emulating `flags`.
+pub(crate) fn faccessat<P: AsRef<Path>>(
+    dirfd: Option<BorrowedFd<'_>>,
+    path: P,
+    mode: libc::c_int,
+    flags: AtFlags,
+) -> Result<()> {
+    let pathname = CString::new(path.as_ref().as_os_str().as_bytes()).unwrap();
+
+    match libc_like_syscall::faccessat2(dirfd_or_cwd(dirfd), pathname.as_ptr(), mode, flags.bits())
+    {
+        Err(errno) if !faccessat2_available(errno) => {
+            faccessat_emulated(dirfd, path.as_ref(), &pathname, mode, flags)
+        }
+        res => res.map_err(Error::from_raw_os_error),
+    }
+}
+
+/// The fallback of [`faccessat`], which emulates `flags` with `faccessat(2)`
+/// and the file status.
+fn faccessat_emulated(
+    dirfd: Option<BorrowedFd<'_>>,
+    path: &Path,
+    pathname: &CStr,
+    mode: libc::c_int,
+    flags: AtFlags,
+) -> Result<()> {
+    let mut flags = flags;
+    if flags.contains(AtFlags::AT_SYMLINK_NOFOLLOW) {
+        let stat = file_stat_at(dirfd, path, flags, libc::STATX_TYPE)?;
+        // The permissions of a symbolic link are always `0o777`.
+        if matches!(stat.file_type(), FileType::Symlink) {
+            return check_read_only_mount(dirfd, pathname, true, mode);
+        }
+        flags.remove(AtFlags::AT_SYMLINK_NOFOLLOW);
+    }
+    if flags.contains(AtFlags::AT_EACCESS)
+        && libc_like_syscall::getuid() == libc_like_syscall::geteuid()
+        && libc_like_syscall::getgid() == libc_like_syscall::getegid()
+    {
+        flags.remove(AtFlags::AT_EACCESS);
+    }
+    if !flags.contains(AtFlags::AT_EACCESS) {
+        return libc_like_syscall::faccessat(dirfd_or_cwd(dirfd), pathname.as_ptr(), mode)
+            .map_err(Error::from_raw_os_error);
+    }
+
+    // Like glibc, check the permission bits against the effective IDs, which
+    // ignores ACLs and capabilities other than the ones of root.
+    let stat = file_stat_at(
+        dirfd,
+        path,
+        flags - AtFlags::AT_EACCESS,
+        libc::STATX_TYPE | libc::STATX_MODE | libc::STATX_UID | libc::STATX_GID,
+    )?;
+    let requested = mode as u32 & 0o7;
+    let uid = libc_like_syscall::geteuid();
+    let granted = if uid == 0 {
+        // Executing a file needs at least one execute bit, even for root, who
+        // can search any directory.
+        if requested & 0o1 == 0
+            || matches!(stat.file_type(), FileType::Directory)
+            || stat.mode() & 0o111 != 0
+        {
+            0o7
+        } else {
+            0o6
+        }
+    } else if stat.uid() == uid {
+        stat.mode() >> 6 & 0o7
+    } else if stat.gid() == libc_like_syscall::getegid() || getgroups()?.contains(&stat.gid()) {
+        stat.mode() >> 3 & 0o7
+    } else {
+        stat.mode() & 0o7
+    };
+
+    if requested & granted != requested {
+        return Err(Error::from_raw_os_error(libc::EACCES));
+    }
+    // Writing to device files, FIFOs and sockets does not write to the
+    // filesystem.
+    match stat.file_type() {
+        FileType::RegularFile | FileType::Directory | FileType::Symlink => {
+            check_read_only_mount(dirfd, pathname, false, mode)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Returns whether `faccessat2(2)` is available, given that a call to it
+/// failed with `errno`.
+///
+/// `ENOSYS` is returned by old kernels, and some seccomp sandboxes return
+/// `EPERM`, but writing to an immutable file is reported with `EPERM` as
+/// well. Like [`probe_statx`], a call with a null path tells them apart.
+fn faccessat2_available(errno: libc::c_int) -> bool {
+    if errno != libc::ENOSYS && errno != libc::EPERM {
+        return true;
+    }
+
+    matches!(
+        libc_like_syscall::faccessat2(libc::AT_FDCWD, ptr::null(), libc::F_OK, 0),
+        Err(libc::EFAULT)
+    )
+}
+
+/// Fails with `EROFS` if `mode` includes `W_OK` and `pathname` is on a
+/// read-only mount, as `faccessat(2)` does once the permission bits allow the
+/// access.
+fn check_read_only_mount(
+    dirfd: Option<BorrowedFd<'_>>,
+    pathname: &CStr,
+    nofollow: bool,
+    mode: libc::c_int,
+) -> Result<()> {
+    if mode & libc::W_OK == 0 {
+        return Ok(());
+    }
+
+    let mut flags = libc::O_PATH | libc::O_CLOEXEC;
+    if nofollow {
+        flags |= libc::O_NOFOLLOW;
+    }
+    let fd = libc_like_syscall::openat(dirfd_or_cwd(dirfd), pathname.as_ptr(), flags, 0)
+        .map(|raw_fd| unsafe { OwnedFd::from_raw_fd(raw_fd) })
+        .map_err(Error::from_raw_os_error)?;
+
+    if fstatfs(&fd)?.flags() & libc::ST_RDONLY != 0 {
+        Err(Error::from_raw_os_error(libc::EROFS))
+    } else {
+        Ok(())
+    }
+}
+
 /// Change Root Directory.
 ///
 /// Note: `path_name` should not contain byte 0, or this function will panic.
@@ -735,6 +1492,264 @@ pub(crate) fn chroot<P: AsRef<Path>>(path: P) -> Result<()> {
     libc_like_syscall::chroot(path.as_ptr()).map_err(Error::from_raw_os_error)
 }
 
+/// Mount a filesystem with the legacy `mount(2)`.
+///
+/// Note: none of the arguments should contain byte 0, or this function will panic.
+pub(crate) fn mount<P: AsRef<Path>>(
+    source: Option<&OsStr>,
+    target: P,
+    fstype: Option<&OsStr>,
+    flags: libc::c_ulong,
+    data: Option<&OsStr>,
+) -> Result<()> {
+    let to_cstring = |s: &OsStr| CString::new(s.as_bytes()).unwrap();
+    let source = source.map(to_cstring);
+    let target = to_cstring(target.as_ref().as_os_str());
+    let fstype = fstype.map(to_cstring);
+    let data = data.map(to_cstring);
+
+    libc_like_syscall::mount(
+        source.as_ref().map_or(ptr::null(), |s| s.as_ptr()),
+        target.as_ptr(),
+        fstype.as_ref().map_or(ptr::null(), |s| s.as_ptr()),
+        flags,
+        data.as_ref().map_or(ptr::null(), |s| s.as_ptr().cast()),
+    )
+    .map_err(Error::from_raw_os_error)
+}
+
+/// Unmount the filesystem mounted on `target`.
+///
+/// Note: `target` should not contain byte 0, or this function will panic.
+pub(crate) fn umount2<P: AsRef<Path>>(target: P, flags: libc::c_int) -> Result<()> {
+    let target = CString::new(target.as_ref().as_os_str().as_bytes()).unwrap();
+    libc_like_syscall::umount2(target.as_ptr(), flags).map_err(Error::from_raw_os_error)
+}
+
+/// `FSOPEN_CLOEXEC` from `<linux/mount.h>`.
+const FSOPEN_CLOEXEC: libc::c_uint = 0x1;
+/// `FSMOUNT_CLOEXEC` from `<linux/mount.h>`.
+const FSMOUNT_CLOEXEC: libc::c_uint = 0x1;
+
+/// Create a filesystem context for the filesystem type `fs_name`, the
+/// returned fd has `FSOPEN_CLOEXEC` set.
+///
+/// Note: `fs_name` should not contain byte 0, or this function will panic.
+pub(crate) fn fsopen(fs_name: &OsStr) -> Result<OwnedFd> {
+    let fs_name = CString::new(fs_name.as_bytes()).unwrap();
+    libc_like_syscall::fsopen(fs_name.as_ptr(), FSOPEN_CLOEXEC)
+        .map(|raw_fd| unsafe { OwnedFd::from_raw_fd(raw_fd) })
+        .map_err(Error::from_raw_os_error)
+}
+
+/// Command of `fsconfig(2)`, with its arguments.
+pub(crate) enum FsconfigCmd<'a> {
+    SetFlag(&'a OsStr),
+    SetString(&'a OsStr, &'a OsStr),
+    SetBinary(&'a OsStr, &'a [u8]),
+    SetPath(&'a OsStr, &'a Path),
+    SetFd(&'a OsStr, BorrowedFd<'a>),
+    Create,
+}
+
+/// Configure the filesystem context `fd`.
+///
+/// Note: the keys, string values and paths should not contain byte 0, or this
+/// function will panic.
+pub(crate) fn fsconfig<Fd: AsFd>(fd: Fd, cmd: FsconfigCmd<'_>) -> Result<()> {
+    // Values of `enum fsconfig_command` from `<linux/mount.h>`.
+    const FSCONFIG_SET_FLAG: libc::c_uint = 0;
+    const FSCONFIG_SET_STRING: libc::c_uint = 1;
+    const FSCONFIG_SET_BINARY: libc::c_uint = 2;
+    const FSCONFIG_SET_PATH: libc::c_uint = 3;
+    const FSCONFIG_SET_FD: libc::c_uint = 5;
+    const FSCONFIG_CMD_CREATE: libc::c_uint = 6;
+
+    let to_cstring = |s: &OsStr| CString::new(s.as_bytes()).unwrap();
+    let raw_fd = fd.as_fd().as_raw_fd();
+    let (cmd, key, value, aux) = match cmd {
+        FsconfigCmd::SetFlag(key) => (FSCONFIG_SET_FLAG, Some(to_cstring(key)), None, 0),
+        FsconfigCmd::SetString(key, value) => (
+            FSCONFIG_SET_STRING,
+            Some(to_cstring(key)),
+            Some(to_cstring(value).into_bytes_with_nul()),
+            0,
+        ),
+        FsconfigCmd::SetBinary(key, value) => (
+            FSCONFIG_SET_BINARY,
+            Some(to_cstring(key)),
+            Some(value.to_vec()),
+            value.len() as libc::c_int,
+        ),
+        FsconfigCmd::SetPath(key, path) => (
+            FSCONFIG_SET_PATH,
+            Some(to_cstring(key)),
+            Some(to_cstring(path.as_os_str()).into_bytes_with_nul()),
+            libc::AT_FDCWD,
+        ),
+        FsconfigCmd::SetFd(key, fd) => {
+            (FSCONFIG_SET_FD, Some(to_cstring(key)), None, fd.as_raw_fd())
+        }
+        FsconfigCmd::Create => (FSCONFIG_CMD_CREATE, None, None, 0),
+    };
+
+    libc_like_syscall::fsconfig(
+        raw_fd,
+        cmd,
+        key.as_ref().map_or(ptr::null(), |key| key.as_ptr()),
+        value
+            .as_ref()
+            .map_or(ptr::null(), |value| value.as_ptr().cast()),
+        aux,
+    )
+    .map_err(Error::from_raw_os_error)
+}
+
+/// Create a detached mount from the filesystem context `fd`, the returned fd
+/// has `FSMOUNT_CLOEXEC` set.
+pub(crate) fn fsmount<Fd: AsFd>(fd: Fd, attr_flags: libc::c_uint) -> Result<OwnedFd> {
+    libc_like_syscall::fsmount(fd.as_fd().as_raw_fd(), FSMOUNT_CLOEXEC, attr_flags)
+        .map(|raw_fd| unsafe { OwnedFd::from_raw_fd(raw_fd) })
+        .map_err(Error::from_raw_os_error)
+}
+
+/// Move a mount from one place to another.
+///
+/// Note: `from_path` and `to_path` should not contain byte 0, or this function
+/// will panic.
+pub(crate) fn move_mount<P: AsRef<Path>, Q: AsRef<Path>>(
+    from_dirfd: Option<BorrowedFd<'_>>,
+    from_path: P,
+    to_dirfd: Option<BorrowedFd<'_>>,
+    to_path: Q,
+    flags: libc::c_uint,
+) -> Result<()> {
+    let from_path = CString::new(from_path.as_ref().as_os_str().as_bytes()).unwrap();
+    let to_path = CString::new(to_path.as_ref().as_os_str().as_bytes()).unwrap();
+
+    libc_like_syscall::move_mount(
+        dirfd_or_cwd(from_dirfd),
+        from_path.as_ptr(),
+        dirfd_or_cwd(to_dirfd),
+        to_path.as_ptr(),
+        flags,
+    )
+    .map_err(Error::from_raw_os_error)
+}
+
+/// Open the mount at `path`, or clone it as a detached bind mount with
+/// `OPEN_TREE_CLONE`. `OPEN_TREE_CLOEXEC` is always set.
+///
+/// Note: `path` should not contain byte 0, or this function will panic.
+pub(crate) fn open_tree<P: AsRef<Path>>(
+    dirfd: Option<BorrowedFd<'_>>,
+    path: P,
+    flags: libc::c_uint,
+) -> Result<OwnedFd> {
+    let path = CString::new(path.as_ref().as_os_str().as_bytes()).unwrap();
+
+    libc_like_syscall::open_tree(
+        dirfd_or_cwd(dirfd),
+        path.as_ptr(),
+        flags | libc::OPEN_TREE_CLOEXEC,
+    )
+    .map(|raw_fd| unsafe { OwnedFd::from_raw_fd(raw_fd) })
+    .map_err(Error::from_raw_os_error)
+}
+
+/// Change the properties of the mount at `path`, `userns_fd` is only used with
+/// `MOUNT_ATTR_IDMAP`.
+///
+/// Note: `path` should not contain byte 0, or this function will panic.
+pub(crate) fn mount_setattr<P: AsRef<Path>>(
+    dirfd: Option<BorrowedFd<'_>>,
+    path: P,
+    flags: libc::c_uint,
+    attr_set: u64,
+    attr_clr: u64,
+    propagation: u64,
+    userns_fd: Option<BorrowedFd<'_>>,
+) -> Result<()> {
+    let path = CString::new(path.as_ref().as_os_str().as_bytes()).unwrap();
+    let mut attr = libc_like_syscall::MountAttr {
+        attr_set,
+        attr_clr,
+        propagation,
+        userns_fd: userns_fd.map_or(0, |fd| fd.as_raw_fd() as u64),
+    };
+
+    libc_like_syscall::mount_setattr(
+        dirfd_or_cwd(dirfd),
+        path.as_ptr(),
+        flags,
+        &mut attr,
+        std::mem::size_of::<libc_like_syscall::MountAttr>(),
+    )
+    .map_err(Error::from_raw_os_error)
+}
+
+/// Set the `no_new_privs` attribute of the calling thread, which is inherited by
+/// its children and can not be unset.
+pub(crate) fn set_no_new_privs() -> Result<()> {
+    libc_like_syscall::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0)
+        .map(drop)
+        .map_err(Error::from_raw_os_error)
+}
+
+/// `LANDLOCK_CREATE_RULESET_VERSION` from `<linux/landlock.h>`.
+const LANDLOCK_CREATE_RULESET_VERSION: u32 = 1 << 0;
+/// `LANDLOCK_RULE_PATH_BENEATH` from `<linux/landlock.h>`.
+const LANDLOCK_RULE_PATH_BENEATH: libc::c_int = 1;
+
+/// Get the highest Landlock ABI version supported by the kernel.
+pub(crate) fn landlock_abi_version() -> Result<u32> {
+    libc_like_syscall::landlock_create_ruleset(ptr::null(), 0, LANDLOCK_CREATE_RULESET_VERSION)
+        .map(|version| version as u32)
+        .map_err(Error::from_raw_os_error)
+}
+
+/// Create a Landlock ruleset handling the filesystem accesses
+/// `handled_access_fs`, the returned fd has `O_CLOEXEC` set.
+pub(crate) fn landlock_create_ruleset(handled_access_fs: u64) -> Result<OwnedFd> {
+    let attr = libc_like_syscall::LandlockRulesetAttr { handled_access_fs };
+
+    libc_like_syscall::landlock_create_ruleset(
+        &attr,
+        std::mem::size_of::<libc_like_syscall::LandlockRulesetAttr>(),
+        0,
+    )
+    .map(|raw_fd| unsafe { OwnedFd::from_raw_fd(raw_fd) })
+    .map_err(Error::from_raw_os_error)
+}
+
+/// Allow the accesses `allowed_access` beneath the file referred to by
+/// `parent` in the Landlock ruleset `ruleset`.
+pub(crate) fn landlock_add_path_beneath_rule<Fd1: AsFd, Fd2: AsFd>(
+    ruleset: Fd1,
+    parent: Fd2,
+    allowed_access: u64,
+) -> Result<()> {
+    let attr = libc_like_syscall::LandlockPathBeneathAttr {
+        allowed_access,
+        parent_fd: parent.as_fd().as_raw_fd(),
+    };
+
+    libc_like_syscall::landlock_add_rule(
+        ruleset.as_fd().as_raw_fd(),
+        LANDLOCK_RULE_PATH_BENEATH,
+        &attr as *const libc_like_syscall::LandlockPathBeneathAttr as *const libc::c_void,
+        0,
+    )
+    .map_err(Error::from_raw_os_error)
+}
+
+/// Enforce the Landlock ruleset `ruleset` on the calling thread and its
+/// future children.
+pub(crate) fn landlock_restrict_self<Fd: AsFd>(ruleset: Fd) -> Result<()> {
+    libc_like_syscall::landlock_restrict_self(ruleset.as_fd().as_raw_fd(), 0)
+        .map_err(Error::from_raw_os_error)
+}
+
 /// `whence` argument of `lseek64(2)`
 #[repr(i32)]
 pub(crate) enum Whence {
@@ -770,110 +1785,437 @@ pub(crate) fn readlink<P: AsRef<Path>>(pathname: P) -> Result<PathBuf> {
     Ok(PathBuf::from(OsString::from_vec(buf)))
 }
 
-/// A simplified version of `fcntl(2)`, supports only two arguments.
-//
-// Currently, this will be only used in the `Debug` implementation for `File`,
-// so this simple wrapper would suffice.
-pub(crate) use libc_like_syscall::fcntl_with_two_args;
+/// A simplified version of `fcntl(2)`, supports only two arguments.
+//
+// Currently, this will be only used in the `Debug` implementation for `File`,
+// so this simple wrapper would suffice.
+pub(crate) use libc_like_syscall::fcntl_with_two_args;
+
+/// Transfers  ("flushes") all modified in-core data of (i.e., modified buffer
+/// cache pages for) the file referred to by the file descriptor fd to the
+/// disk device
+pub(crate) fn fsync<Fd: AsFd>(fd: Fd) -> Result<()> {
+    libc_like_syscall::fsync(fd.as_fd().as_raw_fd()).map_err(Error::from_raw_os_error)
+}
+/// `fdatasync()` is similar to [`fsync()`], but does not flush modified metadata
+/// unless that metadata  is needed in order to allow a subsequent data retrieval
+/// to be correctly handled
+pub(crate) fn fdatasync<Fd: AsFd>(fd: Fd) -> Result<()> {
+    libc_like_syscall::fdatasync(fd.as_fd().as_raw_fd()).map_err(Error::from_raw_os_error)
+}
+
+/// Like [`fsync()`], but synchronizes the whole filesystem containing the file
+/// referred to by `fd`
+pub(crate) fn syncfs<Fd: AsFd>(fd: Fd) -> Result<()> {
+    libc_like_syscall::syncfs(fd.as_fd().as_raw_fd()).map_err(Error::from_raw_os_error)
+}
+
+bitflags! {
+    /// Flags of `sync_file_range(2)`.
+    pub(crate) struct SyncFileRangeFlags: libc::c_uint {
+        /// Wait upon write-out of all pages in the range that have already
+        /// been submitted to the device driver for write-out before performing
+        /// any write.
+        const SYNC_FILE_RANGE_WAIT_BEFORE = libc::SYNC_FILE_RANGE_WAIT_BEFORE;
+        /// Initiate write-out of all dirty pages in the range which are not
+        /// presently submitted write-out.
+        const SYNC_FILE_RANGE_WRITE = libc::SYNC_FILE_RANGE_WRITE;
+        /// Wait upon write-out of all pages in the range after performing any
+        /// write.
+        const SYNC_FILE_RANGE_WAIT_AFTER = libc::SYNC_FILE_RANGE_WAIT_AFTER;
+    }
+}
+
+/// Syncs a file segment with disk
+///
+/// `nbytes` set to 0 means all bytes from `offset` through to the end of the
+/// file.
+pub(crate) fn sync_file_range<Fd: AsFd>(
+    fd: Fd,
+    offset: u64,
+    nbytes: u64,
+    flags: SyncFileRangeFlags,
+) -> Result<()> {
+    let offset = offset
+        .try_into()
+        .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+    let nbytes = nbytes
+        .try_into()
+        .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+
+    libc_like_syscall::sync_file_range(fd.as_fd().as_raw_fd(), offset, nbytes, flags.bits())
+        .map_err(Error::from_raw_os_error)
+}
+
+/// Commits filesystem caches to disk
+pub(crate) use libc_like_syscall::sync;
+
+/// Truncate a file to a specified length
+///
+/// If the file previously was larger than this size, the extra data is lost.
+/// If the file previously was shorter, it is extended, and the extended part
+/// reads as null bytes ('\0').
+pub(crate) fn ftruncate<Fd: AsFd>(fd: Fd, length: u64) -> Result<()> {
+    let length = length
+        .try_into()
+        .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+
+    libc_like_syscall::ftruncate(fd.as_fd().as_raw_fd(), length).map_err(Error::from_raw_os_error)
+}
+
+/// Changes permissions of a file
+pub(crate) fn chmod<P: AsRef<Path>>(pathname: P, mode: Mode) -> Result<()> {
+    let pathname = CString::new(pathname.as_ref().as_os_str().as_bytes()).unwrap();
+    let mode = mode.bits();
+    libc_like_syscall::chmod(pathname.as_ptr(), mode).map_err(Error::from_raw_os_error)
+}
+
+/// Changes permissions of a file
+pub(crate) fn fchmod<Fd: AsFd>(fd: Fd, mode: Mode) -> Result<()> {
+    let mode = mode.bits();
+    libc_like_syscall::fchmod(fd.as_fd().as_raw_fd(), mode).map_err(Error::from_raw_os_error)
+}
+
+/// Sets the file mode creation mask of the process and returns the previous
+/// one.
+pub(crate) fn umask(mask: Mode) -> Mode {
+    Mode::from_bits_truncate(libc_like_syscall::umask(mask.bits()))
+}
+
+/// Time operation used in [`futimens()`] and [`utimensat()`].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) enum TimestampSpec {
+    #[default]
+    Omit,
+    SetToNow,
+    Set(SystemTime),
+}
+
+impl From<&TimestampSpec> for libc_like_syscall::Timespec {
+    fn from(value: &TimestampSpec) -> Self {
+        let mut default = libc_like_syscall::Timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        };
+
+        match value {
+            TimestampSpec::Omit => default.tv_nsec = libc::UTIME_OMIT,
+            TimestampSpec::SetToNow => default.tv_nsec = libc::UTIME_NOW,
+            TimestampSpec::Set(time) => {
+                default.tv_sec = time.sec;
+                default.tv_nsec = time.nsec;
+            }
+        }
+        default
+    }
+}
+
+/// `clockid` argument of `clock_gettime(2)`
+#[repr(i32)]
+pub(crate) enum ClockId {
+    Realtime = libc::CLOCK_REALTIME,
+    Monotonic = libc::CLOCK_MONOTONIC,
+}
+
+/// Retrieves the time of the specified clock, returns a tuple (sec, nsec).
+//
+// This is a raw syscall, the vDSO is not used.
+pub(crate) fn clock_gettime(clockid: ClockId) -> Result<(libc::time_t, libc::c_long)> {
+    let mut tp = libc_like_syscall::Timespec::default();
+
+    libc_like_syscall::clock_gettime(
+        clockid as libc::clockid_t,
+        &mut tp as *mut libc_like_syscall::Timespec,
+    )
+    .map_err(Error::from_raw_os_error)?;
+
+    Ok((tp.tv_sec, tp.tv_nsec))
+}
+
+/// Changes file timestamps with nanosecond precision
+//
+// This syscall is implemented on the top of `utimensat(2)`, for more information, see
+// https://man7.org/linux/man-pages/man2/utimensat.2.html
+pub(crate) fn futimens<Fd: AsFd>(
+    fd: Fd,
+    atime: &TimestampSpec,
+    mtime: &TimestampSpec,
+) -> Result<()> {
+    // atime and mtime
+    let times = [atime.into(), mtime.into()];
+
+    libc_like_syscall::utimensat(
+        fd.as_fd().as_raw_fd(),
+        std::ptr::null(),
+        &times as *const libc_like_syscall::Timespec,
+        0,
+    )
+    .map_err(Error::from_raw_os_error)
+}
+
+/// Changes timestamps of a file with nanosecond precision
+///
+/// `flags` can contain `AT_SYMLINK_NOFOLLOW` to update the timestamps of a
+/// symbolic link itself.
+///
+/// Note: `pathname` should not contain byte 0, or this function will panic.
+pub(crate) fn utimensat<P: AsRef<Path>>(
+    dirfd: Option<BorrowedFd<'_>>,
+    pathname: P,
+    atime: &TimestampSpec,
+    mtime: &TimestampSpec,
+    flags: AtFlags,
+) -> Result<()> {
+    let pathname = CString::new(pathname.as_ref().as_os_str().as_bytes()).unwrap();
+    // atime and mtime
+    let times = [atime.into(), mtime.into()];
+
+    libc_like_syscall::utimensat(
+        dirfd_or_cwd(dirfd),
+        pathname.as_ptr(),
+        &times as *const libc_like_syscall::Timespec,
+        flags.bits(),
+    )
+    .map_err(Error::from_raw_os_error)
+}
+
+/// Change ownership of a file
+pub(crate) fn chown<P: AsRef<Path>>(
+    pathname: P,
+    owner: Option<u32>,
+    group: Option<u32>,
+) -> Result<()> {
+    let pathname = CString::new(pathname.as_ref().as_os_str().as_bytes()).unwrap();
+    // libc::uid_t and libc::gid_t are unsigned number, -1 = MAX
+    let owner = owner.unwrap_or(u32::MAX);
+    let group = group.unwrap_or(u32::MAX);
+
+    libc_like_syscall::chown(pathname.as_ptr(), owner, group).map_err(Error::from_raw_os_error)
+}
+
+/// Get an extended attribute value
+///
+/// If the buffer is empty, the size it needs is returned.
+pub(crate) fn getxattr<P: AsRef<Path>, N: AsRef<OsStr>>(
+    path: P,
+    name: N,
+    value: &mut [u8],
+) -> Result<usize> {
+    let pathname = CString::new(path.as_ref().as_os_str().as_bytes()).unwrap();
+    let name = CString::new(name.as_ref().as_bytes()).unwrap();
+
+    libc_like_syscall::getxattr(
+        pathname.as_ptr(),
+        name.as_ptr(),
+        value.as_mut_ptr().cast(),
+        value.len(),
+    )
+    .map_err(Error::from_raw_os_error)
+}
+
+/// Get an extended attribute value
+///
+/// If `path` refers to a symlink, the attribute of the link **itself** is
+/// retrieved.
+///
+/// If the buffer is empty, the size it needs is returned.
+pub(crate) fn lgetxattr<P: AsRef<Path>, N: AsRef<OsStr>>(
+    path: P,
+    name: N,
+    value: &mut [u8],
+) -> Result<usize> {
+    let pathname = CString::new(path.as_ref().as_os_str().as_bytes()).unwrap();
+    let name = CString::new(name.as_ref().as_bytes()).unwrap();
+
+    libc_like_syscall::lgetxattr(
+        pathname.as_ptr(),
+        name.as_ptr(),
+        value.as_mut_ptr().cast(),
+        value.len(),
+    )
+    .map_err(Error::from_raw_os_error)
+}
+
+/// Get an extended attribute value of the file referred to by `fd`
+///
+/// If the buffer is empty, the size it needs is returned.
+pub(crate) fn fgetxattr<Fd: AsFd, N: AsRef<OsStr>>(
+    fd: Fd,
+    name: N,
+    value: &mut [u8],
+) -> Result<usize> {
+    let name = CString::new(name.as_ref().as_bytes()).unwrap();
+
+    libc_like_syscall::fgetxattr(
+        fd.as_fd().as_raw_fd(),
+        name.as_ptr(),
+        value.as_mut_ptr().cast(),
+        value.len(),
+    )
+    .map_err(Error::from_raw_os_error)
+}
+
+/// Set an extended attribute value
+pub(crate) fn setxattr<P: AsRef<Path>, N: AsRef<OsStr>>(
+    path: P,
+    name: N,
+    value: &[u8],
+    flags: XattrFlags,
+) -> Result<()> {
+    let pathname = CString::new(path.as_ref().as_os_str().as_bytes()).unwrap();
+    let name = CString::new(name.as_ref().as_bytes()).unwrap();
+
+    libc_like_syscall::setxattr(
+        pathname.as_ptr(),
+        name.as_ptr(),
+        value.as_ptr().cast(),
+        value.len(),
+        flags.bits(),
+    )
+    .map_err(Error::from_raw_os_error)
+}
+
+/// Set an extended attribute value
+///
+/// If `path` refers to a symlink, the attribute of the link **itself** is
+/// set.
+pub(crate) fn lsetxattr<P: AsRef<Path>, N: AsRef<OsStr>>(
+    path: P,
+    name: N,
+    value: &[u8],
+    flags: XattrFlags,
+) -> Result<()> {
+    let pathname = CString::new(path.as_ref().as_os_str().as_bytes()).unwrap();
+    let name = CString::new(name.as_ref().as_bytes()).unwrap();
+
+    libc_like_syscall::lsetxattr(
+        pathname.as_ptr(),
+        name.as_ptr(),
+        value.as_ptr().cast(),
+        value.len(),
+        flags.bits(),
+    )
+    .map_err(Error::from_raw_os_error)
+}
+
+/// Set an extended attribute value of the file referred to by `fd`
+pub(crate) fn fsetxattr<Fd: AsFd, N: AsRef<OsStr>>(
+    fd: Fd,
+    name: N,
+    value: &[u8],
+    flags: XattrFlags,
+) -> Result<()> {
+    let name = CString::new(name.as_ref().as_bytes()).unwrap();
+
+    libc_like_syscall::fsetxattr(
+        fd.as_fd().as_raw_fd(),
+        name.as_ptr(),
+        value.as_ptr().cast(),
+        value.len(),
+        flags.bits(),
+    )
+    .map_err(Error::from_raw_os_error)
+}
+
+/// List extended attribute names
+///
+/// If the buffer is empty, the size it needs is returned.
+pub(crate) fn listxattr<P: AsRef<Path>>(path: P, list: &mut [u8]) -> Result<usize> {
+    let pathname = CString::new(path.as_ref().as_os_str().as_bytes()).unwrap();
 
-/// Transfers  ("flushes") all modified in-core data of (i.e., modified buffer
-/// cache pages for) the file referred to by the file descriptor fd to the
-/// disk device
-pub(crate) fn fsync<Fd: AsFd>(fd: Fd) -> Result<()> {
-    libc_like_syscall::fsync(fd.as_fd().as_raw_fd()).map_err(Error::from_raw_os_error)
-}
-/// `fdatasync()` is similar to [`fsync()`], but does not flush modified metadata
-/// unless that metadata  is needed in order to allow a subsequent data retrieval
-/// to be correctly handled
-pub(crate) fn fdatasync<Fd: AsFd>(fd: Fd) -> Result<()> {
-    libc_like_syscall::fdatasync(fd.as_fd().as_raw_fd()).map_err(Error::from_raw_os_error)
+    libc_like_syscall::listxattr(pathname.as_ptr(), list.as_mut_ptr().cast(), list.len())
+        .map_err(Error::from_raw_os_error)
 }
 
-/// Truncate a file to a specified length
+/// List extended attribute names
 ///
-/// If the file previously was larger than this size, the extra data is lost.
-/// If the file previously was shorter, it is extended, and the extended part
-/// reads as null bytes ('\0').
-pub(crate) fn ftruncate<Fd: AsFd>(fd: Fd, length: u64) -> Result<()> {
-    let length = length
-        .try_into()
-        .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+/// If `path` refers to a symlink, the attributes of the link **itself** are
+/// listed.
+///
+/// If the buffer is empty, the size it needs is returned.
+pub(crate) fn llistxattr<P: AsRef<Path>>(path: P, list: &mut [u8]) -> Result<usize> {
+    let pathname = CString::new(path.as_ref().as_os_str().as_bytes()).unwrap();
 
-    libc_like_syscall::ftruncate(fd.as_fd().as_raw_fd(), length).map_err(Error::from_raw_os_error)
+    libc_like_syscall::llistxattr(pathname.as_ptr(), list.as_mut_ptr().cast(), list.len())
+        .map_err(Error::from_raw_os_error)
 }
 
-/// Changes permissions of a file
-pub(crate) fn chmod<P: AsRef<Path>>(pathname: P, mode: Mode) -> Result<()> {
-    let pathname = CString::new(pathname.as_ref().as_os_str().as_bytes()).unwrap();
-    let mode = mode.bits();
-    libc_like_syscall::chmod(pathname.as_ptr(), mode).map_err(Error::from_raw_os_error)
+/// List extended attribute names of the file referred to by `fd`
+///
+/// If the buffer is empty, the size it needs is returned.
+pub(crate) fn flistxattr<Fd: AsFd>(fd: Fd, list: &mut [u8]) -> Result<usize> {
+    libc_like_syscall::flistxattr(fd.as_fd().as_raw_fd(), list.as_mut_ptr().cast(), list.len())
+        .map_err(Error::from_raw_os_error)
 }
 
-/// Changes permissions of a file
-pub(crate) fn fchmod<Fd: AsFd>(fd: Fd, mode: Mode) -> Result<()> {
-    let mode = mode.bits();
-    libc_like_syscall::fchmod(fd.as_fd().as_raw_fd(), mode).map_err(Error::from_raw_os_error)
+/// Remove an extended attribute
+pub(crate) fn removexattr<P: AsRef<Path>, N: AsRef<OsStr>>(path: P, name: N) -> Result<()> {
+    let pathname = CString::new(path.as_ref().as_os_str().as_bytes()).unwrap();
+    let name = CString::new(name.as_ref().as_bytes()).unwrap();
+
+    libc_like_syscall::removexattr(pathname.as_ptr(), name.as_ptr())
+        .map_err(Error::from_raw_os_error)
 }
 
-/// Time operation used in [`futimens()`].
-pub(crate) enum TimestampSpec {
-    Omit,
-    SetToNow,
-    Set(SystemTime),
+/// Remove an extended attribute
+///
+/// If `path` refers to a symlink, the attribute of the link **itself** is
+/// removed.
+pub(crate) fn lremovexattr<P: AsRef<Path>, N: AsRef<OsStr>>(path: P, name: N) -> Result<()> {
+    let pathname = CString::new(path.as_ref().as_os_str().as_bytes()).unwrap();
+    let name = CString::new(name.as_ref().as_bytes()).unwrap();
+
+    libc_like_syscall::lremovexattr(pathname.as_ptr(), name.as_ptr())
+        .map_err(Error::from_raw_os_error)
 }
 
-impl From<&TimestampSpec> for libc_like_syscall::Timespec {
-    fn from(value: &TimestampSpec) -> Self {
-        let mut default = libc_like_syscall::Timespec {
-            tv_sec: 0,
-            tv_nsec: 0,
-        };
+/// Remove an extended attribute of the file referred to by `fd`
+pub(crate) fn fremovexattr<Fd: AsFd, N: AsRef<OsStr>>(fd: Fd, name: N) -> Result<()> {
+    let name = CString::new(name.as_ref().as_bytes()).unwrap();
 
-        match value {
-            TimestampSpec::Omit => default.tv_nsec = libc::UTIME_OMIT,
-            TimestampSpec::SetToNow => default.tv_nsec = libc::UTIME_NOW,
-            TimestampSpec::Set(time) => {
-                default.tv_sec = time.sec;
-                default.tv_nsec = time.nsec;
-            }
-        }
-        default
-    }
+    libc_like_syscall::fremovexattr(fd.as_fd().as_raw_fd(), name.as_ptr())
+        .map_err(Error::from_raw_os_error)
 }
 
-/// Changes file timestamps with nanosecond precision
-//
-// This syscall is implemented on the top of `utimensat(2)`, for more information, see
-// https://man7.org/linux/man-pages/man2/utimensat.2.html
-pub(crate) fn futimens<Fd: AsFd>(
-    fd: Fd,
-    atime: &TimestampSpec,
-    mtime: &TimestampSpec,
-) -> Result<()> {
-    // atime and mtime
-    let times = [atime.into(), mtime.into()];
+/// `_IOR('f', 1, long)` from `<linux/fs.h>`, the kernel actually reads an
+/// `int` despite the `long` in its definition.
+const FS_IOC_GETFLAGS: libc::c_ulong = 0x8008_6601;
+/// `_IOW('f', 2, long)` from `<linux/fs.h>`, takes an `int` as well.
+const FS_IOC_SETFLAGS: libc::c_ulong = 0x4008_6602;
 
-    libc_like_syscall::utimensat(
+/// Gets the inode flags (`FS_*_FL`) of the file referred to by `fd`.
+pub(crate) fn get_inode_flags<Fd: AsFd>(fd: Fd) -> Result<libc::c_int> {
+    let mut flags: libc::c_int = 0;
+    libc_like_syscall::ioctl(
         fd.as_fd().as_raw_fd(),
-        std::ptr::null(),
-        &times as *const libc_like_syscall::Timespec,
-        0,
+        FS_IOC_GETFLAGS,
+        &mut flags as *mut libc::c_int as *mut libc::c_void,
     )
-    .map_err(Error::from_raw_os_error)
+    .map_err(inode_flags_error)?;
+
+    Ok(flags)
 }
 
-/// Change ownership of a file
-pub(crate) fn chown<P: AsRef<Path>>(
-    pathname: P,
-    owner: Option<u32>,
-    group: Option<u32>,
-) -> Result<()> {
-    let pathname = CString::new(pathname.as_ref().as_os_str().as_bytes()).unwrap();
-    // libc::uid_t and libc::gid_t are unsigned number, -1 = MAX
-    let owner = owner.unwrap_or(u32::MAX);
-    let group = group.unwrap_or(u32::MAX);
+/// Sets the inode flags (`FS_*_FL`) of the file referred to by `fd`.
+pub(crate) fn set_inode_flags<Fd: AsFd>(fd: Fd, mut flags: libc::c_int) -> Result<()> {
+    libc_like_syscall::ioctl(
+        fd.as_fd().as_raw_fd(),
+        FS_IOC_SETFLAGS,
+        &mut flags as *mut libc::c_int as *mut libc::c_void,
+    )
+    .map(drop)
+    .map_err(inode_flags_error)
+}
 
-    libc_like_syscall::chown(pathname.as_ptr(), owner, group).map_err(Error::from_raw_os_error)
+/// Filesystems without inode flags fail with `ENOTTY`, which is reported as
+/// an error of kind `Unsupported` wrapping it.
+fn inode_flags_error(errno: libc::c_int) -> Error {
+    let error = Error::from_raw_os_error(errno);
+    if errno == libc::ENOTTY {
+        Error::new(ErrorKind::Unsupported, error)
+    } else {
+        error
+    }
 }
 
 /// Change ownership of the file that are specified by the open file descriptor `fd`
@@ -934,10 +2276,19 @@ pub(crate) fn copy_file_range<F: AsFd>(
     .map_err(Error::from_raw_os_error)
 }
 
+/// Obtains a series of random bytes
+///
+/// The `urandom` source is used, and this function blocks if the entropy pool
+/// has not yet been initialized. Fewer bytes than requested may be returned.
+pub(crate) fn getrandom(buf: &mut [u8]) -> Result<usize> {
+    libc_like_syscall::getrandom(buf.as_mut_ptr() as *mut libc::c_void, buf.len(), 0)
+        .map_err(Error::from_raw_os_error)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
-    use std::{fs::metadata, os::fd::BorrowedFd};
+    use std::fs::metadata;
 
     #[test]
     fn test_open() {
@@ -1016,6 +2367,17 @@ mod test {
         unlink(ln).unwrap();
     }
 
+    #[test]
+    fn test_linkat() {
+        let file = "/tmp/test_linkat";
+        let ln = "/tmp/test_linkat_ln";
+
+        creat(file, Mode::from_bits(0o644).unwrap()).unwrap();
+        linkat(None, file, None, ln, AtFlags::empty()).unwrap();
+        unlink(file).unwrap();
+        unlink(ln).unwrap();
+    }
+
     #[test]
     fn test_mkdir() {
         let dir = "/tmp/test_mkdir";
@@ -1035,6 +2397,35 @@ mod test {
         unlink(new_path).unwrap();
     }
 
+    #[test]
+    fn test_renameat2_noreplace() {
+        let old_path = "/tmp/test_renameat2_noreplace_old_path_encap";
+        let new_path = "/tmp/test_renameat2_noreplace_new_path_encap";
+        creat(old_path, Mode::from_bits(0o644).unwrap()).unwrap();
+        creat(new_path, Mode::from_bits(0o644).unwrap()).unwrap();
+
+        let error = renameat2(
+            None,
+            old_path,
+            None,
+            new_path,
+            RenameFlags::RENAME_NOREPLACE,
+        )
+        .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::AlreadyExists);
+
+        unlink(new_path).unwrap();
+        renameat2(
+            None,
+            old_path,
+            None,
+            new_path,
+            RenameFlags::RENAME_NOREPLACE,
+        )
+        .unwrap();
+        unlink(new_path).unwrap();
+    }
+
     #[test]
     fn test_symlink() {
         let file = "/tmp/test_symlink";
@@ -1094,6 +2485,152 @@ mod test {
         assert_eq!(statx_buf.file_type(), FileType::RegularFile);
         unlink(file).unwrap();
     }
+
+    #[test]
+    fn test_statx_mnt_id() {
+        let statx_buf = statx("/").unwrap();
+
+        assert_ne!(statx_buf.mask() & libc::STATX_MNT_ID, 0);
+        assert_ne!(statx_buf.mnt_id(), 0);
+    }
+
+    #[test]
+    fn test_statx_at() {
+        let file = "/tmp/test_statx_at";
+        creat(file, Mode::from_bits(0o644).unwrap()).unwrap();
+
+        let statx_buf =
+            statx_at(None, file, AtFlags::AT_STATX_DONT_SYNC, libc::STATX_SIZE).unwrap();
+        assert_ne!(statx_buf.mask() & libc::STATX_SIZE, 0);
+        assert_eq!(statx_buf.size(), 0);
+        unlink(file).unwrap();
+    }
+
+    #[test]
+    fn test_xattr() {
+        let file = "/tmp/test_encapsulation_xattr";
+        let name = "user.test_xattr";
+        creat(file, Mode::from_bits(0o644).unwrap()).unwrap();
+
+        setxattr(file, name, b"value", XattrFlags::XATTR_CREATE).unwrap();
+        assert_eq!(
+            setxattr(file, name, b"value", XattrFlags::XATTR_CREATE)
+                .unwrap_err()
+                .kind(),
+            ErrorKind::AlreadyExists
+        );
+        setxattr(file, name, b"new", XattrFlags::XATTR_REPLACE).unwrap();
+
+        let mut buf = [0_u8; 16];
+        assert_eq!(getxattr(file, name, &mut []).unwrap(), 3);
+        let len = getxattr(file, name, &mut buf).unwrap();
+        assert_eq!(&buf[..len], b"new");
+
+        let len = listxattr(file, &mut buf).unwrap();
+        assert!(buf[..len]
+            .split(|b| *b == 0)
+            .any(|attr| attr == name.as_bytes()));
+
+        removexattr(file, name).unwrap();
+        assert!(getxattr(file, name, &mut buf).is_err());
+        unlink(file).unwrap();
+    }
+
+    #[test]
+    fn test_fxattr() {
+        let file = "/tmp/test_encapsulation_fxattr";
+        let name = "user.test_fxattr";
+        let fd = creat(file, Mode::from_bits(0o644).unwrap()).unwrap();
+
+        fsetxattr(&fd, name, b"value", XattrFlags::empty()).unwrap();
+        let mut buf = [0_u8; 16];
+        let len = fgetxattr(&fd, name, &mut buf).unwrap();
+        assert_eq!(&buf[..len], b"value");
+        assert!(flistxattr(&fd, &mut []).unwrap() > name.len());
+        fremovexattr(&fd, name).unwrap();
+
+        unlink(file).unwrap();
+    }
+
+    #[test]
+    fn test_lxattr() {
+        let file = "/tmp/test_encapsulation_lxattr";
+        let link = "/tmp/test_encapsulation_lxattr_link";
+        let name = "user.test_lxattr";
+        creat(file, Mode::from_bits(0o644).unwrap()).unwrap();
+        symlink(file, link).unwrap();
+
+        setxattr(link, name, b"value", XattrFlags::empty()).unwrap();
+        assert!(lgetxattr(link, name, &mut []).is_err());
+        assert!(lsetxattr(link, name, b"value", XattrFlags::empty()).is_err());
+        assert!(lremovexattr(link, name).is_err());
+        let mut buf = [0_u8; 256];
+        let len = llistxattr(link, &mut buf).unwrap();
+        assert!(!buf[..len]
+            .split(|b| *b == 0)
+            .any(|attr| attr == name.as_bytes()));
+
+        unlink(link).unwrap();
+        unlink(file).unwrap();
+    }
+
+    #[test]
+    fn test_inode_flags() {
+        let file = "/tmp/test_inode_flags";
+        let fd = open(
+            file,
+            Flags::O_CREAT | Flags::O_RDONLY,
+            Mode::from_bits_truncate(0o644),
+        )
+        .unwrap();
+
+        match get_inode_flags(&fd) {
+            Ok(flags) => {
+                // FS_NODUMP_FL
+                set_inode_flags(&fd, flags | 0x40).unwrap();
+                assert_eq!(get_inode_flags(&fd).unwrap() & 0x40, 0x40);
+                set_inode_flags(&fd, flags).unwrap();
+                assert_eq!(get_inode_flags(&fd).unwrap(), flags);
+            }
+            // Filesystems like tmpfs on old kernels do not support inode flags.
+            Err(e) => assert_eq!(e.kind(), ErrorKind::Unsupported),
+        }
+        unlink(file).unwrap();
+    }
+
+    #[test]
+    fn test_statfs() {
+        let statfs_buf = statfs("/proc").unwrap();
+        assert_eq!(statfs_buf.fs_type(), libc::PROC_SUPER_MAGIC);
+
+        let fd = open("/proc", Flags::O_RDONLY, Mode::empty()).unwrap();
+        let fstatfs_buf = fstatfs(&fd).unwrap();
+        assert_eq!(fstatfs_buf.fsid(), statfs_buf.fsid());
+    }
+
+    #[test]
+    fn test_file_stat_fallback() {
+        let file = "/tmp/test_file_stat_fallback";
+        creat(file, Mode::from_bits(0o644).unwrap()).unwrap();
+
+        let statx = FileStat::Statx(statx(file).unwrap());
+        let stat = FileStat::Stat(newfstatat(None, file, AtFlags::empty()).unwrap());
+        assert_eq!(stat.mask(), libc::STATX_BASIC_STATS);
+        assert_eq!(statx.ino(), stat.ino());
+        assert_eq!(statx.mode(), stat.mode());
+        assert_eq!(statx.dev(), stat.dev());
+        assert_eq!(statx.mtime(), stat.mtime());
+        unlink(file).unwrap();
+    }
+
+    #[test]
+    fn test_probe_statx() {
+        assert!(probe_statx(libc::ENOENT));
+        // statx(2) is available on the kernels we test against
+        assert!(probe_statx(libc::EPERM));
+        assert_eq!(STATX_STATE.load(Ordering::Relaxed), STATX_AVAILABLE);
+    }
+
     #[test]
     fn test_lstatx() {
         let file = "/tmp/test_lstatx";
@@ -1148,6 +2685,93 @@ mod test {
         assert_eq!(num_of_file, n_files);
     }
 
+    #[test]
+    fn test_faccessat_emulated_root() {
+        if !nix::unistd::geteuid().is_root() {
+            return;
+        }
+
+        let dir = "/tmp/test_faccessat_emulated_root";
+        let file = "/tmp/test_faccessat_emulated_root_file";
+        let _ = rmdir(dir);
+        mkdir(dir, Mode::from_bits(0o600).unwrap()).unwrap();
+        creat(file, Mode::from_bits(0o600).unwrap()).unwrap();
+        chmod(dir, Mode::from_bits(0o600).unwrap()).unwrap();
+        chmod(file, Mode::from_bits(0o600).unwrap()).unwrap();
+
+        std::thread::spawn(move || {
+            // The real UID of this thread only, which makes `AT_EACCESS`
+            // differ from a plain check.
+            let res = unsafe { sc::syscall!(SETRESUID, 65534, u32::MAX, u32::MAX) };
+            assert_eq!(res, 0);
+
+            let check = |path: &str| {
+                let pathname = CString::new(path).unwrap();
+                faccessat_emulated(
+                    None,
+                    Path::new(path),
+                    &pathname,
+                    libc::X_OK,
+                    AtFlags::AT_EACCESS,
+                )
+            };
+            // Root can search any directory, but not execute any file.
+            check(dir).unwrap();
+            assert_eq!(check(file).unwrap_err().raw_os_error(), Some(libc::EACCES));
+        })
+        .join()
+        .unwrap();
+
+        rmdir(dir).unwrap();
+        unlink(file).unwrap();
+    }
+
+    #[test]
+    fn test_faccessat_emulated_read_only_symlink() {
+        let dir = "/tmp/test_faccessat_emulated_read_only_symlink";
+        let link = "/tmp/test_faccessat_emulated_read_only_symlink/link";
+        let _ = mkdir(dir, Mode::from_bits(0o755).unwrap());
+
+        std::thread::spawn(move || {
+            // A read-only mount in a private mount namespace of this thread.
+            use nix::sched::{unshare, CloneFlags};
+            if unshare(CloneFlags::CLONE_NEWNS).is_err() {
+                return;
+            }
+            mount(None, "/", None, libc::MS_REC | libc::MS_PRIVATE, None).unwrap();
+            mount(
+                Some(OsStr::new("tmpfs")),
+                dir,
+                Some(OsStr::new("tmpfs")),
+                0,
+                None,
+            )
+            .unwrap();
+            symlink("missing", link).unwrap();
+            mount(None, dir, None, libc::MS_REMOUNT | libc::MS_RDONLY, None).unwrap();
+
+            let pathname = CString::new(link).unwrap();
+            let check = |mode| {
+                faccessat_emulated(
+                    None,
+                    Path::new(link),
+                    &pathname,
+                    mode,
+                    AtFlags::AT_SYMLINK_NOFOLLOW,
+                )
+            };
+            check(libc::R_OK).unwrap();
+            assert_eq!(
+                check(libc::W_OK).unwrap_err().raw_os_error(),
+                Some(libc::EROFS)
+            );
+        })
+        .join()
+        .unwrap();
+
+        rmdir(dir).unwrap();
+    }
+
     #[test]
     fn test_chroot() {
         let error = chroot(".").unwrap_err();
@@ -1196,6 +2820,36 @@ mod test {
         unlink(file).unwrap();
     }
 
+    #[test]
+    fn test_syncfs() {
+        let file = "/tmp/test_syncfs_encap";
+        let fd = creat(file, Mode::from_bits(0o644).unwrap()).unwrap();
+        syncfs(&fd).unwrap();
+        unlink(file).unwrap();
+    }
+
+    #[test]
+    fn test_sync_file_range() {
+        let file = "/tmp/test_sync_file_range_encap";
+        let fd = open(
+            file,
+            Flags::O_RDWR | Flags::O_CREAT,
+            Mode::from_bits(0o644).unwrap(),
+        )
+        .unwrap();
+        write(&fd, b"hello").unwrap();
+
+        sync_file_range(&fd, 0, 0, SyncFileRangeFlags::all()).unwrap();
+        assert_eq!(
+            ErrorKind::InvalidInput,
+            sync_file_range(&fd, u64::MAX, 0, SyncFileRangeFlags::all())
+                .unwrap_err()
+                .kind()
+        );
+
+        unlink(file).unwrap();
+    }
+
     #[test]
     fn test_ftruncate() {
         let file = "/tmp/ftruncate";
@@ -1333,6 +2987,40 @@ mod test {
         unlink(file).unwrap();
     }
 
+    #[test]
+    fn test_utimensat_nofollow() {
+        let file = "/tmp/test_utimensat_nofollow_encap";
+        let link = "/tmp/test_utimensat_nofollow_link_encap";
+        creat(file, Mode::from_bits(0o644).unwrap()).unwrap();
+        symlink(file, link).unwrap();
+
+        let mtime = SystemTime::new(1, 0);
+        utimensat(
+            None,
+            link,
+            &TimestampSpec::Omit,
+            &TimestampSpec::Set(mtime),
+            AtFlags::AT_SYMLINK_NOFOLLOW,
+        )
+        .unwrap();
+
+        assert_eq!(lstatx(link).unwrap().mtime(), (1, 0));
+        assert_ne!(statx(file).unwrap().mtime(), (1, 0));
+
+        unlink(link).unwrap();
+        unlink(file).unwrap();
+    }
+
+    #[test]
+    fn test_clock_gettime() {
+        let (sec, _) = clock_gettime(ClockId::Realtime).unwrap();
+        assert!(sec > 0);
+
+        let first = clock_gettime(ClockId::Monotonic).unwrap();
+        let second = clock_gettime(ClockId::Monotonic).unwrap();
+        assert!(first <= second);
+    }
+
     #[test]
     fn test_chown() {
         let file = "/tmp/test_chown_encap";
@@ -1439,4 +3127,43 @@ mod test {
         unlink(source).unwrap();
         unlink(des).unwrap();
     }
+
+    #[test]
+    fn test_getrandom() {
+        let mut buf = [0_u8; 32];
+        assert_eq!(32, getrandom(&mut buf).unwrap());
+    }
+
+    #[test]
+    fn test_parse_inotify_events() {
+        let mut buf = Vec::new();
+        for (wd, mask, cookie, name) in [
+            (1, libc::IN_CREATE, 0, &b"file\0\0\0\0"[..]),
+            (-1, libc::IN_Q_OVERFLOW, 0, b""),
+        ] {
+            buf.extend_from_slice(&(wd as libc::c_int).to_ne_bytes());
+            buf.extend_from_slice(&mask.to_ne_bytes());
+            buf.extend_from_slice(&(cookie as u32).to_ne_bytes());
+            buf.extend_from_slice(&(name.len() as u32).to_ne_bytes());
+            buf.extend_from_slice(name);
+        }
+
+        assert_eq!(
+            parse_inotify_events(&buf),
+            [
+                InotifyEvent {
+                    wd: 1,
+                    mask: libc::IN_CREATE,
+                    cookie: 0,
+                    name: Some(OsString::from("file")),
+                },
+                InotifyEvent {
+                    wd: -1,
+                    mask: libc::IN_Q_OVERFLOW,
+                    cookie: 0,
+                    name: None,
+                },
+            ]
+        );
+    }
 }
@@ -10,8 +10,8 @@
 //! `Ok(the_num_of_bytes_read)` on success, `Err(errno_value)` on error.
 
 use libc::{
-    blkcnt64_t, blksize_t, c_char, c_int, c_long, c_uint, c_void, dev_t, gid_t, ino64_t, mode_t,
-    nlink_t, off64_t, off_t, size_t, time_t, uid_t, O_CREAT, O_RDONLY, O_TRUNC,
+    blkcnt64_t, blksize_t, c_char, c_int, c_long, c_uint, c_ulong, c_void, clockid_t, dev_t, gid_t,
+    ino64_t, mode_t, nlink_t, off64_t, off_t, size_t, time_t, uid_t, O_CREAT, O_RDONLY, O_TRUNC,
 };
 use sc::syscall;
 use std::os::unix::io::RawFd;
@@ -32,6 +32,26 @@ pub(crate) fn open(pathname: *const c_char, flags: c_int, mode: mode_t) -> Resul
     syscall_result(res).map(|fd| fd as RawFd)
 }
 
+#[inline]
+pub(crate) fn openat(
+    dirfd: c_int,
+    pathname: *const c_char,
+    flags: c_int,
+    mode: mode_t,
+) -> Result<RawFd, c_int> {
+    let res = unsafe {
+        syscall!(
+            OPENAT,
+            dirfd as usize,
+            pathname as usize,
+            flags as usize,
+            mode
+        )
+    };
+
+    syscall_result(res).map(|fd| fd as RawFd)
+}
+
 #[inline]
 pub(crate) fn creat(pathname: *const c_char, mode: mode_t) -> Result<RawFd, c_int> {
     open(pathname, O_RDONLY | O_CREAT | O_TRUNC, mode)
@@ -91,6 +111,28 @@ pub(crate) fn link(oldpath: *const c_char, newpath: *const c_char) -> Result<(),
     syscall_result(res).map(drop)
 }
 
+#[inline]
+pub(crate) fn linkat(
+    olddirfd: c_int,
+    oldpath: *const c_char,
+    newdirfd: c_int,
+    newpath: *const c_char,
+    flags: c_int,
+) -> Result<(), c_int> {
+    let res = unsafe {
+        syscall!(
+            LINKAT,
+            olddirfd as usize,
+            oldpath as usize,
+            newdirfd as usize,
+            newpath as usize,
+            flags as usize
+        )
+    };
+
+    syscall_result(res).map(drop)
+}
+
 #[inline]
 pub(crate) fn unlink(pathname: *const c_char) -> Result<(), c_int> {
     let res = unsafe { syscall!(UNLINK, pathname as usize) };
@@ -126,8 +168,30 @@ pub(crate) fn rename(oldpath: *const c_char, newpath: *const c_char) -> Result<(
     syscall_result(res).map(drop)
 }
 
+#[inline]
+pub(crate) fn renameat2(
+    olddirfd: c_int,
+    oldpath: *const c_char,
+    newdirfd: c_int,
+    newpath: *const c_char,
+    flags: c_uint,
+) -> Result<(), c_int> {
+    let res = unsafe {
+        syscall!(
+            RENAMEAT2,
+            olddirfd as usize,
+            oldpath as usize,
+            newdirfd as usize,
+            newpath as usize,
+            flags as usize
+        )
+    };
+
+    syscall_result(res).map(drop)
+}
+
 #[repr(C)]
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone)]
 pub(crate) struct Stat {
     pub(crate) st_dev: dev_t,
     pub(crate) st_ino: ino64_t,
@@ -150,97 +214,667 @@ pub(crate) struct Stat {
 }
 
 #[inline]
-pub(crate) fn stat(pathname: *const c_char, statbuf: *mut Stat) -> Result<(), c_int> {
-    let res = unsafe { syscall!(STAT, pathname as usize, statbuf as usize) };
+pub(crate) fn stat(pathname: *const c_char, statbuf: *mut Stat) -> Result<(), c_int> {
+    let res = unsafe { syscall!(STAT, pathname as usize, statbuf as usize) };
+
+    syscall_result(res).map(drop)
+}
+
+#[inline]
+pub(crate) fn fstat(fd: c_int, statbuf: *mut Stat) -> Result<(), c_int> {
+    let res = unsafe { syscall!(FSTAT, fd as usize, statbuf as usize) };
+
+    syscall_result(res).map(drop)
+}
+
+#[inline]
+pub(crate) fn lstat(pathname: *const c_char, statbuf: *mut Stat) -> Result<(), c_int> {
+    let res = unsafe { syscall!(LSTAT, pathname as usize, statbuf as usize) };
+
+    syscall_result(res).map(drop)
+}
+
+#[inline]
+pub(crate) fn newfstatat(
+    dirfd: c_int,
+    pathname: *const c_char,
+    statbuf: *mut Stat,
+    flags: c_int,
+) -> Result<(), c_int> {
+    let res = unsafe {
+        syscall!(
+            NEWFSTATAT,
+            dirfd as usize,
+            pathname as usize,
+            statbuf as usize,
+            flags as usize
+        )
+    };
+
+    syscall_result(res).map(drop)
+}
+
+#[repr(C)]
+#[derive(Default, Debug, Clone)]
+pub(crate) struct Statx {
+    pub(crate) stx_mask: u32,
+    pub(crate) stx_blksize: u32,
+    pub(crate) stx_attributes: u64,
+    pub(crate) stx_nlink: u32,
+    pub(crate) stx_uid: u32,
+    pub(crate) stx_gid: u32,
+    pub(crate) stx_mode: u16,
+    __statx_pad1: [u16; 1],
+    pub(crate) stx_ino: u64,
+    pub(crate) stx_size: u64,
+    pub(crate) stx_blocks: u64,
+    pub(crate) stx_attributes_mask: u64,
+    pub(crate) stx_atime: StatxTimestamp,
+    pub(crate) stx_btime: StatxTimestamp,
+    pub(crate) stx_ctime: StatxTimestamp,
+    pub(crate) stx_mtime: StatxTimestamp,
+    pub(crate) stx_rdev_major: u32,
+    pub(crate) stx_rdev_minor: u32,
+    pub(crate) stx_dev_major: u32,
+    pub(crate) stx_dev_minor: u32,
+    pub(crate) stx_mnt_id: u64,
+    __statx_pad2: u64,
+    __statx_pad3: [u64; 12],
+}
+
+#[repr(C)]
+#[derive(Default, Debug, Copy, Clone)]
+pub(crate) struct StatxTimestamp {
+    pub(crate) tv_sec: i64,
+    pub(crate) tv_nsec: u32,
+    __statx_timestamp_pad1: [i32; 1],
+}
+
+pub(crate) fn statx(
+    dirfd: c_int,
+    pathname: *const c_char,
+    flags: c_int,
+    mask: c_uint,
+    statx_buf: *mut Statx,
+) -> Result<(), c_int> {
+    let res = unsafe {
+        syscall!(
+            STATX,
+            dirfd as usize,
+            pathname as usize,
+            flags as usize,
+            mask as usize,
+            statx_buf as usize
+        )
+    };
+
+    syscall_result(res).map(drop)
+}
+
+#[inline]
+pub(crate) fn ioctl(fd: c_int, request: c_ulong, arg: *mut c_void) -> Result<c_int, c_int> {
+    let res = unsafe { syscall!(IOCTL, fd as usize, request as usize, arg as usize) };
+
+    syscall_result(res).map(|ret| ret as c_int)
+}
+
+#[inline]
+pub(crate) fn getxattr(
+    pathname: *const c_char,
+    name: *const c_char,
+    value: *mut c_void,
+    size: size_t,
+) -> Result<usize, c_int> {
+    let res = unsafe {
+        syscall!(
+            GETXATTR,
+            pathname as usize,
+            name as usize,
+            value as usize,
+            size
+        )
+    };
+
+    syscall_result(res).map(|size| size as usize)
+}
+
+#[inline]
+pub(crate) fn lgetxattr(
+    pathname: *const c_char,
+    name: *const c_char,
+    value: *mut c_void,
+    size: size_t,
+) -> Result<usize, c_int> {
+    let res = unsafe {
+        syscall!(
+            LGETXATTR,
+            pathname as usize,
+            name as usize,
+            value as usize,
+            size
+        )
+    };
+
+    syscall_result(res).map(|size| size as usize)
+}
+
+#[inline]
+pub(crate) fn fgetxattr(
+    fd: c_int,
+    name: *const c_char,
+    value: *mut c_void,
+    size: size_t,
+) -> Result<usize, c_int> {
+    let res = unsafe { syscall!(FGETXATTR, fd as usize, name as usize, value as usize, size) };
+
+    syscall_result(res).map(|size| size as usize)
+}
+
+#[inline]
+pub(crate) fn setxattr(
+    pathname: *const c_char,
+    name: *const c_char,
+    value: *const c_void,
+    size: size_t,
+    flags: c_int,
+) -> Result<(), c_int> {
+    let res = unsafe {
+        syscall!(
+            SETXATTR,
+            pathname as usize,
+            name as usize,
+            value as usize,
+            size,
+            flags as usize
+        )
+    };
+
+    syscall_result(res).map(drop)
+}
+
+#[inline]
+pub(crate) fn lsetxattr(
+    pathname: *const c_char,
+    name: *const c_char,
+    value: *const c_void,
+    size: size_t,
+    flags: c_int,
+) -> Result<(), c_int> {
+    let res = unsafe {
+        syscall!(
+            LSETXATTR,
+            pathname as usize,
+            name as usize,
+            value as usize,
+            size,
+            flags as usize
+        )
+    };
+
+    syscall_result(res).map(drop)
+}
+
+#[inline]
+pub(crate) fn fsetxattr(
+    fd: c_int,
+    name: *const c_char,
+    value: *const c_void,
+    size: size_t,
+    flags: c_int,
+) -> Result<(), c_int> {
+    let res = unsafe {
+        syscall!(
+            FSETXATTR,
+            fd as usize,
+            name as usize,
+            value as usize,
+            size,
+            flags as usize
+        )
+    };
+
+    syscall_result(res).map(drop)
+}
+
+#[inline]
+pub(crate) fn listxattr(
+    pathname: *const c_char,
+    list: *mut c_char,
+    size: size_t,
+) -> Result<usize, c_int> {
+    let res = unsafe { syscall!(LISTXATTR, pathname as usize, list as usize, size) };
+
+    syscall_result(res).map(|size| size as usize)
+}
+
+#[inline]
+pub(crate) fn llistxattr(
+    pathname: *const c_char,
+    list: *mut c_char,
+    size: size_t,
+) -> Result<usize, c_int> {
+    let res = unsafe { syscall!(LLISTXATTR, pathname as usize, list as usize, size) };
+
+    syscall_result(res).map(|size| size as usize)
+}
+
+#[inline]
+pub(crate) fn flistxattr(fd: c_int, list: *mut c_char, size: size_t) -> Result<usize, c_int> {
+    let res = unsafe { syscall!(FLISTXATTR, fd as usize, list as usize, size) };
+
+    syscall_result(res).map(|size| size as usize)
+}
+
+#[inline]
+pub(crate) fn removexattr(pathname: *const c_char, name: *const c_char) -> Result<(), c_int> {
+    let res = unsafe { syscall!(REMOVEXATTR, pathname as usize, name as usize) };
+
+    syscall_result(res).map(drop)
+}
+
+#[inline]
+pub(crate) fn lremovexattr(pathname: *const c_char, name: *const c_char) -> Result<(), c_int> {
+    let res = unsafe { syscall!(LREMOVEXATTR, pathname as usize, name as usize) };
+
+    syscall_result(res).map(drop)
+}
+
+#[inline]
+pub(crate) fn fremovexattr(fd: c_int, name: *const c_char) -> Result<(), c_int> {
+    let res = unsafe { syscall!(FREMOVEXATTR, fd as usize, name as usize) };
+
+    syscall_result(res).map(drop)
+}
+
+#[repr(C)]
+#[derive(Default, Debug, Clone)]
+pub(crate) struct Statfs {
+    pub(crate) f_type: c_long,
+    pub(crate) f_bsize: c_long,
+    pub(crate) f_blocks: u64,
+    pub(crate) f_bfree: u64,
+    pub(crate) f_bavail: u64,
+    pub(crate) f_files: u64,
+    pub(crate) f_ffree: u64,
+    pub(crate) f_fsid: [c_int; 2],
+    pub(crate) f_namelen: c_long,
+    pub(crate) f_frsize: c_long,
+    pub(crate) f_flags: c_long,
+    __f_spare: [c_long; 4],
+}
+
+#[inline]
+pub(crate) fn statfs(pathname: *const c_char, buf: *mut Statfs) -> Result<(), c_int> {
+    let res = unsafe { syscall!(STATFS, pathname as usize, buf as usize) };
+
+    syscall_result(res).map(drop)
+}
+
+#[inline]
+pub(crate) fn fstatfs(fd: c_int, buf: *mut Statfs) -> Result<(), c_int> {
+    let res = unsafe { syscall!(FSTATFS, fd as usize, buf as usize) };
+
+    syscall_result(res).map(drop)
+}
+
+#[inline]
+pub(crate) fn prctl(
+    option: c_int,
+    arg2: c_ulong,
+    arg3: c_ulong,
+    arg4: c_ulong,
+    arg5: c_ulong,
+) -> Result<c_int, c_int> {
+    let res = unsafe {
+        syscall!(
+            PRCTL,
+            option as usize,
+            arg2 as usize,
+            arg3 as usize,
+            arg4 as usize,
+            arg5 as usize
+        )
+    };
+
+    syscall_result(res).map(|ret| ret as c_int)
+}
+
+/// `struct landlock_ruleset_attr` from `<linux/landlock.h>`, missing in `libc`.
+///
+/// Only the field of ABI version 1 is present, the kernel accepts smaller
+/// structures.
+#[repr(C)]
+#[derive(Default, Debug, Clone)]
+pub(crate) struct LandlockRulesetAttr {
+    pub(crate) handled_access_fs: u64,
+}
+
+/// `struct landlock_path_beneath_attr` from `<linux/landlock.h>`, which is
+/// packed.
+#[repr(C, packed)]
+#[derive(Default, Debug, Clone)]
+pub(crate) struct LandlockPathBeneathAttr {
+    pub(crate) allowed_access: u64,
+    pub(crate) parent_fd: i32,
+}
+
+// The landlock syscalls are missing in `sc`, so we call them by their numbers
+// from `libc`.
+
+#[inline]
+pub(crate) fn landlock_create_ruleset(
+    attr: *const LandlockRulesetAttr,
+    size: size_t,
+    flags: u32,
+) -> Result<c_int, c_int> {
+    let res = unsafe {
+        sc::syscall3(
+            libc::SYS_landlock_create_ruleset as usize,
+            attr as usize,
+            size,
+            flags as usize,
+        )
+    };
+
+    syscall_result(res).map(|ret| ret as c_int)
+}
+
+#[inline]
+pub(crate) fn landlock_add_rule(
+    ruleset_fd: c_int,
+    rule_type: c_int,
+    rule_attr: *const c_void,
+    flags: u32,
+) -> Result<(), c_int> {
+    let res = unsafe {
+        sc::syscall4(
+            libc::SYS_landlock_add_rule as usize,
+            ruleset_fd as usize,
+            rule_type as usize,
+            rule_attr as usize,
+            flags as usize,
+        )
+    };
+
+    syscall_result(res).map(drop)
+}
+
+#[inline]
+pub(crate) fn landlock_restrict_self(ruleset_fd: c_int, flags: u32) -> Result<(), c_int> {
+    let res = unsafe {
+        sc::syscall2(
+            libc::SYS_landlock_restrict_self as usize,
+            ruleset_fd as usize,
+            flags as usize,
+        )
+    };
+
+    syscall_result(res).map(drop)
+}
+
+#[inline]
+pub(crate) fn inotify_init1(flags: c_int) -> Result<RawFd, c_int> {
+    let res = unsafe { syscall!(INOTIFY_INIT1, flags as usize) };
+
+    syscall_result(res).map(|fd| fd as RawFd)
+}
+
+#[inline]
+pub(crate) fn inotify_add_watch(
+    fd: c_int,
+    pathname: *const c_char,
+    mask: u32,
+) -> Result<c_int, c_int> {
+    let res = unsafe {
+        syscall!(
+            INOTIFY_ADD_WATCH,
+            fd as usize,
+            pathname as usize,
+            mask as usize
+        )
+    };
+
+    syscall_result(res).map(|wd| wd as c_int)
+}
+
+#[inline]
+pub(crate) fn inotify_rm_watch(fd: c_int, wd: c_int) -> Result<(), c_int> {
+    let res = unsafe { syscall!(INOTIFY_RM_WATCH, fd as usize, wd as usize) };
+
+    syscall_result(res).map(drop)
+}
+
+#[inline]
+pub(crate) fn poll(
+    fds: *mut libc::pollfd,
+    nfds: libc::nfds_t,
+    timeout: c_int,
+) -> Result<c_int, c_int> {
+    let res = unsafe { syscall!(POLL, fds as usize, nfds as usize, timeout as usize) };
+
+    syscall_result(res).map(|num_ready| num_ready as c_int)
+}
+
+#[inline]
+pub(crate) fn mknodat(
+    dirfd: c_int,
+    pathname: *const c_char,
+    mode: mode_t,
+    dev: dev_t,
+) -> Result<(), c_int> {
+    let res = unsafe {
+        syscall!(
+            MKNODAT,
+            dirfd as usize,
+            pathname as usize,
+            mode as usize,
+            dev as usize
+        )
+    };
+
+    syscall_result(res).map(drop)
+}
+
+#[inline]
+pub(crate) fn faccessat(dirfd: c_int, pathname: *const c_char, mode: c_int) -> Result<(), c_int> {
+    let res = unsafe { syscall!(FACCESSAT, dirfd as usize, pathname as usize, mode as usize) };
+
+    syscall_result(res).map(drop)
+}
+
+/// Available since Linux 5.8, `faccessat(2)` has no `flags` argument.
+#[inline]
+pub(crate) fn faccessat2(
+    dirfd: c_int,
+    pathname: *const c_char,
+    mode: c_int,
+    flags: c_int,
+) -> Result<(), c_int> {
+    let res = unsafe {
+        syscall!(
+            FACCESSAT2,
+            dirfd as usize,
+            pathname as usize,
+            mode as usize,
+            flags as usize
+        )
+    };
+
+    syscall_result(res).map(drop)
+}
+
+/// `getuid(2)` and friends always succeed.
+#[inline]
+pub(crate) fn getuid() -> uid_t {
+    unsafe { syscall!(GETUID) as uid_t }
+}
+
+#[inline]
+pub(crate) fn geteuid() -> uid_t {
+    unsafe { syscall!(GETEUID) as uid_t }
+}
+
+#[inline]
+pub(crate) fn getgid() -> gid_t {
+    unsafe { syscall!(GETGID) as gid_t }
+}
+
+#[inline]
+pub(crate) fn getegid() -> gid_t {
+    unsafe { syscall!(GETEGID) as gid_t }
+}
+
+#[inline]
+pub(crate) fn getgroups(size: c_int, list: *mut gid_t) -> Result<usize, c_int> {
+    let res = unsafe { syscall!(GETGROUPS, size as usize, list as usize) };
+
+    syscall_result(res).map(|num| num as usize)
+}
+
+#[inline]
+pub(crate) fn getdents64(fd: c_int, dirp: *mut c_void, count: size_t) -> Result<usize, c_int> {
+    let res = unsafe { syscall!(GETDENTS64, fd as usize, dirp as usize, count) };
+
+    syscall_result(res).map(|num_read| num_read as usize)
+}
+
+#[inline]
+pub(crate) fn chroot(path: *const c_char) -> Result<(), c_int> {
+    let res = unsafe { syscall!(CHROOT, path as usize) };
+
+    syscall_result(res).map(drop)
+}
+
+#[inline]
+pub(crate) fn mount(
+    source: *const c_char,
+    target: *const c_char,
+    filesystemtype: *const c_char,
+    mountflags: c_ulong,
+    data: *const c_void,
+) -> Result<(), c_int> {
+    let res = unsafe {
+        syscall!(
+            MOUNT,
+            source as usize,
+            target as usize,
+            filesystemtype as usize,
+            mountflags as usize,
+            data as usize
+        )
+    };
+
+    syscall_result(res).map(drop)
+}
+
+#[inline]
+pub(crate) fn umount2(target: *const c_char, flags: c_int) -> Result<(), c_int> {
+    let res = unsafe { syscall!(UMOUNT2, target as usize, flags as usize) };
+
+    syscall_result(res).map(drop)
+}
+
+#[inline]
+pub(crate) fn fsopen(fsname: *const c_char, flags: c_uint) -> Result<RawFd, c_int> {
+    let res = unsafe { syscall!(FSOPEN, fsname as usize, flags as usize) };
 
-    syscall_result(res).map(drop)
+    syscall_result(res).map(|fd| fd as RawFd)
 }
 
 #[inline]
-pub(crate) fn fstat(fd: c_int, statbuf: *mut Stat) -> Result<(), c_int> {
-    let res = unsafe { syscall!(FSTAT, fd as usize, statbuf as usize) };
+pub(crate) fn fsconfig(
+    fd: c_int,
+    cmd: c_uint,
+    key: *const c_char,
+    value: *const c_void,
+    aux: c_int,
+) -> Result<(), c_int> {
+    let res = unsafe {
+        syscall!(
+            FSCONFIG,
+            fd as usize,
+            cmd as usize,
+            key as usize,
+            value as usize,
+            aux as usize
+        )
+    };
 
     syscall_result(res).map(drop)
 }
 
 #[inline]
-pub(crate) fn lstat(pathname: *const c_char, statbuf: *mut Stat) -> Result<(), c_int> {
-    let res = unsafe { syscall!(LSTAT, pathname as usize, statbuf as usize) };
+pub(crate) fn fsmount(fs_fd: c_int, flags: c_uint, attr_flags: c_uint) -> Result<RawFd, c_int> {
+    let res = unsafe { syscall!(FSMOUNT, fs_fd as usize, flags as usize, attr_flags as usize) };
+
+    syscall_result(res).map(|fd| fd as RawFd)
+}
+
+#[inline]
+pub(crate) fn move_mount(
+    from_dirfd: c_int,
+    from_pathname: *const c_char,
+    to_dirfd: c_int,
+    to_pathname: *const c_char,
+    flags: c_uint,
+) -> Result<(), c_int> {
+    let res = unsafe {
+        syscall!(
+            MOVE_MOUNT,
+            from_dirfd as usize,
+            from_pathname as usize,
+            to_dirfd as usize,
+            to_pathname as usize,
+            flags as usize
+        )
+    };
 
     syscall_result(res).map(drop)
 }
 
-#[repr(C)]
-#[derive(Default, Debug, Clone)]
-pub(crate) struct Statx {
-    pub(crate) stx_mask: u32,
-    pub(crate) stx_blksize: u32,
-    pub(crate) stx_attributes: u64,
-    pub(crate) stx_nlink: u32,
-    pub(crate) stx_uid: u32,
-    pub(crate) stx_gid: u32,
-    pub(crate) stx_mode: u16,
-    __statx_pad1: [u16; 1],
-    pub(crate) stx_ino: u64,
-    pub(crate) stx_size: u64,
-    pub(crate) stx_blocks: u64,
-    pub(crate) stx_attributes_mask: u64,
-    pub(crate) stx_atime: StatxTimestamp,
-    pub(crate) stx_btime: StatxTimestamp,
-    pub(crate) stx_ctime: StatxTimestamp,
-    pub(crate) stx_mtime: StatxTimestamp,
-    pub(crate) stx_rdev_major: u32,
-    pub(crate) stx_rdev_minor: u32,
-    pub(crate) stx_dev_major: u32,
-    pub(crate) stx_dev_minor: u32,
-    pub(crate) stx_mnt_id: u64,
-    __statx_pad2: u64,
-    __statx_pad3: [u64; 12],
+#[inline]
+pub(crate) fn open_tree(
+    dirfd: c_int,
+    pathname: *const c_char,
+    flags: c_uint,
+) -> Result<RawFd, c_int> {
+    let res = unsafe { syscall!(OPEN_TREE, dirfd as usize, pathname as usize, flags as usize) };
+
+    syscall_result(res).map(|fd| fd as RawFd)
 }
 
+/// `struct mount_attr` from `<linux/mount.h>`, missing in `libc`.
 #[repr(C)]
-#[derive(Default, Debug, Copy, Clone)]
-pub(crate) struct StatxTimestamp {
-    pub(crate) tv_sec: i64,
-    pub(crate) tv_nsec: u32,
-    __statx_timestamp_pad1: [i32; 1],
+#[derive(Default, Debug, Clone)]
+pub(crate) struct MountAttr {
+    pub(crate) attr_set: u64,
+    pub(crate) attr_clr: u64,
+    pub(crate) propagation: u64,
+    pub(crate) userns_fd: u64,
 }
 
-pub(crate) fn statx(
+#[inline]
+pub(crate) fn mount_setattr(
     dirfd: c_int,
     pathname: *const c_char,
-    flags: c_int,
-    mask: c_uint,
-    statx_buf: *mut Statx,
+    flags: c_uint,
+    attr: *mut MountAttr,
+    size: size_t,
 ) -> Result<(), c_int> {
     let res = unsafe {
         syscall!(
-            STATX,
+            MOUNT_SETATTR,
             dirfd as usize,
             pathname as usize,
             flags as usize,
-            mask as usize,
-            statx_buf as usize
+            attr as usize,
+            size
         )
     };
 
     syscall_result(res).map(drop)
 }
 
-#[inline]
-pub(crate) fn getdents64(fd: c_int, dirp: *mut c_void, count: size_t) -> Result<usize, c_int> {
-    let res = unsafe { syscall!(GETDENTS64, fd as usize, dirp as usize, count) };
-
-    syscall_result(res).map(|num_read| num_read as usize)
-}
-
-#[inline]
-pub(crate) fn chroot(path: *const c_char) -> Result<(), c_int> {
-    let res = unsafe { syscall!(CHROOT, path as usize) };
-
-    syscall_result(res).map(drop)
-}
-
 #[inline]
 pub(crate) fn lseek64(fd: c_int, offset: off64_t, whence: c_int) -> Result<u64, c_int> {
     let res = unsafe { syscall!(LSEEK, fd as usize, offset as usize, whence as usize) };
@@ -279,6 +913,37 @@ pub(crate) fn fdatasync(fd: c_int) -> Result<(), c_int> {
     syscall_result(res).map(drop)
 }
 
+#[inline]
+pub(crate) fn syncfs(fd: c_int) -> Result<(), c_int> {
+    let res = unsafe { syscall!(SYNCFS, fd as usize) };
+    syscall_result(res).map(drop)
+}
+
+#[inline]
+pub(crate) fn sync_file_range(
+    fd: c_int,
+    offset: off64_t,
+    nbytes: off64_t,
+    flags: c_uint,
+) -> Result<(), c_int> {
+    let res = unsafe {
+        syscall!(
+            SYNC_FILE_RANGE,
+            fd as usize,
+            offset as usize,
+            nbytes as usize,
+            flags as usize
+        )
+    };
+    syscall_result(res).map(drop)
+}
+
+/// `sync(2)` is always successful.
+#[inline]
+pub(crate) fn sync() {
+    unsafe { syscall!(SYNC) };
+}
+
 #[inline]
 pub(crate) fn ftruncate(fd: c_int, length: off_t) -> Result<(), c_int> {
     let res = unsafe { syscall!(FTRUNCATE, fd as usize, length as usize) };
@@ -297,6 +962,13 @@ pub(crate) fn fchmod(fd: c_int, mode: mode_t) -> Result<(), c_int> {
     syscall_result(res).map(drop)
 }
 
+/// `umask(2)` always succeeds and returns the previous mask.
+#[inline]
+pub(crate) fn umask(mask: mode_t) -> mode_t {
+    let res = unsafe { syscall!(UMASK, mask as usize) };
+    res as mode_t
+}
+
 #[repr(C)]
 #[derive(Default, Copy, Clone)]
 pub(crate) struct Timespec {
@@ -304,6 +976,12 @@ pub(crate) struct Timespec {
     pub(crate) tv_nsec: c_long,
 }
 
+#[inline]
+pub(crate) fn clock_gettime(clockid: clockid_t, tp: *mut Timespec) -> Result<(), c_int> {
+    let res = unsafe { syscall!(CLOCK_GETTIME, clockid as usize, tp as usize) };
+    syscall_result(res).map(drop)
+}
+
 #[inline]
 pub(crate) fn utimensat(
     dirfd: c_int,
@@ -364,6 +1042,13 @@ pub(crate) fn copy_file_range(
     syscall_result(res).map(|num_read| num_read as usize)
 }
 
+#[inline]
+pub(crate) fn getrandom(buf: *mut c_void, buflen: size_t, flags: c_uint) -> Result<usize, c_int> {
+    let res = unsafe { syscall!(GETRANDOM, buf as usize, buflen, flags as usize) };
+
+    syscall_result(res).map(|num_read| num_read as usize)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -434,6 +1119,25 @@ mod test {
         unlink(ln.as_ptr().cast()).unwrap();
     }
 
+    #[test]
+    fn test_linkat() {
+        let file = "/tmp/test_linkat\0";
+        let ln = "/tmp/test_linkat_ln\0";
+        close(creat(file.as_ptr().cast(), 0o644).unwrap()).unwrap();
+
+        linkat(
+            libc::AT_FDCWD,
+            file.as_ptr().cast(),
+            libc::AT_FDCWD,
+            ln.as_ptr().cast(),
+            0,
+        )
+        .unwrap();
+
+        unlink(file.as_ptr().cast()).unwrap();
+        unlink(ln.as_ptr().cast()).unwrap();
+    }
+
     #[test]
     fn test_mkdir() {
         let dir = "/tmp/test_mkdir\0";
@@ -465,6 +1169,28 @@ mod test {
         unlink(new_path.as_ptr().cast()).unwrap();
     }
 
+    #[test]
+    fn test_renameat2_noreplace() {
+        let old_path = "/tmp/test_renameat2_noreplace_old_path\0";
+        let new_path = "/tmp/test_renameat2_noreplace_new_path\0";
+        close(creat(old_path.as_ptr().cast(), 0o644).unwrap()).unwrap();
+        close(creat(new_path.as_ptr().cast(), 0o644).unwrap()).unwrap();
+
+        assert_eq!(
+            renameat2(
+                libc::AT_FDCWD,
+                old_path.as_ptr().cast(),
+                libc::AT_FDCWD,
+                new_path.as_ptr().cast(),
+                libc::RENAME_NOREPLACE,
+            ),
+            Err(libc::EEXIST)
+        );
+
+        unlink(old_path.as_ptr().cast()).unwrap();
+        unlink(new_path.as_ptr().cast()).unwrap();
+    }
+
     #[test]
     fn test_symlink() {
         let file = "/tmp/test_symlink\0";
@@ -502,6 +1228,203 @@ mod test {
         unlink(file.as_ptr().cast()).unwrap();
     }
 
+    #[test]
+    fn test_ioctl() {
+        let file = "/tmp/test_ioctl\0";
+        let fd = open(file.as_ptr().cast(), O_CREAT | O_RDWR | O_TRUNC, 0o644).unwrap();
+        assert_eq!(write(fd, b"hello".as_ptr().cast(), 5), Ok(5));
+        assert_eq!(lseek64(fd, 0, SEEK_SET), Ok(0));
+
+        let mut available: c_int = 0;
+        ioctl(
+            fd,
+            libc::FIONREAD,
+            &mut available as *mut c_int as *mut c_void,
+        )
+        .unwrap();
+        assert_eq!(available, 5);
+
+        close(fd).unwrap();
+        unlink(file.as_ptr().cast()).unwrap();
+    }
+
+    #[test]
+    fn test_xattr() {
+        let file = "/tmp/test_xattr\0";
+        let name = "user.test_xattr\0";
+        close(creat(file.as_ptr().cast(), 0o644).unwrap()).unwrap();
+
+        setxattr(
+            file.as_ptr().cast(),
+            name.as_ptr().cast(),
+            b"value".as_ptr().cast(),
+            5,
+            libc::XATTR_CREATE,
+        )
+        .unwrap();
+        assert_eq!(
+            setxattr(
+                file.as_ptr().cast(),
+                name.as_ptr().cast(),
+                b"value".as_ptr().cast(),
+                5,
+                libc::XATTR_CREATE,
+            ),
+            Err(libc::EEXIST)
+        );
+
+        let mut buf = [0_u8; 16];
+        let len = getxattr(
+            file.as_ptr().cast(),
+            name.as_ptr().cast(),
+            buf.as_mut_ptr().cast(),
+            buf.len(),
+        )
+        .unwrap();
+        assert_eq!(&buf[..len], b"value");
+        assert_eq!(
+            getxattr(
+                file.as_ptr().cast(),
+                name.as_ptr().cast(),
+                buf.as_mut_ptr().cast(),
+                1
+            ),
+            Err(libc::ERANGE)
+        );
+
+        let len = listxattr(file.as_ptr().cast(), buf.as_mut_ptr().cast(), buf.len()).unwrap();
+        assert!(buf[..len]
+            .split(|b| *b == 0)
+            .any(|attr| attr == b"user.test_xattr"));
+
+        removexattr(file.as_ptr().cast(), name.as_ptr().cast()).unwrap();
+        assert_eq!(
+            getxattr(
+                file.as_ptr().cast(),
+                name.as_ptr().cast(),
+                buf.as_mut_ptr().cast(),
+                buf.len(),
+            ),
+            Err(libc::ENODATA)
+        );
+
+        unlink(file.as_ptr().cast()).unwrap();
+    }
+
+    #[test]
+    fn test_fxattr() {
+        let file = "/tmp/test_fxattr\0";
+        let name = "user.test_fxattr\0";
+        let fd = creat(file.as_ptr().cast(), 0o644).unwrap();
+
+        fsetxattr(fd, name.as_ptr().cast(), b"value".as_ptr().cast(), 5, 0).unwrap();
+        assert_eq!(
+            fgetxattr(fd, name.as_ptr().cast(), std::ptr::null_mut(), 0),
+            Ok(5)
+        );
+        assert!(flistxattr(fd, std::ptr::null_mut(), 0).unwrap() >= name.len());
+        fremovexattr(fd, name.as_ptr().cast()).unwrap();
+        assert_eq!(fremovexattr(fd, name.as_ptr().cast()), Err(libc::ENODATA));
+
+        close(fd).unwrap();
+        unlink(file.as_ptr().cast()).unwrap();
+    }
+
+    #[test]
+    fn test_lxattr() {
+        let file = "/tmp/test_lxattr\0";
+        let soft_link = "/tmp/test_lxattr_link\0";
+        let name = "user.test_lxattr\0";
+        close(creat(file.as_ptr().cast(), 0o644).unwrap()).unwrap();
+        symlink(file.as_ptr().cast(), soft_link.as_ptr().cast()).unwrap();
+
+        // user.* attributes are not permitted on symlinks
+        assert_eq!(
+            lsetxattr(
+                soft_link.as_ptr().cast(),
+                name.as_ptr().cast(),
+                b"value".as_ptr().cast(),
+                5,
+                0,
+            ),
+            Err(libc::EPERM)
+        );
+        setxattr(
+            soft_link.as_ptr().cast(),
+            name.as_ptr().cast(),
+            b"value".as_ptr().cast(),
+            5,
+            0,
+        )
+        .unwrap();
+        assert_eq!(
+            lgetxattr(
+                soft_link.as_ptr().cast(),
+                name.as_ptr().cast(),
+                std::ptr::null_mut(),
+                0
+            ),
+            Err(libc::ENODATA)
+        );
+        assert!(llistxattr(soft_link.as_ptr().cast(), std::ptr::null_mut(), 0).is_ok());
+        assert_eq!(
+            lremovexattr(soft_link.as_ptr().cast(), name.as_ptr().cast()),
+            Err(libc::EPERM)
+        );
+
+        unlink(soft_link.as_ptr().cast()).unwrap();
+        unlink(file.as_ptr().cast()).unwrap();
+    }
+
+    #[test]
+    fn test_statfs() {
+        let mut statfs_buf = Statfs::default();
+        statfs("/proc\0".as_ptr().cast(), &mut statfs_buf as *mut Statfs).unwrap();
+
+        assert_eq!(statfs_buf.f_type, libc::PROC_SUPER_MAGIC);
+    }
+
+    #[test]
+    fn test_fstatfs() {
+        let fd = open("/proc\0".as_ptr().cast(), O_RDONLY, 0).unwrap();
+
+        let mut statfs_buf = Statfs::default();
+        fstatfs(fd, &mut statfs_buf as *mut Statfs).unwrap();
+
+        assert_eq!(statfs_buf.f_type, libc::PROC_SUPER_MAGIC);
+        close(fd).unwrap();
+    }
+
+    #[test]
+    fn test_newfstatat() {
+        let file = "/tmp/test_newfstatat\0";
+        let soft_link = "/tmp/test_newfstatat_link\0";
+        close(creat(file.as_ptr().cast(), 0o644).unwrap()).unwrap();
+        symlink(file.as_ptr().cast(), soft_link.as_ptr().cast()).unwrap();
+
+        let mut stat_buf = Stat::default();
+        newfstatat(
+            libc::AT_FDCWD,
+            soft_link.as_ptr().cast(),
+            &mut stat_buf as *mut Stat,
+            libc::AT_SYMLINK_NOFOLLOW,
+        )
+        .unwrap();
+        assert_eq!(stat_buf.st_mode & S_IFMT, S_IFLNK);
+
+        newfstatat(
+            libc::AT_FDCWD,
+            soft_link.as_ptr().cast(),
+            &mut stat_buf as *mut Stat,
+            0,
+        )
+        .unwrap();
+        assert_eq!(stat_buf.st_mode & S_IFMT, S_IFREG);
+
+        unlink(soft_link.as_ptr().cast()).unwrap();
+        unlink(file.as_ptr().cast()).unwrap();
+    }
+
     #[test]
     fn test_lstat() {
         let file = "/tmp/test_lstat\0";
@@ -662,6 +1585,38 @@ mod test {
         unlink(file.as_ptr().cast()).unwrap();
     }
 
+    #[test]
+    fn test_syncfs() {
+        let file = "/tmp/test_syncfs\0";
+        let fd = creat(file.as_ptr().cast(), 0o644).unwrap();
+
+        syncfs(fd).unwrap();
+
+        close(fd).unwrap();
+        unlink(file.as_ptr().cast()).unwrap();
+    }
+
+    #[test]
+    fn test_sync_file_range() {
+        let file = "/tmp/test_sync_file_range\0";
+        let fd = open(file.as_ptr().cast(), O_CREAT | O_RDWR, 0o644).unwrap();
+        write(fd, b"hello".as_ptr().cast(), 5).unwrap();
+
+        sync_file_range(
+            fd,
+            0,
+            5,
+            libc::SYNC_FILE_RANGE_WAIT_BEFORE
+                | libc::SYNC_FILE_RANGE_WRITE
+                | libc::SYNC_FILE_RANGE_WAIT_AFTER,
+        )
+        .unwrap();
+        assert_eq!(sync_file_range(fd, -1, 5, 0), Err(libc::EINVAL));
+
+        close(fd).unwrap();
+        unlink(file.as_ptr().cast()).unwrap();
+    }
+
     #[test]
     fn test_ftruncate() {
         let file = "/tmp/test_ftruncate\0";
@@ -739,6 +1694,19 @@ mod test {
         unlink(file.as_ptr().cast()).unwrap();
     }
 
+    #[test]
+    fn test_clock_gettime() {
+        let mut tp = Timespec::default();
+        clock_gettime(libc::CLOCK_REALTIME, &mut tp as *mut Timespec).unwrap();
+        assert!(tp.tv_sec > 0);
+        assert!((0..1_000_000_000).contains(&tp.tv_nsec));
+
+        assert_eq!(
+            clock_gettime(-1, &mut tp as *mut Timespec),
+            Err(libc::EINVAL)
+        );
+    }
+
     #[test]
     fn test_chown() {
         let file = "/tmp/test_chown_libc\0";
@@ -856,4 +1824,10 @@ mod test {
         unlink(source.as_ptr().cast()).unwrap();
         unlink(des.as_ptr().cast()).unwrap();
     }
+
+    #[test]
+    fn test_getrandom() {
+        let mut buf = [0_u8; 16];
+        assert_eq!(getrandom(buf.as_mut_ptr().cast(), 16, 0).unwrap(), 16);
+    }
 }
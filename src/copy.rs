@@ -0,0 +1,526 @@
+use crate::{
+    acl::AclType,
+    backend::encapsulation,
+    file::File,
+    filetimes::FileTimes,
+    functions::{
+        fchown, lchown, read_link, remove_file, set_symlink_file_times, symlink, symlink_metadata,
+    },
+    metadata::Metadata,
+    open_option::OpenOptions,
+    permissions::Permissions,
+    xattr::{get_symlink_xattr, list_symlink_xattr, set_symlink_xattr, SetXattrFlags},
+};
+use std::{
+    ffi::OsStr,
+    io::{self, Error, ErrorKind, Result},
+    os::{
+        linux::fs::MetadataExt,
+        unix::{ffi::OsStrExt, fs::PermissionsExt},
+    },
+    path::Path,
+};
+
+/// The most bytes asked of each `copy_file_range(2)` call, which never copies
+/// more than about 2 GiB at once anyway.
+const COPY_CHUNK_SIZE: usize = 1 << 30;
+
+/// The metadata that [`CopyOptions`] can preserve, used to report the ones
+/// that could not be.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Preserve {
+    /// The access and modification times.
+    Timestamps,
+    /// The owner and the group.
+    Ownership,
+    /// The extended attributes, except the ones holding ACLs.
+    Xattrs,
+    /// The access ACL.
+    Acls,
+}
+
+/// Options which can be used to configure how a file is copied, like the
+/// options of `cp(1)`.
+///
+/// The `copy_with` function is an alias for [`CopyOptions::copy`].
+#[derive(Debug, Clone)]
+pub struct CopyOptions {
+    timestamps: bool,
+    ownership: bool,
+    xattrs: bool,
+    acls: bool,
+    dereference: bool,
+    no_clobber: bool,
+}
+
+impl CopyOptions {
+    /// Creates a blank new set of options.
+    ///
+    /// By default, like `copy`, only the contents and the permission bits are
+    /// copied, symbolic links are followed and the destination is overwritten
+    /// if it exists.
+    pub fn new() -> Self {
+        Self {
+            timestamps: false,
+            ownership: false,
+            xattrs: false,
+            acls: false,
+            dereference: true,
+            no_clobber: false,
+        }
+    }
+
+    /// Creates a set of options preserving everything, and copying symbolic
+    /// links as symbolic links, like `cp -a`.
+    pub fn archive() -> Self {
+        Self {
+            timestamps: true,
+            ownership: true,
+            xattrs: true,
+            acls: true,
+            dereference: false,
+            no_clobber: false,
+        }
+    }
+
+    /// Sets the option to preserve the access and modification times.
+    pub fn preserve_timestamps(&mut self, timestamps: bool) -> &mut Self {
+        self.timestamps = timestamps;
+        self
+    }
+
+    /// Sets the option to preserve the owner and the group, which usually
+    /// needs the `CAP_CHOWN` capability.
+    ///
+    /// The set-user-ID and set-group-ID bits are only copied when the
+    /// ownership is preserved.
+    pub fn preserve_ownership(&mut self, ownership: bool) -> &mut Self {
+        self.ownership = ownership;
+        self
+    }
+
+    /// Sets the option to preserve the extended attributes. Attributes of the
+    /// `trusted` and `security` namespaces can only be set with the
+    /// `CAP_SYS_ADMIN` capability.
+    pub fn preserve_xattrs(&mut self, xattrs: bool) -> &mut Self {
+        self.xattrs = xattrs;
+        self
+    }
+
+    /// Sets the option to preserve the access ACL.
+    pub fn preserve_acls(&mut self, acls: bool) -> &mut Self {
+        self.acls = acls;
+        self
+    }
+
+    /// Sets the option to copy a symbolic link as a symbolic link to the same
+    /// target, rather than copying the file it points to.
+    ///
+    /// This is the opposite of [`dereference`](CopyOptions::dereference).
+    pub fn preserve_symlinks(&mut self, symlinks: bool) -> &mut Self {
+        self.dereference = !symlinks;
+        self
+    }
+
+    /// Sets the option to follow a symbolic link and copy the file it points
+    /// to, which is the default.
+    pub fn dereference(&mut self, dereference: bool) -> &mut Self {
+        self.dereference = dereference;
+        self
+    }
+
+    /// Sets the option to never overwrite the destination, copying fails with
+    /// `ErrorKind::AlreadyExists` if it exists.
+    pub fn no_clobber(&mut self, no_clobber: bool) -> &mut Self {
+        self.no_clobber = no_clobber;
+        self
+    }
+
+    /// Copies the regular file or symbolic link `from` to `to` with the
+    /// options specified by self.
+    ///
+    /// Failing to preserve some metadata does not fail the copy, the failures
+    /// are listed in the returned [`CopyOutcome`].
+    pub fn copy<P: AsRef<Path>, Q: AsRef<Path>>(&self, from: P, to: Q) -> Result<CopyOutcome> {
+        let (from, to) = (from.as_ref(), to.as_ref());
+        if !self.dereference && symlink_metadata(from)?.is_symlink() {
+            return self.copy_symlink(from, to);
+        }
+
+        let source = File::open(from)?;
+        let metadata = source.metadata()?;
+        if !metadata.is_file() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "the source is neither a regular file nor a symlink",
+            ));
+        }
+
+        let mut options = OpenOptions::new();
+        options.write(true);
+        if self.no_clobber {
+            options.create_new(true);
+        } else {
+            options.create(true);
+        }
+        let mut dest = options.open(to)?;
+        let dest_metadata = dest.metadata()?;
+        if (dest_metadata.st_dev(), dest_metadata.st_ino())
+            == (metadata.st_dev(), metadata.st_ino())
+        {
+            return Err(same_file());
+        }
+        dest.set_len(0)?;
+
+        let bytes_copied = copy_data(&source, &mut dest)?;
+        let mut outcome = CopyOutcome {
+            bytes_copied,
+            failures: Vec::new(),
+        };
+
+        let mut mode = metadata.st_mode() & 0o777;
+        if self.ownership {
+            let res = fchown(&dest, Some(metadata.st_uid()), Some(metadata.st_gid()));
+            if res.is_ok() {
+                mode = metadata.st_mode() & 0o7777;
+            }
+            outcome.record(Preserve::Ownership, res);
+        }
+        // After changing the owner, which clears the set-user-ID and
+        // set-group-ID bits.
+        dest.set_permissions(Permissions::from_mode(mode))?;
+
+        if self.xattrs {
+            let res = copy_xattrs(
+                source.list_xattr(),
+                |name| source.get_xattr(name),
+                |name, value| dest.set_xattr(name, value, SetXattrFlags::empty()),
+            );
+            outcome.record(Preserve::Xattrs, res);
+        }
+        if self.acls {
+            let name = AclType::Access.xattr_name();
+            let res = match source.get_xattr(name) {
+                Ok(value) => dest.set_xattr(name, value, SetXattrFlags::empty()),
+                Err(e) if is_missing_xattr(&e) => Ok(()),
+                Err(e) => Err(e),
+            };
+            outcome.record(Preserve::Acls, res);
+        }
+        // Last, as writing the data updated them.
+        if self.timestamps {
+            let res = file_times(&metadata).and_then(|times| dest.set_times(times));
+            outcome.record(Preserve::Timestamps, res);
+        }
+
+        Ok(outcome)
+    }
+
+    fn copy_symlink(&self, from: &Path, to: &Path) -> Result<CopyOutcome> {
+        let metadata = symlink_metadata(from)?;
+        let target = read_link(from)?;
+        match symlink_metadata(to) {
+            Ok(dest)
+                if (dest.st_dev(), dest.st_ino()) == (metadata.st_dev(), metadata.st_ino()) =>
+            {
+                return Err(same_file());
+            }
+            Ok(dest) if !self.no_clobber && !dest.is_dir() => remove_file(to)?,
+            _ => {}
+        }
+        symlink(target, to)?;
+
+        let mut outcome = CopyOutcome {
+            bytes_copied: 0,
+            failures: Vec::new(),
+        };
+        if self.ownership {
+            let res = lchown(to, Some(metadata.st_uid()), Some(metadata.st_gid()));
+            outcome.record(Preserve::Ownership, res);
+        }
+        if self.xattrs {
+            let res = copy_xattrs(
+                list_symlink_xattr(from),
+                |name| get_symlink_xattr(from, name),
+                |name, value| set_symlink_xattr(to, name, value, SetXattrFlags::empty()),
+            );
+            outcome.record(Preserve::Xattrs, res);
+        }
+        // Symbolic links have no ACLs.
+        if self.timestamps {
+            let res = file_times(&metadata).and_then(|times| set_symlink_file_times(to, times));
+            outcome.record(Preserve::Timestamps, res);
+        }
+
+        Ok(outcome)
+    }
+}
+
+impl Default for CopyOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The result of a copy with [`CopyOptions`].
+#[derive(Debug)]
+pub struct CopyOutcome {
+    bytes_copied: u64,
+    failures: Vec<(Preserve, Error)>,
+}
+
+impl CopyOutcome {
+    /// Returns the number of bytes copied, 0 for a symbolic link.
+    #[inline]
+    pub fn bytes_copied(&self) -> u64 {
+        self.bytes_copied
+    }
+
+    /// Returns the metadata that could not be preserved, with the first error
+    /// hit for each of them.
+    #[inline]
+    pub fn failures(&self) -> &[(Preserve, Error)] {
+        &self.failures
+    }
+
+    /// Returns true if all the requested metadata has been preserved.
+    #[inline]
+    pub fn is_complete(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    fn record(&mut self, step: Preserve, res: Result<()>) {
+        if let Err(e) = res {
+            self.failures.push((step, e));
+        }
+    }
+}
+
+/// Copies the data from the offset of `source` to the offset of `dest`, in
+/// the kernel with `copy_file_range(2)`, which may share the extents on some
+/// filesystems, or with `read(2)` and `write(2)` where it fails, e.g., across
+/// filesystems before Linux 5.3.
+fn copy_data(source: &File, dest: &mut File) -> Result<u64> {
+    let mut copied = 0;
+    loop {
+        match encapsulation::copy_file_range(source, None, &*dest, None, COPY_CHUNK_SIZE) {
+            Ok(0) => return Ok(copied),
+            Ok(n) => copied += n as u64,
+            Err(_) => break,
+        }
+    }
+
+    // Both offsets are past what has been copied already.
+    Ok(copied + io::copy(&mut &*source, dest)?)
+}
+
+fn same_file() -> Error {
+    Error::new(
+        ErrorKind::InvalidInput,
+        "the source and the destination are the same file",
+    )
+}
+
+/// Returns true if `e` means that a file has no such extended attribute,
+/// or that its filesystem does not support them at all.
+fn is_missing_xattr(e: &Error) -> bool {
+    matches!(e.raw_os_error(), Some(libc::ENODATA | libc::EOPNOTSUPP))
+}
+
+/// Copies all the extended attributes but the ACLs, going on after a failure
+/// and returning the first one.
+fn copy_xattrs<N, G, S>(names: Result<N>, get: G, set: S) -> Result<()>
+where
+    N: Iterator,
+    N::Item: AsRef<OsStr>,
+    G: Fn(&OsStr) -> Result<Vec<u8>>,
+    S: Fn(&OsStr, Vec<u8>) -> Result<()>,
+{
+    let names = match names {
+        Ok(names) => names,
+        Err(e) if is_missing_xattr(&e) => return Ok(()),
+        Err(e) => return Err(e),
+    };
+
+    let mut first_error = None;
+    for name in names {
+        let name = name.as_ref();
+        if name.as_bytes().starts_with(b"system.posix_acl_") {
+            continue;
+        }
+        let res = match get(name) {
+            Ok(value) => set(name, value),
+            // Removed in the meantime.
+            Err(e) if e.raw_os_error() == Some(libc::ENODATA) => Ok(()),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = res {
+            first_error.get_or_insert(e);
+        }
+    }
+
+    first_error.map_or(Ok(()), Err)
+}
+
+fn file_times(metadata: &Metadata) -> Result<FileTimes> {
+    Ok(FileTimes::new()
+        .set_accessed(metadata.accessed()?)
+        .set_modified(metadata.modified()?))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        acl::{Acl, AclPerm, AclTag},
+        functions::{metadata, read_to_string, set_file_times, write},
+        non_fs::SystemTime,
+        temp::TempDir,
+        xattr::get_xattr,
+    };
+
+    #[test]
+    fn contents_and_permissions() {
+        let dir = TempDir::new().unwrap();
+        let (from, to) = (dir.path().join("from"), dir.path().join("to"));
+        write(&from, "hello").unwrap();
+        File::open(&from)
+            .unwrap()
+            .set_permissions(Permissions::from_mode(0o640))
+            .unwrap();
+        write(&to, "longer contents").unwrap();
+
+        let outcome = CopyOptions::new().copy(&from, &to).unwrap();
+        assert_eq!(outcome.bytes_copied(), 5);
+        assert!(outcome.is_complete());
+        assert_eq!(read_to_string(&to).unwrap(), "hello");
+        assert_eq!(metadata(&to).unwrap().st_mode() & 0o7777, 0o640);
+
+        let err = CopyOptions::new()
+            .no_clobber(true)
+            .copy(&from, &to)
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+        let err = CopyOptions::new().copy(&from, &from).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        assert_eq!(read_to_string(&from).unwrap(), "hello");
+        let err = CopyOptions::new().copy(dir.path(), &to).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn archive() {
+        let dir = TempDir::new().unwrap();
+        let (from, to) = (dir.path().join("from"), dir.path().join("to"));
+        write(&from, "hello").unwrap();
+        let source = File::open(&from).unwrap();
+        // Giving the file away needs CAP_CHOWN.
+        let root = nix::unistd::geteuid().is_root();
+        if root {
+            fchown(&source, Some(1234), Some(5678)).unwrap();
+        }
+        source
+            .set_permissions(Permissions::from_mode(0o4750))
+            .unwrap();
+        source
+            .set_xattr("user.origin", b"test", SetXattrFlags::empty())
+            .unwrap();
+        let mut acl = Acl::from_mode(0o750);
+        acl.set(AclTag::User(4321), AclPerm::READ);
+        acl.set(AclTag::Mask, AclPerm::READ | AclPerm::EXECUTE);
+        source.set_acl(AclType::Access, &acl).unwrap();
+        let times = FileTimes::new()
+            .set_accessed(SystemTime::new(1_000_000_000, 1))
+            .set_modified(SystemTime::new(1_500_000_000, 123_456_789));
+        set_file_times(&from, times).unwrap();
+
+        let outcome = CopyOptions::archive().copy(&from, &to).unwrap();
+        assert!(outcome.is_complete(), "{:?}", outcome.failures());
+
+        let (source, dest) = (metadata(&from).unwrap(), metadata(&to).unwrap());
+        assert_eq!(
+            (dest.st_uid(), dest.st_gid()),
+            (source.st_uid(), source.st_gid())
+        );
+        if root {
+            assert_eq!((dest.st_uid(), dest.st_gid()), (1234, 5678));
+        }
+        assert_eq!(dest.st_mode() & 0o7777, source.st_mode() & 0o7777);
+        assert_eq!(dest.st_mode() & libc::S_ISUID, libc::S_ISUID);
+        assert_eq!(get_xattr(&to, "user.origin").unwrap(), b"test");
+        assert_eq!(
+            File::open(&to).unwrap().get_acl(AclType::Access).unwrap(),
+            acl
+        );
+        // The source has been accessed by the copy since.
+        assert_eq!(dest.accessed().unwrap(), SystemTime::new(1_000_000_000, 1));
+        assert_eq!(dest.modified().unwrap(), source.modified().unwrap());
+    }
+
+    #[test]
+    fn symlinks() {
+        let dir = TempDir::new().unwrap();
+        let target = dir.path().join("target");
+        let (from, to) = (dir.path().join("from"), dir.path().join("to"));
+        write(&target, "hello").unwrap();
+        symlink("target", &from).unwrap();
+        write(&to, "clobbered").unwrap();
+
+        let outcome = CopyOptions::new()
+            .preserve_symlinks(true)
+            .preserve_timestamps(true)
+            .copy(&from, &to)
+            .unwrap();
+        assert_eq!(outcome.bytes_copied(), 0);
+        assert!(outcome.is_complete(), "{:?}", outcome.failures());
+        assert_eq!(read_link(&to).unwrap(), Path::new("target"));
+        assert_eq!(
+            symlink_metadata(&to).unwrap().modified().unwrap(),
+            symlink_metadata(&from).unwrap().modified().unwrap()
+        );
+
+        let err = CopyOptions::archive().copy(&to, &to).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        assert_eq!(read_link(&to).unwrap(), Path::new("target"));
+
+        remove_file(&to).unwrap();
+        let outcome = CopyOptions::archive()
+            .dereference(true)
+            .copy(&from, &to)
+            .unwrap();
+        assert_eq!(outcome.bytes_copied(), 5);
+        assert!(symlink_metadata(&to).unwrap().is_file());
+    }
+
+    #[test]
+    fn failures_are_reported() {
+        // Giving the file away needs CAP_CHOWN.
+        if !nix::unistd::geteuid().is_root() {
+            return;
+        }
+
+        let dir = TempDir::new().unwrap();
+        File::open(dir.path())
+            .unwrap()
+            .set_permissions(Permissions::from_mode(0o777))
+            .unwrap();
+        let (from, to) = (dir.path().join("from"), dir.path().join("to"));
+        write(&from, "hello").unwrap();
+        fchown(File::open(&from).unwrap(), Some(1234), None).unwrap();
+
+        // Without `CAP_CHOWN`, which is dropped along with the filesystem UID
+        // of this thread only, the owner can not be preserved.
+        let outcome = std::thread::spawn(move || {
+            nix::unistd::setfsuid(nix::unistd::Uid::from_raw(65534));
+            CopyOptions::archive().copy(&from, &to).unwrap()
+        })
+        .join()
+        .unwrap();
+
+        assert_eq!(outcome.bytes_copied(), 5);
+        let failed: Vec<Preserve> = outcome.failures().iter().map(|(step, _)| *step).collect();
+        assert_eq!(failed, [Preserve::Ownership]);
+        assert_eq!(outcome.failures()[0].1.kind(), ErrorKind::PermissionDenied);
+        assert_eq!(read_to_string(dir.path().join("to")).unwrap(), "hello");
+    }
+}
@@ -0,0 +1,113 @@
+use crate::backend::major_minor;
+use std::fmt;
+
+/// Returns the major ID of the device ID `dev`.
+#[inline]
+pub fn major(dev: u64) -> u32 {
+    major_minor::major(dev)
+}
+
+/// Returns the minor ID of the device ID `dev`.
+#[inline]
+pub fn minor(dev: u64) -> u32 {
+    major_minor::minor(dev)
+}
+
+/// Returns the device ID made of `major` and `minor`.
+#[inline]
+pub fn makedev(major: u32, minor: u32) -> u64 {
+    major_minor::makedev(major, minor)
+}
+
+/// A device ID, made of a major ID identifying the driver and a minor ID
+/// identifying the device among the ones of the driver.
+///
+/// It is displayed as `major:minor`, like in `/proc/self/mountinfo`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DeviceId {
+    major: u32,
+    minor: u32,
+}
+
+impl DeviceId {
+    /// Creates a device ID from its major and minor IDs.
+    #[inline]
+    pub fn new(major: u32, minor: u32) -> Self {
+        Self { major, minor }
+    }
+
+    /// Decodes the raw device ID `dev`, e.g., `st_rdev` of `stat(2)`.
+    #[inline]
+    pub fn from_raw(dev: u64) -> Self {
+        Self::new(major(dev), minor(dev))
+    }
+
+    /// Encodes the device ID as used by `stat(2)` and `mknod(2)`.
+    #[inline]
+    pub fn as_raw(&self) -> u64 {
+        makedev(self.major, self.minor)
+    }
+
+    /// Returns the major ID.
+    #[inline]
+    pub fn major(&self) -> u32 {
+        self.major
+    }
+
+    /// Returns the minor ID.
+    #[inline]
+    pub fn minor(&self) -> u32 {
+        self.minor
+    }
+}
+
+impl From<(u32, u32)> for DeviceId {
+    fn from((major, minor): (u32, u32)) -> Self {
+        Self::new(major, minor)
+    }
+}
+
+impl From<DeviceId> for (u32, u32) {
+    fn from(dev: DeviceId) -> Self {
+        (dev.major, dev.minor)
+    }
+}
+
+impl fmt::Display for DeviceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.major, self.minor)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn major_minor_match_nix() {
+        for (major, minor) in [
+            (0, 0),
+            (1, 3),
+            (8, 17),
+            (259, 65536),
+            (0xfff, 0xff),
+            (u32::MAX, u32::MAX),
+        ] {
+            let dev = makedev(major, minor);
+            assert_eq!(dev, nix::sys::stat::makedev(major as u64, minor as u64));
+            assert_eq!(self::major(dev), major);
+            assert_eq!(self::minor(dev), minor);
+
+            let id = DeviceId::from_raw(dev);
+            assert_eq!((id.major(), id.minor()), (major, minor));
+            assert_eq!(id.as_raw(), dev);
+        }
+    }
+
+    #[test]
+    fn display() {
+        let id = DeviceId::from((8, 1));
+        assert_eq!(id.to_string(), "8:1");
+        assert_eq!(<(u32, u32)>::from(id), (8, 1));
+    }
+}
@@ -58,7 +58,7 @@ impl DirEntry {
     #[inline]
     pub fn metadata(&self) -> io::Result<Metadata> {
         let path = self.0.path.as_path();
-        encapsulation::statx(path).map(|statx| Metadata(statx))
+        encapsulation::file_stat(path).map(Metadata)
     }
 
     /// Returns the file type for the file that this entry points at.
@@ -92,7 +92,7 @@ impl DirEntryExt for DirEntry {
 
 #[cfg(test)]
 mod test {
-    use tempdir::TempDir;
+    use crate::temp::Builder;
 
     #[test]
     fn iterating_over_root() {
@@ -112,7 +112,7 @@ mod test {
 
     #[test]
     fn empty_dir() {
-        let temp_dir = TempDir::new("test_empty_dir").unwrap();
+        let temp_dir = Builder::new().prefix("test_empty_dir").tempdir().unwrap();
         let temp_dir_path = temp_dir.path();
 
         let mut dir = crate::functions::read_dir(temp_dir_path).unwrap();
@@ -1,7 +1,10 @@
-use crate::backend::encapsulation::{mkdir, Mode};
+use crate::{
+    backend::encapsulation::{chmod, fchmod, mkdir, open, Flags, Mode},
+    umask::get_umask,
+};
 use std::{
     io::{Error, ErrorKind, Result},
-    os::unix::fs::DirBuilderExt,
+    os::unix::{fs::DirBuilderExt, io::AsRawFd},
     path::Path,
 };
 
@@ -10,6 +13,7 @@ use std::{
 pub struct DirBuilder {
     mode: Mode,
     recursive: bool,
+    exact_mode: bool,
 }
 
 impl DirBuilder {
@@ -19,6 +23,7 @@ impl DirBuilder {
         Self {
             mode: Mode::from_bits(0o777).unwrap(),
             recursive: false,
+            exact_mode: false,
         }
     }
 
@@ -30,13 +35,50 @@ impl DirBuilder {
         self
     }
 
+    /// Indicates that the created directories should get exactly the mode set
+    /// with `DirBuilderExt::mode`, regardless of the umask of the process.
+    ///
+    /// The mode is changed with `chmod(2)` right after the creation when the
+    /// umask removes some of its bits, directories that already exist are left
+    /// untouched.
+    pub fn exact_mode(&mut self, exact_mode: bool) -> &mut Self {
+        self.exact_mode = exact_mode;
+        self
+    }
+
     /// Creates the specified directory with the options configured in this
     /// builder.
     pub fn create<P: AsRef<Path>>(&self, path: P) -> Result<()> {
         if self.recursive {
             self.create_dir_all(path.as_ref())
         } else {
-            mkdir(path.as_ref(), self.mode)
+            mkdir(path.as_ref(), self.mode)?;
+            self.apply_exact_mode(path.as_ref())
+        }
+    }
+
+    /// Gives the directory `path`, which has just been created, the requested
+    /// mode if it has been masked.
+    ///
+    /// The directory is opened without following symlinks, so that the mode
+    /// of another file can not be changed if `path` has been replaced in the
+    /// meantime.
+    fn apply_exact_mode(&self, path: &Path) -> Result<()> {
+        if !self.exact_mode || self.mode.bits() & get_umask() == 0 {
+            return Ok(());
+        }
+
+        let flags = Flags::O_CLOEXEC | Flags::O_DIRECTORY | Flags::O_NOFOLLOW;
+        match open(path, flags | Flags::O_RDONLY, Mode::empty()) {
+            Ok(fd) => fchmod(fd, self.mode),
+            // The masked mode does not let us read the directory, `fchmod(2)`
+            // does not work on `O_PATH` descriptors but `chmod(2)` on their
+            // magic link refers to the very same directory.
+            Err(e) if e.kind() == ErrorKind::PermissionDenied => {
+                let fd = open(path, flags | Flags::O_PATH, Mode::empty())?;
+                chmod(format!("/proc/self/fd/{}", fd.as_raw_fd()), self.mode)
+            }
+            Err(e) => Err(e),
         }
     }
 
@@ -52,7 +94,7 @@ impl DirBuilder {
         }
 
         match mkdir(path, self.mode) {
-            Ok(()) => return Ok(()),
+            Ok(()) => return self.apply_exact_mode(path),
             // There is at least one parent directory that should be created first,
             // execute the next block.
             Err(ref e) if e.kind() == ErrorKind::NotFound => {}
@@ -70,7 +112,7 @@ impl DirBuilder {
             }
         }
         match mkdir(path, self.mode) {
-            Ok(()) => Ok(()),
+            Ok(()) => self.apply_exact_mode(path),
             Err(_) if path.is_dir() => Ok(()),
             Err(e) => Err(e),
         }
@@ -89,14 +131,6 @@ mod test {
     use super::*;
     use std::os::{linux::fs::MetadataExt, unix::fs::DirBuilderExt};
 
-    /// Get the umask used by the underlying file system.
-    #[inline]
-    fn get_umask() -> u32 {
-        let umask = nix::sys::stat::umask(nix::sys::stat::Mode::from_bits(0o22).unwrap());
-        nix::sys::stat::umask(umask);
-        umask.bits()
-    }
-
     #[test]
     fn mode() {
         let mode = 0o666_u32;
@@ -122,4 +156,53 @@ mod test {
         crate::functions::remove_dir("dir1/dir2").unwrap();
         crate::functions::remove_dir("dir1").unwrap();
     }
+
+    #[test]
+    fn exact_mode() {
+        let dir = "/tmp/test_dirbuilder_exact_mode";
+        DirBuilder::new()
+            .recursive(true)
+            .exact_mode(true)
+            .mode(0o777)
+            .create(format!("{}/sub", dir))
+            .unwrap();
+
+        for path in [dir.to_owned(), format!("{}/sub", dir)] {
+            let metadata = crate::functions::metadata(path).unwrap();
+            assert_eq!(metadata.st_mode() & 0o7777, 0o777);
+        }
+
+        crate::functions::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn exact_mode_unreadable() {
+        let dir = "/tmp/test_dirbuilder_exact_mode_unreadable";
+        DirBuilder::new()
+            .mode(0o777)
+            .exact_mode(true)
+            .create(dir)
+            .unwrap();
+
+        // The directory can not be opened for reading by its owner, if the
+        // umask masks any bit, and the owner is not privileged.
+        let path = format!("{}/sub", dir);
+        std::thread::spawn(move || {
+            nix::unistd::setfsuid(nix::unistd::Uid::from_raw(65534));
+            DirBuilder::new()
+                .mode(0o333)
+                .exact_mode(true)
+                .create(path)
+                .unwrap();
+        })
+        .join()
+        .unwrap();
+
+        let metadata = crate::functions::metadata(format!("{}/sub", dir)).unwrap();
+        assert_eq!(metadata.st_mode() & 0o7777, 0o333);
+
+        // Unprivileged users can not list it to remove it otherwise.
+        chmod(format!("{}/sub", dir), Mode::from_bits_truncate(0o755)).unwrap();
+        crate::functions::remove_dir_all(dir).unwrap();
+    }
 }
@@ -1,14 +1,26 @@
 use crate::{
-    backend::encapsulation, filetimes::FileTimes, functions::read_link, metadata::Metadata,
-    non_fs::SystemTime, open_option::OpenOptions, permissions::Permissions,
+    acl::{acl_from_xattr, Acl, AclType},
+    backend::encapsulation::{self, AtFlags, SyncFileRangeFlags},
+    filetimes::FileTimes,
+    fs_stats::FsStats,
+    functions::read_link,
+    inode_flags::{InodeFlags, RejectedInodeFlags},
+    metadata::Metadata,
+    non_fs::SystemTime,
+    open_option::OpenOptions,
+    permissions::Permissions,
+    xattr::{read_growing, SetXattrFlags, XattrNames},
 };
+use bitflags::bitflags;
 use std::{
+    ffi::OsStr,
     fmt::{self, Debug, Formatter},
-    io::{Read, Result, Seek, SeekFrom, Write},
+    io::{Error, ErrorKind, Read, Result, Seek, SeekFrom, Write},
     os::{
         fd::{BorrowedFd, FromRawFd, IntoRawFd, RawFd},
+        linux::fs::MetadataExt,
         unix::{
-            fs::FileExt,
+            fs::{FileExt, OpenOptionsExt},
             io::{AsFd, AsRawFd, OwnedFd},
         },
     },
@@ -16,6 +28,21 @@ use std::{
     process::Stdio,
 };
 
+bitflags! {
+    /// Flags used by [`File::sync_range`].
+    pub struct SyncRangeFlags: u32 {
+        /// Wait upon write-out of the pages in the range that have already been
+        /// submitted to the device driver before doing anything else.
+        const WAIT_BEFORE = libc::SYNC_FILE_RANGE_WAIT_BEFORE;
+        /// Initiate write-out of the dirty pages in the range that are not
+        /// presently submitted for write-out.
+        const WRITE = libc::SYNC_FILE_RANGE_WRITE;
+        /// Wait upon write-out of the pages in the range after performing any
+        /// write.
+        const WAIT_AFTER = libc::SYNC_FILE_RANGE_WAIT_AFTER;
+    }
+}
+
 /// An object providing access to an open file on the filesystem.
 ///
 /// An instance of a File can be read and/or written depending on what options
@@ -54,6 +81,56 @@ impl File {
             .open(path.as_ref())
     }
 
+    /// Creates an unnamed regular file in read-write mode inside the directory
+    /// `dir`.
+    ///
+    /// The file is created with `O_TMPFILE`, it has no name and will be removed
+    /// once it is closed, unless it is given a name with [`link_into`] first.
+    /// This makes it possible to fully write a file before it becomes visible
+    /// to others.
+    ///
+    /// [`link_into`]: File::link_into
+    pub fn create_unnamed_in<P: AsRef<Path>>(dir: P) -> Result<File> {
+        OpenOptions::new()
+            .read(true)
+            .write(true)
+            .custom_flags(libc::O_TMPFILE)
+            .open(dir.as_ref())
+    }
+
+    /// Links the underlying file into the filesystem at `path`.
+    ///
+    /// This is intended to be used with files created by [`create_unnamed_in`],
+    /// `path` must be on the same filesystem as the directory the file was
+    /// created in, and it must not exist.
+    ///
+    /// [`create_unnamed_in`]: File::create_unnamed_in
+    pub fn link_into<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        // `linkat(fd, "", AT_FDCWD, path, AT_EMPTY_PATH)` requires the
+        // `CAP_DAC_READ_SEARCH` capability, so we try the `/proc/self/fd/N`
+        // way first, which works for unprivileged processes as long as procfs
+        // is mounted.
+        let mut proc_path = PathBuf::from("/proc/self/fd");
+        proc_path.push(self.fd.as_raw_fd().to_string());
+
+        match encapsulation::linkat(
+            None,
+            &proc_path,
+            None,
+            path.as_ref(),
+            AtFlags::AT_SYMLINK_FOLLOW,
+        ) {
+            Err(e) if e.kind() == ErrorKind::NotFound => encapsulation::linkat(
+                Some(self.fd.as_fd()),
+                "",
+                None,
+                path.as_ref(),
+                AtFlags::AT_EMPTY_PATH,
+            ),
+            res => res,
+        }
+    }
+
     /// Returns a new OpenOptions object.
     #[inline]
     pub fn options() -> OpenOptions {
@@ -75,6 +152,135 @@ impl File {
         encapsulation::fdatasync(&self.fd.as_fd())
     }
 
+    /// Synchronizes the whole filesystem that contains the underlying file,
+    /// not only this file.
+    #[inline]
+    pub fn sync_filesystem(&self) -> Result<()> {
+        encapsulation::syncfs(self)
+    }
+
+    /// Initiates and/or waits for the write-out of the dirty pages in the
+    /// range `[offset, offset + len)` of the underlying file, `len` set to 0
+    /// means through to the end of the file.
+    ///
+    /// This is useful to pace writeback, but it provides no durability
+    /// guarantee: neither file metadata nor the disk write cache is flushed,
+    /// use [`sync_all`] or [`sync_data`] for that.
+    ///
+    /// [`sync_all`]: File::sync_all
+    /// [`sync_data`]: File::sync_data
+    #[inline]
+    pub fn sync_range(&self, offset: u64, len: u64, flags: SyncRangeFlags) -> Result<()> {
+        encapsulation::sync_file_range(
+            self,
+            offset,
+            len,
+            SyncFileRangeFlags::from_bits_truncate(flags.bits()),
+        )
+    }
+
+    /// Returns the inode flags of the underlying file, like `lsattr(1)`.
+    ///
+    /// Flags unknown to [`InodeFlags`] are not included. On filesystems
+    /// without inode flags, an error of kind `Unsupported` is returned.
+    #[inline]
+    pub fn inode_flags(&self) -> Result<InodeFlags> {
+        encapsulation::get_inode_flags(self)
+            .map(|flags| InodeFlags::from_bits_truncate(flags as u32))
+    }
+
+    /// Sets the inode flags of the underlying file, like `chattr(1)`.
+    ///
+    /// Flags unknown to [`InodeFlags`] are left unchanged. Setting or clearing
+    /// `IMMUTABLE` and `APPEND` requires the `CAP_LINUX_IMMUTABLE` capability.
+    ///
+    /// # Errors
+    /// If the filesystem rejects the request, e.g., because it does not
+    /// support some of the changed flags, an error of kind `Unsupported`
+    /// wrapping a [`RejectedInodeFlags`] with all the changed flags is
+    /// returned. The file is left unchanged.
+    pub fn set_inode_flags(&self, flags: InodeFlags) -> Result<()> {
+        let current = encapsulation::get_inode_flags(self)? as u32;
+        let new = (current & !InodeFlags::all().bits()) | flags.bits();
+
+        match encapsulation::set_inode_flags(self, new as libc::c_int) {
+            Err(e) if is_unsupported_flags_error(&e) => {
+                let changed = InodeFlags::from_bits_truncate(current ^ new);
+                if changed.is_empty() {
+                    Err(e)
+                } else {
+                    Err(Error::new(
+                        ErrorKind::Unsupported,
+                        RejectedInodeFlags(changed),
+                    ))
+                }
+            }
+            res => res,
+        }
+    }
+
+    /// Retrieves the value of the extended attribute `name` of the underlying
+    /// file.
+    pub fn get_xattr<N: AsRef<OsStr>>(&self, name: N) -> Result<Vec<u8>> {
+        let name = name.as_ref();
+        read_growing(|buf| encapsulation::fgetxattr(self, name, buf))
+    }
+
+    /// Sets the value of the extended attribute `name` of the underlying file.
+    #[inline]
+    pub fn set_xattr<N: AsRef<OsStr>, V: AsRef<[u8]>>(
+        &self,
+        name: N,
+        value: V,
+        flags: SetXattrFlags,
+    ) -> Result<()> {
+        encapsulation::fsetxattr(self, name, value.as_ref(), flags.into())
+    }
+
+    /// Returns an iterator over the names of the extended attributes of the
+    /// underlying file.
+    pub fn list_xattr(&self) -> Result<XattrNames> {
+        read_growing(|buf| encapsulation::flistxattr(self, buf)).map(XattrNames::new)
+    }
+
+    /// Removes the extended attribute `name` of the underlying file.
+    #[inline]
+    pub fn remove_xattr<N: AsRef<OsStr>>(&self, name: N) -> Result<()> {
+        encapsulation::fremovexattr(self, name)
+    }
+
+    /// Retrieves the ACL of type `acl_type` of the underlying file, see
+    /// [`get_acl`](crate::get_acl).
+    pub fn get_acl(&self, acl_type: AclType) -> Result<Acl> {
+        let value = read_growing(|buf| encapsulation::fgetxattr(self, acl_type.xattr_name(), buf));
+        acl_from_xattr(value, acl_type, || {
+            self.metadata().map(|metadata| metadata.st_mode())
+        })
+    }
+
+    /// Sets the ACL of type `acl_type` of the underlying file, see
+    /// [`set_acl`](crate::set_acl).
+    pub fn set_acl(&self, acl_type: AclType, acl: &Acl) -> Result<()> {
+        if acl_type == AclType::Default && acl.is_empty() {
+            return match self.remove_xattr(acl_type.xattr_name()) {
+                Err(e) if e.raw_os_error() == Some(libc::ENODATA) => Ok(()),
+                res => res,
+            };
+        }
+
+        self.set_xattr(
+            acl_type.xattr_name(),
+            acl.to_bytes(),
+            SetXattrFlags::empty(),
+        )
+    }
+
+    /// Returns statistics about the filesystem containing the underlying file.
+    #[inline]
+    pub fn fs_stats(&self) -> Result<FsStats> {
+        encapsulation::fstatfs(self).map(FsStats)
+    }
+
     /// Truncates or extends the underlying file, updating the size of
     /// this file to become `size`.
     #[inline]
@@ -85,8 +291,7 @@ impl File {
     /// Queries metadata about the underlying file.
     #[inline]
     pub fn metadata(&self) -> Result<Metadata> {
-        let statx = encapsulation::fstatx(&self.fd.as_fd())?;
-        Ok(Metadata(statx))
+        encapsulation::file_fstat(self).map(Metadata)
     }
 
     /// Creates a new `File` instance that shares the same underlying file handle
@@ -110,11 +315,7 @@ impl File {
     /// Changes the timestamps of the underlying file.
     #[inline]
     pub fn set_times(&self, times: FileTimes) -> Result<()> {
-        encapsulation::futimens(
-            &self.fd.as_fd(),
-            &encapsulation::TimestampSpec::Set(times.0[0]),
-            &encapsulation::TimestampSpec::Set(times.0[1]),
-        )
+        encapsulation::futimens(&self.fd.as_fd(), &times.0[0], &times.0[1])
     }
 
     /// Changes the modification time of the underlying file.
@@ -258,10 +459,18 @@ impl Seek for File {
     }
 }
 
+/// Filesystems reject inode flags they don't support with either
+/// `EOPNOTSUPP` or `EINVAL`.
+fn is_unsupported_flags_error(e: &Error) -> bool {
+    matches!(
+        e.raw_os_error(),
+        Some(libc::EOPNOTSUPP) | Some(libc::EINVAL)
+    )
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
-    use std::io::ErrorKind;
 
     #[test]
     fn open() {
@@ -276,6 +485,22 @@ mod test {
         crate::functions::remove_file(name).unwrap();
     }
 
+    #[test]
+    fn create_unnamed_in_and_link_into() {
+        let name = "file_create_unnamed_in_and_link_into";
+        let mut file = File::create_unnamed_in(".").unwrap();
+        file.write_all(b"hello").unwrap();
+        assert!(!crate::functions::try_exists(name).unwrap());
+
+        file.link_into(name).unwrap();
+        assert_eq!(crate::functions::read(name).unwrap(), b"hello");
+
+        let err = file.link_into(name).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+
+        crate::functions::remove_file(name).unwrap();
+    }
+
     #[test]
     fn create_new_already_exists() {
         let err = File::create_new("Cargo.toml").unwrap_err();
@@ -320,6 +545,73 @@ mod test {
         crate::functions::remove_file(name).unwrap();
     }
 
+    #[test]
+    fn sync_filesystem() {
+        let file = File::open("Cargo.toml").unwrap();
+        file.sync_filesystem().unwrap();
+    }
+
+    #[test]
+    fn sync_range() {
+        let name = "file_sync_range";
+        let mut file = File::create_new(name).unwrap();
+        file.write_all(b"hello").unwrap();
+        file.sync_range(0, 0, SyncRangeFlags::all()).unwrap();
+        file.sync_range(1, 3, SyncRangeFlags::WRITE).unwrap();
+
+        crate::functions::remove_file(name).unwrap();
+    }
+
+    #[test]
+    fn inode_flags() {
+        let name = "file_inode_flags";
+        let file = File::create_new(name).unwrap();
+
+        let flags = match file.inode_flags() {
+            Ok(flags) => flags,
+            // Not every filesystem supports inode flags.
+            Err(e) => {
+                assert_eq!(e.kind(), ErrorKind::Unsupported);
+                crate::functions::remove_file(name).unwrap();
+                return;
+            }
+        };
+
+        file.set_inode_flags(flags | InodeFlags::NODUMP).unwrap();
+        assert!(file.inode_flags().unwrap().contains(InodeFlags::NODUMP));
+        file.set_inode_flags(flags).unwrap();
+        assert_eq!(file.inode_flags().unwrap(), flags);
+
+        if let Err(e) = file.set_inode_flags(flags | InodeFlags::NOCOW | InodeFlags::NODUMP) {
+            assert_eq!(e.kind(), ErrorKind::Unsupported);
+            let rejected = e
+                .get_ref()
+                .and_then(|e| e.downcast_ref::<RejectedInodeFlags>())
+                .unwrap()
+                .flags();
+            // All the changed flags, and only them.
+            assert_eq!(rejected, (InodeFlags::NOCOW | InodeFlags::NODUMP) - flags);
+            assert_eq!(file.inode_flags().unwrap(), flags);
+        }
+
+        crate::functions::remove_file(name).unwrap();
+    }
+
+    #[test]
+    fn xattr() {
+        let name = "file_xattr";
+        let file = File::create_new(name).unwrap();
+
+        file.set_xattr("user.a", b"1", SetXattrFlags::CREATE)
+            .unwrap();
+        assert_eq!(file.get_xattr("user.a").unwrap(), b"1");
+        assert!(file.list_xattr().unwrap().any(|name| name == "user.a"));
+        file.remove_xattr("user.a").unwrap();
+        assert!(file.get_xattr("user.a").is_err());
+
+        crate::functions::remove_file(name).unwrap();
+    }
+
     #[test]
     fn set_len() {
         let name = "file_set_len";
@@ -361,7 +653,9 @@ mod test {
         let name = "file_set_times";
 
         let file = File::create_new(name).unwrap();
-        let file_times = FileTimes::new();
+        let file_times = FileTimes::new()
+            .set_accessed(SystemTime::default())
+            .set_modified(SystemTime::default());
         file.set_times(file_times).unwrap();
 
         let atime = file.metadata().unwrap().accessed().unwrap();
@@ -371,4 +665,27 @@ mod test {
 
         crate::functions::remove_file(name).unwrap();
     }
+
+    #[test]
+    fn set_times_omit() {
+        let name = "file_set_times_omit";
+
+        let file = File::create_new(name).unwrap();
+        let atime = SystemTime::new(1, 2);
+        file.set_times(FileTimes::new().set_accessed(atime))
+            .unwrap();
+
+        // Unset timestamps are left unchanged.
+        file.set_modified(SystemTime::new(3, 4)).unwrap();
+        assert_eq!(file.metadata().unwrap().accessed().unwrap(), atime);
+
+        file.set_times(FileTimes::new().set_modified_now()).unwrap();
+        assert_eq!(file.metadata().unwrap().accessed().unwrap(), atime);
+        assert_ne!(
+            file.metadata().unwrap().modified().unwrap(),
+            SystemTime::new(3, 4)
+        );
+
+        crate::functions::remove_file(name).unwrap();
+    }
 }
@@ -0,0 +1,441 @@
+//! File capabilities, stored in the `security.capability` extended attribute.
+
+use crate::xattr::{get_xattr, remove_xattr, set_xattr, SetXattrFlags};
+use bitflags::bitflags;
+use std::{
+    io::{Error, ErrorKind, Result},
+    path::Path,
+};
+
+const XATTR_NAME: &str = "security.capability";
+
+const VFS_CAP_REVISION_MASK: u32 = 0xff00_0000;
+const VFS_CAP_REVISION_1: u32 = 0x0100_0000;
+const VFS_CAP_REVISION_2: u32 = 0x0200_0000;
+const VFS_CAP_REVISION_3: u32 = 0x0300_0000;
+const VFS_CAP_FLAGS_EFFECTIVE: u32 = 0x0000_0001;
+
+/// Sizes of `struct vfs_cap_data` (`vfs_ns_cap_data` for revision 3).
+const XATTR_CAPS_SZ_1: usize = 4 + 8;
+const XATTR_CAPS_SZ_2: usize = 4 + 2 * 8;
+const XATTR_CAPS_SZ_3: usize = 4 + 2 * 8 + 4;
+
+bitflags! {
+    /// A set of capabilities, see `capabilities(7)`.
+    pub struct CapSet: u64 {
+        const CHOWN = 1 << 0;
+        const DAC_OVERRIDE = 1 << 1;
+        const DAC_READ_SEARCH = 1 << 2;
+        const FOWNER = 1 << 3;
+        const FSETID = 1 << 4;
+        const KILL = 1 << 5;
+        const SETGID = 1 << 6;
+        const SETUID = 1 << 7;
+        const SETPCAP = 1 << 8;
+        const LINUX_IMMUTABLE = 1 << 9;
+        const NET_BIND_SERVICE = 1 << 10;
+        const NET_BROADCAST = 1 << 11;
+        const NET_ADMIN = 1 << 12;
+        const NET_RAW = 1 << 13;
+        const IPC_LOCK = 1 << 14;
+        const IPC_OWNER = 1 << 15;
+        const SYS_MODULE = 1 << 16;
+        const SYS_RAWIO = 1 << 17;
+        const SYS_CHROOT = 1 << 18;
+        const SYS_PTRACE = 1 << 19;
+        const SYS_PACCT = 1 << 20;
+        const SYS_ADMIN = 1 << 21;
+        const SYS_BOOT = 1 << 22;
+        const SYS_NICE = 1 << 23;
+        const SYS_RESOURCE = 1 << 24;
+        const SYS_TIME = 1 << 25;
+        const SYS_TTY_CONFIG = 1 << 26;
+        const MKNOD = 1 << 27;
+        const LEASE = 1 << 28;
+        const AUDIT_WRITE = 1 << 29;
+        const AUDIT_CONTROL = 1 << 30;
+        const SETFCAP = 1 << 31;
+        const MAC_OVERRIDE = 1 << 32;
+        const MAC_ADMIN = 1 << 33;
+        const SYSLOG = 1 << 34;
+        const WAKE_ALARM = 1 << 35;
+        const BLOCK_SUSPEND = 1 << 36;
+        const AUDIT_READ = 1 << 37;
+        const PERFMON = 1 << 38;
+        const BPF = 1 << 39;
+        const CHECKPOINT_RESTORE = 1 << 40;
+    }
+}
+
+/// Names of the capabilities, indexed by their numbers.
+const CAP_NAMES: [&str; 41] = [
+    "cap_chown",
+    "cap_dac_override",
+    "cap_dac_read_search",
+    "cap_fowner",
+    "cap_fsetid",
+    "cap_kill",
+    "cap_setgid",
+    "cap_setuid",
+    "cap_setpcap",
+    "cap_linux_immutable",
+    "cap_net_bind_service",
+    "cap_net_broadcast",
+    "cap_net_admin",
+    "cap_net_raw",
+    "cap_ipc_lock",
+    "cap_ipc_owner",
+    "cap_sys_module",
+    "cap_sys_rawio",
+    "cap_sys_chroot",
+    "cap_sys_ptrace",
+    "cap_sys_pacct",
+    "cap_sys_admin",
+    "cap_sys_boot",
+    "cap_sys_nice",
+    "cap_sys_resource",
+    "cap_sys_time",
+    "cap_sys_tty_config",
+    "cap_mknod",
+    "cap_lease",
+    "cap_audit_write",
+    "cap_audit_control",
+    "cap_setfcap",
+    "cap_mac_override",
+    "cap_mac_admin",
+    "cap_syslog",
+    "cap_wake_alarm",
+    "cap_block_suspend",
+    "cap_audit_read",
+    "cap_perfmon",
+    "cap_bpf",
+    "cap_checkpoint_restore",
+];
+
+impl CapSet {
+    /// Returns the capability named `name`, e.g., `cap_net_bind_service`,
+    /// case-insensitively.
+    pub fn from_name(name: &str) -> Option<CapSet> {
+        CAP_NAMES
+            .iter()
+            .position(|cap| cap.eq_ignore_ascii_case(name))
+            .map(|idx| CapSet::from_bits_truncate(1 << idx))
+    }
+}
+
+/// The capabilities attached to an executable file, see `capabilities(7)`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FileCapabilities {
+    /// Capabilities permitted to the program regardless of the inheritable
+    /// capabilities of the thread.
+    pub permitted: CapSet,
+    /// Capabilities ANDed with the inheritable capabilities of the thread.
+    pub inheritable: CapSet,
+    /// If set, all the new permitted capabilities are raised in the effective
+    /// set of the thread.
+    pub effective: bool,
+    /// The root user ID of the user namespace the capabilities apply to, only
+    /// stored in revision 3 of the format.
+    pub root_id: Option<u32>,
+}
+
+impl FileCapabilities {
+    /// Creates empty file capabilities.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            permitted: CapSet::empty(),
+            inheritable: CapSet::empty(),
+            effective: false,
+            root_id: None,
+        }
+    }
+
+    /// Parses file capabilities in the kernel's `vfs_cap_data` format, all
+    /// the revisions (1, 2 and 3) are supported.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let u32_at = |idx: usize| -> Option<u32> {
+            bytes
+                .get(idx * 4..idx * 4 + 4)
+                .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+        };
+
+        let magic_etc = u32_at(0).ok_or_else(|| invalid("invalid capability size"))?;
+        let (expected_size, words) = match magic_etc & VFS_CAP_REVISION_MASK {
+            VFS_CAP_REVISION_1 => (XATTR_CAPS_SZ_1, 1),
+            VFS_CAP_REVISION_2 => (XATTR_CAPS_SZ_2, 2),
+            VFS_CAP_REVISION_3 => (XATTR_CAPS_SZ_3, 2),
+            revision => {
+                return Err(invalid(format!(
+                    "unsupported capability revision {:#x}",
+                    revision >> 24
+                )))
+            }
+        };
+        if bytes.len() != expected_size {
+            return Err(invalid("invalid capability size"));
+        }
+
+        let (mut permitted, mut inheritable) = (0_u64, 0_u64);
+        for word in 0..words {
+            permitted |= (u32_at(1 + word * 2).unwrap() as u64) << (32 * word);
+            inheritable |= (u32_at(2 + word * 2).unwrap() as u64) << (32 * word);
+        }
+
+        Ok(Self {
+            permitted: CapSet::from_bits_truncate(permitted),
+            inheritable: CapSet::from_bits_truncate(inheritable),
+            effective: magic_etc & VFS_CAP_FLAGS_EFFECTIVE != 0,
+            root_id: (magic_etc & VFS_CAP_REVISION_MASK == VFS_CAP_REVISION_3)
+                .then(|| u32_at(5).unwrap()),
+        })
+    }
+
+    /// Serializes file capabilities in the kernel's `vfs_cap_data` format,
+    /// revision 3 is used if `root_id` is set, revision 2 otherwise.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let revision = if self.root_id.is_some() {
+            VFS_CAP_REVISION_3
+        } else {
+            VFS_CAP_REVISION_2
+        };
+        let magic_etc = if self.effective {
+            revision | VFS_CAP_FLAGS_EFFECTIVE
+        } else {
+            revision
+        };
+        let (permitted, inheritable) = (self.permitted.bits(), self.inheritable.bits());
+
+        let mut bytes = Vec::with_capacity(XATTR_CAPS_SZ_3);
+        bytes.extend_from_slice(&magic_etc.to_le_bytes());
+        for word in 0..2 {
+            bytes.extend_from_slice(&((permitted >> (32 * word)) as u32).to_le_bytes());
+            bytes.extend_from_slice(&((inheritable >> (32 * word)) as u32).to_le_bytes());
+        }
+        if let Some(root_id) = self.root_id {
+            bytes.extend_from_slice(&root_id.to_le_bytes());
+        }
+
+        bytes
+    }
+
+    /// Parses file capabilities in the text form used by `setcap(8)`, e.g.,
+    /// `cap_net_bind_service=ep` or `cap_net_raw,cap_net_admin+ep cap_chown=i`.
+    ///
+    /// Clauses are separated by whitespace. Each one has a comma separated list
+    /// of capability names (`all` or none for all of them) followed by one or
+    /// more operators (`=`, `+` or `-`) and flags (`e`, `i` or `p`). As the
+    /// effective set of a file is a single bit, it must be either empty or
+    /// contain all the permitted and inheritable capabilities.
+    pub fn from_text(text: &str) -> Result<Self> {
+        let (mut e, mut i, mut p) = (CapSet::empty(), CapSet::empty(), CapSet::empty());
+
+        for clause in text.split_whitespace() {
+            let op_idx = clause
+                .find(['=', '+', '-'])
+                .ok_or_else(|| invalid(format!("missing operator in '{}'", clause)))?;
+            let (names, mut actions) = clause.split_at(op_idx);
+
+            let mut caps = CapSet::empty();
+            if names.is_empty() || names.eq_ignore_ascii_case("all") {
+                caps = CapSet::all();
+            } else {
+                for name in names.split(',') {
+                    caps |= CapSet::from_name(name)
+                        .ok_or_else(|| invalid(format!("unknown capability '{}'", name)))?;
+                }
+            }
+
+            while let Some(op) = actions.chars().next() {
+                let flags_end = actions[1..]
+                    .find(['=', '+', '-'])
+                    .map_or(actions.len(), |idx| idx + 1);
+                let flags = &actions[1..flags_end];
+                actions = &actions[flags_end..];
+
+                if op == '=' {
+                    e.remove(caps);
+                    i.remove(caps);
+                    p.remove(caps);
+                }
+                for flag in flags.chars() {
+                    let set = match flag {
+                        'e' => &mut e,
+                        'i' => &mut i,
+                        'p' => &mut p,
+                        _ => return Err(invalid(format!("invalid capability flag '{}'", flag))),
+                    };
+                    if op == '-' {
+                        set.remove(caps);
+                    } else {
+                        set.insert(caps);
+                    }
+                }
+            }
+        }
+
+        if !e.is_empty() && e != p | i {
+            return Err(invalid(
+                "the effective set of a file must contain all or none of its capabilities",
+            ));
+        }
+
+        Ok(Self {
+            permitted: p,
+            inheritable: i,
+            effective: !e.is_empty(),
+            root_id: None,
+        })
+    }
+
+    /// Returns the text form used by `getcap(8)`, e.g.,
+    /// `cap_net_bind_service=ep`.
+    ///
+    /// Capabilities with the same flags are grouped into one clause, empty
+    /// capabilities are represented by `=`.
+    pub fn to_text(&self) -> String {
+        // (flags, names) in the order of the first capability with the flags
+        let mut clauses: Vec<(String, Vec<&str>)> = Vec::new();
+
+        for (idx, name) in CAP_NAMES.iter().enumerate() {
+            let cap = CapSet::from_bits_truncate(1 << idx);
+            let (i, p) = (self.inheritable.contains(cap), self.permitted.contains(cap));
+            let e = self.effective && (i || p);
+
+            let flags: String = [(e, 'e'), (i, 'i'), (p, 'p')]
+                .iter()
+                .filter(|(set, _)| *set)
+                .map(|(_, flag)| *flag)
+                .collect();
+            if flags.is_empty() {
+                continue;
+            }
+
+            match clauses.iter_mut().find(|(f, _)| *f == flags) {
+                Some((_, names)) => names.push(name),
+                None => clauses.push((flags, vec![name])),
+            }
+        }
+
+        if clauses.is_empty() {
+            return String::from("=");
+        }
+        clauses
+            .iter()
+            .map(|(flags, names)| format!("{}={}", names.join(","), flags))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl Default for FileCapabilities {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn invalid<E: Into<Box<dyn std::error::Error + Send + Sync>>>(error: E) -> Error {
+    Error::new(ErrorKind::InvalidData, error)
+}
+
+/// Retrieves the capabilities of a file, `None` if it has no capabilities.
+pub fn get_file_caps<P: AsRef<Path>>(path: P) -> Result<Option<FileCapabilities>> {
+    match get_xattr(path, XATTR_NAME) {
+        Ok(bytes) => FileCapabilities::from_bytes(&bytes).map(Some),
+        Err(e) if e.raw_os_error() == Some(libc::ENODATA) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Sets the capabilities of a file, which requires the `CAP_SETFCAP`
+/// capability.
+pub fn set_file_caps<P: AsRef<Path>>(path: P, caps: &FileCapabilities) -> Result<()> {
+    set_xattr(path, XATTR_NAME, caps.to_bytes(), SetXattrFlags::empty())
+}
+
+/// Removes the capabilities of a file, which requires the `CAP_SETFCAP`
+/// capability.
+pub fn remove_file_caps<P: AsRef<Path>>(path: P) -> Result<()> {
+    remove_xattr(path, XATTR_NAME)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{file::File, temp::TempDir};
+
+    #[test]
+    fn bytes() {
+        let caps = FileCapabilities {
+            permitted: CapSet::NET_BIND_SERVICE | CapSet::CHECKPOINT_RESTORE,
+            inheritable: CapSet::CHOWN,
+            effective: true,
+            root_id: None,
+        };
+        let bytes = caps.to_bytes();
+        assert_eq!(
+            bytes,
+            [1, 0, 0, 2, 0, 4, 0, 0, 1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0]
+        );
+        assert_eq!(FileCapabilities::from_bytes(&bytes).unwrap(), caps);
+
+        let caps = FileCapabilities {
+            root_id: Some(1000),
+            ..caps
+        };
+        let bytes = caps.to_bytes();
+        assert_eq!(bytes.len(), XATTR_CAPS_SZ_3);
+        assert_eq!(bytes[3], 3);
+        assert_eq!(FileCapabilities::from_bytes(&bytes).unwrap(), caps);
+
+        let v1 = [0, 0, 0, 1, 0, 4, 0, 0, 0, 0, 0, 0];
+        assert_eq!(
+            FileCapabilities::from_bytes(&v1).unwrap().permitted,
+            CapSet::NET_BIND_SERVICE
+        );
+        assert!(FileCapabilities::from_bytes(&bytes[..20]).is_err());
+        assert!(FileCapabilities::from_bytes(&[0, 0, 0, 4]).is_err());
+    }
+
+    #[test]
+    fn text() {
+        let caps = FileCapabilities::from_text("cap_net_bind_service=ep").unwrap();
+        assert_eq!(caps.permitted, CapSet::NET_BIND_SERVICE);
+        assert!(caps.effective);
+        assert_eq!(caps.to_text(), "cap_net_bind_service=ep");
+
+        let caps = FileCapabilities::from_text("cap_net_raw,CAP_NET_ADMIN+p cap_chown=i").unwrap();
+        assert_eq!(caps.permitted, CapSet::NET_RAW | CapSet::NET_ADMIN);
+        assert_eq!(caps.inheritable, CapSet::CHOWN);
+        assert!(!caps.effective);
+        assert_eq!(caps.to_text(), "cap_chown=i cap_net_admin,cap_net_raw=p");
+
+        let caps = FileCapabilities::from_text("all=ep cap_sys_admin-ep").unwrap();
+        assert_eq!(caps.permitted, CapSet::all() - CapSet::SYS_ADMIN);
+        assert_eq!(FileCapabilities::from_text("=").unwrap().to_text(), "=");
+
+        assert!(FileCapabilities::from_text("cap_chown").is_err());
+        assert!(FileCapabilities::from_text("cap_foo=p").is_err());
+        assert!(FileCapabilities::from_text("cap_chown=x").is_err());
+        assert!(FileCapabilities::from_text("cap_chown=p cap_kill=ep").is_err());
+    }
+
+    #[test]
+    fn get_set_remove() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("file");
+        File::create(&path).unwrap();
+        assert_eq!(get_file_caps(&path).unwrap(), None);
+
+        let caps = FileCapabilities::from_text("cap_net_bind_service=ep").unwrap();
+        match set_file_caps(&path, &caps) {
+            // needs CAP_SETFCAP
+            Err(e) if e.kind() == ErrorKind::PermissionDenied => return,
+            res => res.unwrap(),
+        }
+        assert_eq!(get_file_caps(&path).unwrap(), Some(caps));
+
+        remove_file_caps(&path).unwrap();
+        assert_eq!(get_file_caps(&path).unwrap(), None);
+    }
+}
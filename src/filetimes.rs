@@ -1,8 +1,10 @@
-use crate::non_fs::SystemTime;
+use crate::{backend::encapsulation::TimestampSpec, non_fs::SystemTime};
 
 /// Representation of the various timestamps on a file.
+///
+/// Timestamps that are not set will be left unchanged.
 #[derive(Copy, Clone, Debug, Default)]
-pub struct FileTimes(pub(crate) [SystemTime; 2]);
+pub struct FileTimes(pub(crate) [TimestampSpec; 2]);
 
 impl FileTimes {
     pub fn new() -> Self {
@@ -11,13 +13,25 @@ impl FileTimes {
 
     /// Set the last access time of a file.
     pub fn set_accessed(mut self, t: SystemTime) -> Self {
-        self.0[0] = t;
+        self.0[0] = TimestampSpec::Set(t);
         self
     }
 
     /// Set the last modified time of a file.
     pub fn set_modified(mut self, t: SystemTime) -> Self {
-        self.0[1] = t;
+        self.0[1] = TimestampSpec::Set(t);
+        self
+    }
+
+    /// Set the last access time of a file to the current time.
+    pub fn set_accessed_now(mut self) -> Self {
+        self.0[0] = TimestampSpec::SetToNow;
+        self
+    }
+
+    /// Set the last modified time of a file to the current time.
+    pub fn set_modified_now(mut self) -> Self {
+        self.0[1] = TimestampSpec::SetToNow;
         self
     }
 }
@@ -26,19 +40,33 @@ impl FileTimes {
 mod test {
     use super::*;
 
+    #[test]
+    fn new() {
+        let file_times = FileTimes::new();
+        assert_eq!(file_times.0[0], TimestampSpec::Omit);
+        assert_eq!(file_times.0[1], TimestampSpec::Omit);
+    }
+
     #[test]
     fn set_accessed() {
         let mut file_times = FileTimes::new();
         file_times = file_times.set_accessed(SystemTime::new(1, 2));
-        assert_eq!(file_times.0[0].sec, 1);
-        assert_eq!(file_times.0[0].nsec, 2);
+        assert_eq!(file_times.0[0], TimestampSpec::Set(SystemTime::new(1, 2)));
+        assert_eq!(file_times.0[1], TimestampSpec::Omit);
     }
 
     #[test]
     fn set_modified() {
         let mut file_times = FileTimes::new();
         file_times = file_times.set_modified(SystemTime::new(1, 2));
-        assert_eq!(file_times.0[1].sec, 1);
-        assert_eq!(file_times.0[1].nsec, 2);
+        assert_eq!(file_times.0[0], TimestampSpec::Omit);
+        assert_eq!(file_times.0[1], TimestampSpec::Set(SystemTime::new(1, 2)));
+    }
+
+    #[test]
+    fn set_now() {
+        let file_times = FileTimes::new().set_accessed_now().set_modified_now();
+        assert_eq!(file_times.0[0], TimestampSpec::SetToNow);
+        assert_eq!(file_times.0[1], TimestampSpec::SetToNow);
     }
 }
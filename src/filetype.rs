@@ -1,5 +1,5 @@
 use crate::backend::encapsulation;
-use std::os::unix::fs::FileTypeExt;
+use std::{fmt, os::unix::fs::FileTypeExt};
 
 /// A structure representing a type of file with accessors for each file type.
 /// It is returned by `Metadata::file_type` method.
@@ -7,6 +7,17 @@ use std::os::unix::fs::FileTypeExt;
 pub struct FileType(pub(crate) encapsulation::FileType);
 
 impl FileType {
+    /// A regular file, which [`mknod`](crate::mknod) can create.
+    pub const FILE: FileType = FileType(encapsulation::FileType::RegularFile);
+    /// A FIFO (named pipe).
+    pub const FIFO: FileType = FileType(encapsulation::FileType::Fifo);
+    /// A UNIX domain socket file.
+    pub const SOCKET: FileType = FileType(encapsulation::FileType::Socket);
+    /// A character device.
+    pub const CHAR_DEVICE: FileType = FileType(encapsulation::FileType::CharDev);
+    /// A block device.
+    pub const BLOCK_DEVICE: FileType = FileType(encapsulation::FileType::BlkDev);
+
     /// Tests whether this file type represents a directory. The result is
     /// mutually exclusive to the results of `is_file` and `is_symlink`; only
     /// zero or one of these tests may pass.
@@ -65,6 +76,24 @@ impl FileTypeExt for FileType {
     }
 }
 
+/// Formats the file type as the leading character of `ls -l`: `-` for regular
+/// files, `d`, `l`, `p`, `s`, `c` and `b` for directories, symlinks, FIFOs,
+/// sockets, character and block devices.
+impl fmt::Display for FileType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let c = match self.0 {
+            encapsulation::FileType::RegularFile => '-',
+            encapsulation::FileType::Directory => 'd',
+            encapsulation::FileType::Symlink => 'l',
+            encapsulation::FileType::Fifo => 'p',
+            encapsulation::FileType::Socket => 's',
+            encapsulation::FileType::CharDev => 'c',
+            encapsulation::FileType::BlkDev => 'b',
+        };
+        write!(f, "{}", c)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -127,4 +156,15 @@ mod test {
             assert!(metadata.file_type().is_char_device());
         }
     }
+
+    #[test]
+    fn display() {
+        assert_eq!(FileType::FILE.to_string(), "-");
+        assert_eq!(FileType::FIFO.to_string(), "p");
+        assert_eq!(FileType::SOCKET.to_string(), "s");
+        assert_eq!(FileType::CHAR_DEVICE.to_string(), "c");
+        assert_eq!(FileType::BLOCK_DEVICE.to_string(), "b");
+        let cwd = File::open(".").unwrap();
+        assert_eq!(cwd.metadata().unwrap().file_type().to_string(), "d");
+    }
 }
@@ -0,0 +1,261 @@
+use crate::backend::encapsulation::{self, Statfs};
+use bitflags::bitflags;
+use std::{io::Result, path::Path};
+
+bitflags! {
+    /// Flags a filesystem is mounted with.
+    pub struct MountFlags: u64 {
+        /// Mounted read-only.
+        const RDONLY = libc::ST_RDONLY;
+        /// Set-user-ID and set-group-ID bits are ignored.
+        const NOSUID = libc::ST_NOSUID;
+        /// Device files can not be accessed.
+        const NODEV = libc::ST_NODEV;
+        /// Programs can not be executed.
+        const NOEXEC = libc::ST_NOEXEC;
+        /// Writes are synchronous.
+        const SYNCHRONOUS = libc::ST_SYNCHRONOUS;
+        /// Mandatory locking is permitted.
+        const MANDLOCK = libc::ST_MANDLOCK;
+        /// Access times are not updated.
+        const NOATIME = libc::ST_NOATIME;
+        /// Access times of directories are not updated.
+        const NODIRATIME = libc::ST_NODIRATIME;
+        /// Access times are updated relative to modification or change times.
+        const RELATIME = libc::ST_RELATIME;
+    }
+}
+
+/// `RAMFS_MAGIC` from `<linux/magic.h>`, missing in `libc`.
+const RAMFS_MAGIC: libc::c_long = 0x8584_58f6;
+/// `SQUASHFS_MAGIC` from `<linux/magic.h>`, missing in `libc`.
+const SQUASHFS_MAGIC: libc::c_long = 0x7371_7368;
+/// `CIFS_SUPER_MAGIC` from `fs/smb/client/cifsglob.h`.
+const CIFS_MAGIC: libc::c_long = 0xff53_4d42;
+/// `SMB2_SUPER_MAGIC` from `fs/smb/client/cifsglob.h`.
+const SMB2_MAGIC: libc::c_long = 0xfe53_4d42;
+/// Used by OpenZFS.
+const ZFS_MAGIC: libc::c_long = 0x2fc1_2fc1;
+
+/// Type of a filesystem, decoded from the magic number reported by `statfs(2)`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum FsType {
+    /// ext2, ext3 or ext4, which share the same magic number.
+    Ext4,
+    Btrfs,
+    Xfs,
+    F2fs,
+    Zfs,
+    Tmpfs,
+    Ramfs,
+    Overlayfs,
+    Squashfs,
+    Nfs,
+    /// SMB through the CIFS client.
+    Cifs,
+    /// SMB 2/3 through the CIFS client.
+    Smb2,
+    Fuse,
+    Vfat,
+    Iso9660,
+    Proc,
+    Sysfs,
+    Devpts,
+    Cgroup,
+    Cgroup2,
+    Debugfs,
+    Tracefs,
+    Securityfs,
+    Hugetlbfs,
+    Bpf,
+    Nsfs,
+    /// A filesystem not listed above, with its magic number.
+    Other(i64),
+}
+
+impl FsType {
+    /// Decodes the magic number `f_type` of `statfs(2)`.
+    pub fn from_magic(magic: i64) -> Self {
+        match magic {
+            libc::EXT4_SUPER_MAGIC => FsType::Ext4,
+            libc::BTRFS_SUPER_MAGIC => FsType::Btrfs,
+            libc::XFS_SUPER_MAGIC => FsType::Xfs,
+            libc::F2FS_SUPER_MAGIC => FsType::F2fs,
+            ZFS_MAGIC => FsType::Zfs,
+            libc::TMPFS_MAGIC => FsType::Tmpfs,
+            RAMFS_MAGIC => FsType::Ramfs,
+            libc::OVERLAYFS_SUPER_MAGIC => FsType::Overlayfs,
+            SQUASHFS_MAGIC => FsType::Squashfs,
+            libc::NFS_SUPER_MAGIC => FsType::Nfs,
+            CIFS_MAGIC => FsType::Cifs,
+            SMB2_MAGIC => FsType::Smb2,
+            libc::FUSE_SUPER_MAGIC => FsType::Fuse,
+            libc::MSDOS_SUPER_MAGIC => FsType::Vfat,
+            libc::ISOFS_SUPER_MAGIC => FsType::Iso9660,
+            libc::PROC_SUPER_MAGIC => FsType::Proc,
+            libc::SYSFS_MAGIC => FsType::Sysfs,
+            libc::DEVPTS_SUPER_MAGIC => FsType::Devpts,
+            libc::CGROUP_SUPER_MAGIC => FsType::Cgroup,
+            libc::CGROUP2_SUPER_MAGIC => FsType::Cgroup2,
+            libc::DEBUGFS_MAGIC => FsType::Debugfs,
+            libc::TRACEFS_MAGIC => FsType::Tracefs,
+            libc::SECURITYFS_MAGIC => FsType::Securityfs,
+            libc::HUGETLBFS_MAGIC => FsType::Hugetlbfs,
+            libc::BPF_FS_MAGIC => FsType::Bpf,
+            libc::NSFS_MAGIC => FsType::Nsfs,
+            other => FsType::Other(other),
+        }
+    }
+
+    /// Returns true for filesystems whose contents live on another machine.
+    pub fn is_network(&self) -> bool {
+        matches!(self, FsType::Nfs | FsType::Cifs | FsType::Smb2)
+    }
+}
+
+/// Statistics about a mounted filesystem.
+///
+/// This structure is returned from [`fs_stats`] and [`File::fs_stats`].
+///
+/// [`File::fs_stats`]: crate::File::fs_stats
+#[derive(Clone)]
+pub struct FsStats(pub(crate) Statfs);
+
+impl FsStats {
+    /// Returns the type of the filesystem.
+    #[inline]
+    pub fn fs_type(&self) -> FsType {
+        FsType::from_magic(self.0.fs_type())
+    }
+
+    /// Returns the optimal transfer block size.
+    #[inline]
+    pub fn block_size(&self) -> u64 {
+        self.0.bsize()
+    }
+
+    /// Returns the fragment size, which is the unit of the block counts.
+    #[inline]
+    pub fn fragment_size(&self) -> u64 {
+        self.0.frsize()
+    }
+
+    /// Returns the total number of blocks.
+    #[inline]
+    pub fn blocks(&self) -> u64 {
+        self.0.blocks()
+    }
+
+    /// Returns the number of free blocks.
+    #[inline]
+    pub fn blocks_free(&self) -> u64 {
+        self.0.bfree()
+    }
+
+    /// Returns the number of free blocks available to unprivileged users.
+    #[inline]
+    pub fn blocks_available(&self) -> u64 {
+        self.0.bavail()
+    }
+
+    /// Returns the total number of inodes.
+    #[inline]
+    pub fn files(&self) -> u64 {
+        self.0.files()
+    }
+
+    /// Returns the number of free inodes.
+    #[inline]
+    pub fn files_free(&self) -> u64 {
+        self.0.ffree()
+    }
+
+    /// Returns the maximum length of file names.
+    #[inline]
+    pub fn max_name_len(&self) -> u64 {
+        self.0.namelen()
+    }
+
+    /// Returns the filesystem ID.
+    #[inline]
+    pub fn fsid(&self) -> u64 {
+        let (high, low) = self.0.fsid();
+        ((high as u32 as u64) << 32) | low as u32 as u64
+    }
+
+    /// Returns the flags the filesystem is mounted with.
+    #[inline]
+    pub fn mount_flags(&self) -> MountFlags {
+        MountFlags::from_bits_truncate(self.0.flags())
+    }
+
+    /// Returns the total size of the filesystem, in bytes.
+    #[inline]
+    pub fn total_space(&self) -> u64 {
+        self.blocks().saturating_mul(self.fragment_size())
+    }
+
+    /// Returns the free space of the filesystem, in bytes.
+    #[inline]
+    pub fn free_space(&self) -> u64 {
+        self.blocks_free().saturating_mul(self.fragment_size())
+    }
+
+    /// Returns the free space available to unprivileged users, in bytes.
+    #[inline]
+    pub fn available_space(&self) -> u64 {
+        self.blocks_available().saturating_mul(self.fragment_size())
+    }
+}
+
+/// Returns statistics about the filesystem containing `path`.
+pub fn fs_stats<P: AsRef<Path>>(path: P) -> Result<FsStats> {
+    encapsulation::statfs(path).map(FsStats)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::File;
+
+    #[test]
+    fn fs_stats_proc() {
+        let stats = fs_stats("/proc").unwrap();
+        assert_eq!(stats.fs_type(), FsType::Proc);
+        assert!(!stats.fs_type().is_network());
+    }
+
+    #[test]
+    fn fs_stats_match_nix() {
+        let stats = fs_stats("Cargo.toml").unwrap();
+        let nix_stats = nix::sys::statvfs::statvfs("Cargo.toml").unwrap();
+
+        assert_eq!(stats.fragment_size(), nix_stats.fragment_size());
+        assert_eq!(stats.blocks(), nix_stats.blocks());
+        assert_eq!(stats.files(), nix_stats.files());
+        assert_eq!(stats.max_name_len(), nix_stats.name_max());
+        assert_eq!(
+            stats.mount_flags().contains(MountFlags::RDONLY),
+            nix_stats
+                .flags()
+                .contains(nix::sys::statvfs::FsFlags::ST_RDONLY)
+        );
+        assert!(stats.blocks_available() <= stats.blocks_free());
+        assert!(stats.blocks_free() <= stats.blocks());
+        assert_eq!(stats.total_space(), stats.blocks() * stats.fragment_size());
+
+        let file_stats = File::open("Cargo.toml").unwrap().fs_stats().unwrap();
+        assert_eq!(file_stats.fsid(), stats.fsid());
+        assert_eq!(file_stats.fs_type(), stats.fs_type());
+    }
+
+    #[test]
+    fn fs_type_from_magic() {
+        assert_eq!(FsType::from_magic(0xef53), FsType::Ext4);
+        assert_eq!(FsType::from_magic(0x0102_1994), FsType::Tmpfs);
+        assert_eq!(FsType::from_magic(0x6969), FsType::Nfs);
+        assert!(FsType::from_magic(0x6969).is_network());
+        assert_eq!(FsType::from_magic(0x794c_7630), FsType::Overlayfs);
+        assert_eq!(FsType::from_magic(1), FsType::Other(1));
+    }
+}
@@ -1,15 +1,24 @@
 //! Functions exposed by  `std::fs` and `std::os::unix::fs`
 
 use crate::{
-    backend::{encapsulation, realpath::realpath},
+    atomic_writer::AtomicWriteOptions,
+    backend::{
+        encapsulation::{self, AtFlags},
+        realpath::realpath,
+    },
+    copy::{CopyOptions, CopyOutcome},
+    device::DeviceId,
     dir::ReadDir,
     dirbuilder::DirBuilder,
     file::File,
+    filetimes::FileTimes,
+    filetype::FileType,
     metadata::Metadata,
+    open_option::OpenOptions,
     permissions::Permissions,
 };
 use std::{
-    io::{ErrorKind, Read, Result, Write},
+    io::{Error, ErrorKind, Read, Result, Write},
     os::unix::io::AsFd,
     path::{Path, PathBuf},
 };
@@ -48,6 +57,21 @@ pub fn copy<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) -> Result<u64> {
     Ok(num_written as u64)
 }
 
+/// Copies the regular file or symbolic link `from` to `to`, preserving the
+/// metadata selected in `options`, e.g., `CopyOptions::archive()` for
+/// `cp -a`.
+///
+/// Failing to preserve some metadata does not fail the copy, see
+/// [`CopyOutcome::failures`].
+#[inline]
+pub fn copy_with<P: AsRef<Path>, Q: AsRef<Path>>(
+    from: P,
+    to: Q,
+    options: &CopyOptions,
+) -> Result<CopyOutcome> {
+    options.copy(from, to)
+}
+
 /// create_dir: Creates a new, empty directory at the provided path
 #[inline]
 pub fn create_dir<P: AsRef<Path>>(path: P) -> Result<()> {
@@ -70,7 +94,7 @@ pub fn hard_link<P: AsRef<Path>, Q: AsRef<Path>>(original: P, link: Q) -> Result
 /// Given a path, query the file system to get information about a
 /// file, directory, etc.
 pub fn metadata<P: AsRef<Path>>(path: P) -> Result<Metadata> {
-    encapsulation::statx(path.as_ref()).map(Metadata)
+    encapsulation::file_stat(path.as_ref()).map(Metadata)
 }
 
 /// Read the entire contents of a file into a bytes vector.
@@ -156,7 +180,7 @@ pub fn set_permissions<P: AsRef<Path>>(path: P, perm: Permissions) -> Result<()>
 
 /// Query the metadata about a file without following symlinks.
 pub fn symlink_metadata<P: AsRef<Path>>(path: P) -> Result<Metadata> {
-    encapsulation::lstatx(path.as_ref()).map(Metadata)
+    encapsulation::file_lstat(path.as_ref()).map(Metadata)
 }
 
 /// Write a slice as the entire contents of a file.
@@ -164,6 +188,16 @@ pub fn write<P: AsRef<Path>, C: AsRef<[u8]>>(path: P, contents: C) -> Result<()>
     File::create(path)?.write_all(contents.as_ref())
 }
 
+/// Write a slice as the entire contents of a file, atomically.
+///
+/// Unlike [`write()`], which truncates the file in place, the contents are
+/// written to a temporary file that is then renamed over `path`, so a crash
+/// never leaves a torn file behind. See [`AtomicWriteOptions`] for details.
+#[inline]
+pub fn write_atomic<P: AsRef<Path>, C: AsRef<[u8]>>(path: P, contents: C) -> Result<()> {
+    AtomicWriteOptions::new().write(path, contents)
+}
+
 /// Change the owner and group of the specified path.
 #[inline]
 pub fn chown<P: AsRef<Path>>(dir: P, uid: Option<u32>, gid: Option<u32>) -> Result<()> {
@@ -190,12 +224,87 @@ pub fn chroot<P: AsRef<Path>>(dir: P) -> Result<()> {
     encapsulation::chroot(dir)
 }
 
+/// Changes the timestamps of the file or directory at `path`, symbolic links
+/// are followed.
+///
+/// Timestamps that are not set in `times` are left unchanged.
+#[inline]
+pub fn set_file_times<P: AsRef<Path>>(path: P, times: FileTimes) -> Result<()> {
+    encapsulation::utimensat(None, path, &times.0[0], &times.0[1], AtFlags::empty())
+}
+
+/// Changes the timestamps of the file or directory at `path`, without
+/// dereferencing symbolic links.
+///
+/// Timestamps that are not set in `times` are left unchanged.
+#[inline]
+pub fn set_symlink_file_times<P: AsRef<Path>>(path: P, times: FileTimes) -> Result<()> {
+    encapsulation::utimensat(
+        None,
+        path,
+        &times.0[0],
+        &times.0[1],
+        AtFlags::AT_SYMLINK_NOFOLLOW,
+    )
+}
+
+/// Updates the access and modification times of `path` to the current time,
+/// the file will be created if it does not exist, like `touch(1)`.
+pub fn touch<P: AsRef<Path>>(path: P) -> Result<()> {
+    let now = FileTimes::new().set_accessed_now().set_modified_now();
+    match set_file_times(path.as_ref(), now) {
+        Err(e) if e.kind() == ErrorKind::NotFound => OpenOptions::new()
+            .write(true)
+            .create(true)
+            .open(path)
+            .map(drop),
+        res => res,
+    }
+}
+
+/// Commits all filesystem caches to disk.
+#[inline]
+pub fn sync() {
+    encapsulation::sync()
+}
+
 /// Creates a new symbolic link on the filesystem.
 #[inline]
 pub fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(original: P, link: Q) -> Result<()> {
     encapsulation::symlink(original, link)
 }
 
+/// Creates a filesystem node at `path`: a FIFO, a socket file, a character or
+/// block device with the ID `dev`, or an empty regular file.
+///
+/// `mode` holds the permission bits, which are masked by the umask of the
+/// process. `dev` is ignored unless `file_type` is a device. Creating devices
+/// needs the `CAP_MKNOD` capability.
+pub fn mknod<P: AsRef<Path>>(path: P, file_type: FileType, mode: u32, dev: DeviceId) -> Result<()> {
+    let type_bits = match file_type.0 {
+        encapsulation::FileType::RegularFile => libc::S_IFREG,
+        encapsulation::FileType::Fifo => libc::S_IFIFO,
+        encapsulation::FileType::Socket => libc::S_IFSOCK,
+        encapsulation::FileType::CharDev => libc::S_IFCHR,
+        encapsulation::FileType::BlkDev => libc::S_IFBLK,
+        encapsulation::FileType::Directory | encapsulation::FileType::Symlink => {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "mknod can not create directories or symlinks",
+            ))
+        }
+    };
+
+    encapsulation::mknodat(None, path, type_bits | (mode & 0o7777), dev.as_raw())
+}
+
+/// Creates a FIFO (named pipe) at `path`, with the permission bits `mode`
+/// masked by the umask of the process.
+#[inline]
+pub fn mkfifo<P: AsRef<Path>>(path: P, mode: u32) -> Result<()> {
+    mknod(path, FileType::FIFO, mode, DeviceId::default())
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -213,6 +322,50 @@ mod test {
         remove_file(to).unwrap();
     }
 
+    #[test]
+    fn test_write_atomic() {
+        let file = "/tmp/test_write_atomic";
+        write_atomic(file, b"hello").unwrap();
+        write_atomic(file, b"world").unwrap();
+
+        assert_eq!(read(file).unwrap(), b"world");
+        remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn test_set_file_times() {
+        let file = "/tmp/test_set_file_times";
+        let link = "/tmp/test_set_file_times_link";
+        File::create(file).unwrap();
+        symlink(file, link).unwrap();
+        let atime = crate::non_fs::SystemTime::new(1, 2);
+        let mtime = crate::non_fs::SystemTime::new(3, 4);
+
+        set_file_times(link, FileTimes::new().set_accessed(atime)).unwrap();
+        set_symlink_file_times(link, FileTimes::new().set_modified(mtime)).unwrap();
+
+        assert_eq!(metadata(file).unwrap().accessed().unwrap(), atime);
+        assert_ne!(metadata(file).unwrap().modified().unwrap(), mtime);
+        assert_eq!(symlink_metadata(link).unwrap().modified().unwrap(), mtime);
+
+        remove_file(link).unwrap();
+        remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn test_touch() {
+        let file = "/tmp/test_touch";
+        touch(file).unwrap();
+        assert!(metadata(file).unwrap().is_file());
+
+        let old = crate::non_fs::SystemTime::new(1, 2);
+        set_file_times(file, FileTimes::new().set_modified(old)).unwrap();
+        touch(file).unwrap();
+        assert_ne!(metadata(file).unwrap().modified().unwrap(), old);
+
+        remove_file(file).unwrap();
+    }
+
     #[test]
     fn test_remove_dir_all() {
         // Create dir
@@ -239,4 +392,59 @@ mod test {
 
         remove_file("/tmp/test_remove_dir_all_symlink").unwrap();
     }
+
+    #[test]
+    fn test_mknod_mkfifo() {
+        use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+
+        let fifo = "/tmp/test_mknod_mkfifo_fifo";
+        let _ = remove_file(fifo);
+        mkfifo(fifo, 0o600).unwrap();
+        let fifo_metadata = symlink_metadata(fifo).unwrap();
+        assert!(fifo_metadata.file_type().is_fifo());
+        assert_eq!(fifo_metadata.permission().mode() & 0o777, 0o600);
+        assert_eq!(
+            mkfifo(fifo, 0o600).unwrap_err().kind(),
+            ErrorKind::AlreadyExists
+        );
+
+        let socket = "/tmp/test_mknod_mkfifo_socket";
+        let _ = remove_file(socket);
+        mknod(socket, FileType::SOCKET, 0o600, DeviceId::default()).unwrap();
+        assert!(symlink_metadata(socket).unwrap().file_type().is_socket());
+
+        // Creating devices needs `CAP_MKNOD`.
+        let null = "/tmp/test_mknod_mkfifo_null";
+        let _ = remove_file(null);
+        match mknod(null, FileType::CHAR_DEVICE, 0o666, DeviceId::new(1, 3)) {
+            Err(e) if e.kind() == ErrorKind::PermissionDenied => {}
+            res => {
+                res.unwrap();
+                let null_metadata = symlink_metadata(null).unwrap();
+                assert!(null_metadata.file_type().is_char_device());
+                assert_eq!(null_metadata.rdev_id(), DeviceId::new(1, 3));
+                assert_eq!(
+                    null_metadata.rdev_id(),
+                    metadata("/dev/null").unwrap().rdev_id()
+                );
+                remove_file(null).unwrap();
+            }
+        }
+
+        let dir = metadata("/tmp").unwrap().file_type();
+        assert_eq!(
+            mknod(
+                "/tmp/test_mknod_mkfifo_dir",
+                dir,
+                0o755,
+                DeviceId::default()
+            )
+            .unwrap_err()
+            .kind(),
+            ErrorKind::InvalidInput
+        );
+
+        remove_file(fifo).unwrap();
+        remove_file(socket).unwrap();
+    }
 }
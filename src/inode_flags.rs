@@ -0,0 +1,63 @@
+use bitflags::bitflags;
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+};
+
+bitflags! {
+    /// Inode flags, also known as file attributes, as manipulated by
+    /// `chattr(1)` and listed by `lsattr(1)`.
+    ///
+    /// Values are the `FS_*_FL` constants from `<linux/fs.h>`, support for
+    /// them varies between filesystems.
+    pub struct InodeFlags: u32 {
+        /// Writes to the file are synchronous (`S`).
+        const SYNC = 0x0000_0008;
+        /// The file cannot be modified, deleted, renamed or linked to (`i`).
+        const IMMUTABLE = 0x0000_0010;
+        /// The file can only be opened in append mode for writing (`a`).
+        const APPEND = 0x0000_0020;
+        /// The file is not a candidate for backup by `dump(8)` (`d`).
+        const NODUMP = 0x0000_0040;
+        /// The access time of the file is not updated (`A`).
+        const NOATIME = 0x0000_0080;
+        /// Changes to the directory are written synchronously (`D`).
+        const DIRSYNC = 0x0001_0000;
+        /// The file is not subject to copy-on-write updates (`C`).
+        const NOCOW = 0x0080_0000;
+        /// Files created in the directory inherit its project ID (`P`).
+        const PROJINHERIT = 0x2000_0000;
+        /// Name lookups in the directory are case-insensitive (`F`).
+        const CASEFOLD = 0x4000_0000;
+    }
+}
+
+/// The error returned by [`File::set_inode_flags`] when the filesystem rejects
+/// the requested flags.
+///
+/// The filesystem rejects a request as a whole, without telling which flags
+/// it does not support, so these are all the requested changes, supported or
+/// not. Changing them one at a time tells which ones are rejected.
+///
+/// It is wrapped in an [`std::io::Error`] of kind `Unsupported`, use
+/// [`std::io::Error::get_ref`] and `downcast_ref` to get it.
+///
+/// [`File::set_inode_flags`]: crate::File::set_inode_flags
+#[derive(Clone, Debug)]
+pub struct RejectedInodeFlags(pub(crate) InodeFlags);
+
+impl RejectedInodeFlags {
+    /// Returns the flags that were requested to be set or cleared.
+    #[inline]
+    pub fn flags(&self) -> InodeFlags {
+        self.0
+    }
+}
+
+impl Display for RejectedInodeFlags {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "inode flags rejected by the filesystem: {:?}", self.0)
+    }
+}
+
+impl Error for RejectedInodeFlags {}
@@ -0,0 +1,251 @@
+//! Unprivileged sandboxing of filesystem accesses with Landlock, see
+//! `landlock(7)`.
+//!
+//! A [`Ruleset`] lists the accesses to restrict and the paths beneath which
+//! they stay allowed. Once enforced with [`Ruleset::restrict_self`], the
+//! calling thread and the children it creates afterwards can not perform any
+//! other restricted access, and this can not be undone.
+//!
+//! ```no_run
+//! use fs::landlock::{AccessFs, Ruleset};
+//!
+//! let status = Ruleset::new()
+//!     .allow("/usr", AccessFs::READ_FILE | AccessFs::READ_DIR | AccessFs::EXECUTE)?
+//!     .allow("/var/lib/worker", AccessFs::all())?
+//!     .restrict_self()?;
+//! println!("sandboxed: {:?}", status);
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use crate::{backend::encapsulation, File, OpenOptions};
+use bitflags::bitflags;
+use std::{io::Result, os::unix::fs::OpenOptionsExt, path::Path};
+
+bitflags! {
+    /// Filesystem access rights, `LANDLOCK_ACCESS_FS_*` from
+    /// `<linux/landlock.h>`.
+    pub struct AccessFs: u64 {
+        /// Execute a file.
+        const EXECUTE = 1 << 0;
+        /// Open a file with write access.
+        const WRITE_FILE = 1 << 1;
+        /// Open a file with read access.
+        const READ_FILE = 1 << 2;
+        /// Open a directory or list its content.
+        const READ_DIR = 1 << 3;
+        /// Remove an empty directory or rename one.
+        const REMOVE_DIR = 1 << 4;
+        /// Unlink or rename a file.
+        const REMOVE_FILE = 1 << 5;
+        /// Create, rename or link a character device.
+        const MAKE_CHAR = 1 << 6;
+        /// Create or rename a directory.
+        const MAKE_DIR = 1 << 7;
+        /// Create, rename or link a regular file.
+        const MAKE_REG = 1 << 8;
+        /// Create, rename or link a UNIX domain socket.
+        const MAKE_SOCK = 1 << 9;
+        /// Create, rename or link a named pipe.
+        const MAKE_FIFO = 1 << 10;
+        /// Create, rename or link a block device.
+        const MAKE_BLOCK = 1 << 11;
+        /// Create, rename or link a symbolic link.
+        const MAKE_SYM = 1 << 12;
+        /// Link or rename a file from or to a different directory, since ABI
+        /// version 2.
+        ///
+        /// Before that, such renames and links are always denied in a
+        /// sandbox.
+        const REFER = 1 << 13;
+        /// Truncate a file, since ABI version 3.
+        const TRUNCATE = 1 << 14;
+        /// Call `ioctl(2)` on a device file, since ABI version 5.
+        const IOCTL_DEV = 1 << 15;
+    }
+}
+
+impl AccessFs {
+    /// Returns the access rights known to the Landlock ABI version `abi`,
+    /// which is empty for version 0, i.e., when Landlock is unavailable.
+    pub fn from_abi(abi: u32) -> Self {
+        match abi {
+            0 => AccessFs::empty(),
+            1 => AccessFs::from_bits_truncate((1 << 13) - 1),
+            2 => AccessFs::from_abi(1) | AccessFs::REFER,
+            // Version 4 only added network rights.
+            3 | 4 => AccessFs::from_abi(2) | AccessFs::TRUNCATE,
+            _ => AccessFs::from_abi(4) | AccessFs::IOCTL_DEV,
+        }
+    }
+
+    /// The access rights which apply to files, as opposed to directories.
+    fn file() -> Self {
+        AccessFs::EXECUTE
+            | AccessFs::WRITE_FILE
+            | AccessFs::READ_FILE
+            | AccessFs::TRUNCATE
+            | AccessFs::IOCTL_DEV
+    }
+}
+
+/// Returns the highest Landlock ABI version supported by the running kernel,
+/// or 0 if Landlock is not built in or disabled at boot time.
+pub fn abi_version() -> u32 {
+    // `ENOSYS` and `EOPNOTSUPP` are the expected errors, there is nothing
+    // better to do with any other one.
+    encapsulation::landlock_abi_version().unwrap_or(0)
+}
+
+/// How much of a [`Ruleset`] is enforced by the running kernel.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum RestrictionStatus {
+    /// All the handled accesses are restricted.
+    FullyEnforced,
+    /// Some of the handled accesses are unknown to the kernel and stay
+    /// allowed everywhere.
+    PartiallyEnforced,
+    /// Landlock is unavailable, nothing is restricted.
+    NotEnforced,
+}
+
+/// A set of Landlock rules to enforce on the calling thread.
+///
+/// Accesses that are handled by the ruleset, all of them by default, are
+/// denied except beneath the paths added with [`allow`](Ruleset::allow).
+/// Accesses that the running kernel does not know are dropped, so that the
+/// ruleset is enforced as much as possible, see [`RestrictionStatus`].
+#[derive(Debug)]
+pub struct Ruleset {
+    handled: AccessFs,
+    rules: Vec<(File, AccessFs)>,
+}
+
+impl Ruleset {
+    /// Creates a ruleset which handles all accesses and allows none.
+    pub fn new() -> Self {
+        Self {
+            handled: AccessFs::all(),
+            rules: Vec::new(),
+        }
+    }
+
+    /// Sets the accesses handled by the ruleset, the other ones stay allowed
+    /// everywhere.
+    pub fn handle_access(&mut self, access: AccessFs) -> &mut Self {
+        self.handled = access;
+        self
+    }
+
+    /// Allows the accesses `access` beneath `path`, which can be a directory or
+    /// a file.
+    ///
+    /// `path` is opened right away with `O_PATH`, so that the rule applies to
+    /// the file it currently names. Accesses which only apply to directories
+    /// are ignored for files.
+    pub fn allow<P: AsRef<Path>>(&mut self, path: P, access: AccessFs) -> Result<&mut Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_PATH)
+            .open(path)?;
+        let access = if file.metadata()?.is_dir() {
+            access
+        } else {
+            access & AccessFs::file()
+        };
+
+        self.rules.push((file, access));
+        Ok(self)
+    }
+
+    /// Enforces the ruleset on the calling thread and the children it creates
+    /// afterwards.
+    ///
+    /// This sets the `no_new_privs` attribute of the thread, as required by
+    /// Landlock for unprivileged processes.
+    pub fn restrict_self(&self) -> Result<RestrictionStatus> {
+        let supported = AccessFs::from_abi(abi_version());
+        let handled = self.handled & supported;
+        if handled.is_empty() {
+            return Ok(RestrictionStatus::NotEnforced);
+        }
+
+        let ruleset = encapsulation::landlock_create_ruleset(handled.bits())?;
+        for (file, access) in self.rules.iter() {
+            let access = *access & handled;
+            // Rules without any access are rejected with `ENOMSG`.
+            if !access.is_empty() {
+                encapsulation::landlock_add_path_beneath_rule(&ruleset, file, access.bits())?;
+            }
+        }
+
+        encapsulation::set_no_new_privs()?;
+        encapsulation::landlock_restrict_self(&ruleset)?;
+
+        if handled == self.handled {
+            Ok(RestrictionStatus::FullyEnforced)
+        } else {
+            Ok(RestrictionStatus::PartiallyEnforced)
+        }
+    }
+}
+
+impl Default for Ruleset {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::functions::{create_dir_all, read_to_string, remove_dir_all, write};
+    use std::thread;
+
+    #[test]
+    fn access_from_abi() {
+        assert!(AccessFs::from_abi(0).is_empty());
+        assert_eq!(
+            AccessFs::from_abi(1),
+            AccessFs::all() - AccessFs::REFER - AccessFs::TRUNCATE - AccessFs::IOCTL_DEV
+        );
+        assert!(AccessFs::from_abi(2).contains(AccessFs::REFER));
+        assert!(!AccessFs::from_abi(2).contains(AccessFs::TRUNCATE));
+        assert_eq!(AccessFs::from_abi(3), AccessFs::from_abi(4));
+        assert_eq!(AccessFs::from_abi(5), AccessFs::all());
+        assert_eq!(AccessFs::from_abi(u32::MAX), AccessFs::all());
+    }
+
+    #[test]
+    fn restrict_thread() {
+        let dir = "/tmp/test_landlock_restrict_thread";
+        create_dir_all(dir).unwrap();
+        write(format!("{}/file", dir), "hello").unwrap();
+
+        // The restriction is only enforced on the spawned thread.
+        let status = thread::spawn(move || {
+            let status = Ruleset::new()
+                .allow(dir, AccessFs::READ_FILE | AccessFs::READ_DIR)
+                .unwrap()
+                .restrict_self()
+                .unwrap();
+
+            let read = read_to_string(format!("{}/file", dir));
+            let written = write(format!("{}/file", dir), "bye");
+            let outside = read_to_string("Cargo.toml");
+            if status == RestrictionStatus::NotEnforced {
+                assert!(read.is_ok() && written.is_ok() && outside.is_ok());
+            } else {
+                assert_eq!(read.unwrap(), "hello");
+                assert_eq!(written.unwrap_err().raw_os_error(), Some(libc::EACCES));
+                assert_eq!(outside.unwrap_err().raw_os_error(), Some(libc::EACCES));
+            }
+            status
+        })
+        .join()
+        .unwrap();
+
+        assert_eq!(status == RestrictionStatus::NotEnforced, abi_version() == 0);
+        assert!(read_to_string("Cargo.toml").is_ok());
+        remove_dir_all(dir).unwrap();
+    }
+}
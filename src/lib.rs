@@ -1,24 +1,50 @@
 #![feature(io_error_uncategorized)]
 // #![deny(unused)]
 
+mod access;
+mod acl;
+mod atomic_writer;
 mod backend;
+mod copy;
+mod device;
 mod dir;
 mod dirbuilder;
 mod file;
+mod file_caps;
 mod filetimes;
 mod filetype;
+mod fs_stats;
 mod functions;
+mod inode_flags;
+pub mod landlock;
 mod metadata;
+pub mod mount;
+pub mod mounts;
 pub mod non_fs;
 mod open_option;
 mod permissions;
+pub mod temp;
+mod umask;
+pub mod users;
+pub mod watch;
+mod xattr;
 
+pub use access::*;
+pub use acl::*;
+pub use atomic_writer::*;
+pub use copy::*;
+pub use device::*;
 pub use dir::*;
 pub use dirbuilder::*;
 pub use file::*;
+pub use file_caps::*;
 pub use filetimes::*;
 pub use filetype::*;
+pub use fs_stats::*;
 pub use functions::*;
+pub use inode_flags::*;
 pub use metadata::*;
 pub use open_option::*;
 pub use permissions::*;
+pub use umask::*;
+pub use xattr::*;
@@ -1,14 +1,20 @@
 use crate::{
-    backend::{encapsulation::Statx, major_minor::makedev},
+    backend::{
+        encapsulation::{self, AtFlags, FileStat},
+        major_minor::makedev,
+    },
+    device::DeviceId,
     filetype::FileType,
     non_fs::SystemTime,
     permissions::Permissions,
 };
+use bitflags::bitflags;
 #[allow(deprecated)]
 use std::os::linux::raw::stat;
 use std::{
-    io::Result,
-    os::{linux::fs::MetadataExt, unix::fs::PermissionsExt},
+    io::{Error, ErrorKind, Result},
+    os::{fd::AsFd, linux::fs::MetadataExt, unix::fs::PermissionsExt},
+    path::Path,
 };
 
 /// Metadata information about a file.
@@ -17,7 +23,7 @@ use std::{
 /// or method and represents known metadata about a file such as its permissions,
 /// size, modification times, etc.
 #[derive(Clone)]
-pub struct Metadata(pub(crate) Statx);
+pub struct Metadata(pub(crate) FileStat);
 
 impl Metadata {
     /// Returns the file type for this metadata.
@@ -60,6 +66,23 @@ impl Metadata {
         self.0.size()
     }
 
+    /// Returns the ID of the device containing the file.
+    #[inline]
+    pub fn dev_id(&self) -> DeviceId {
+        DeviceId::from(self.0.dev())
+    }
+
+    /// Returns the ID of the device this file represents, if it is a device
+    /// file, see [`FileTypeExt::is_char_device`] and
+    /// [`FileTypeExt::is_block_device`].
+    ///
+    /// [`FileTypeExt::is_char_device`]: std::os::unix::fs::FileTypeExt::is_char_device
+    /// [`FileTypeExt::is_block_device`]: std::os::unix::fs::FileTypeExt::is_block_device
+    #[inline]
+    pub fn rdev_id(&self) -> DeviceId {
+        DeviceId::from(self.0.rdev())
+    }
+
     /// Returns the permissions of the file this metadata is for.
     pub fn permission(&self) -> Permissions {
         Permissions::from_mode(self.0.mode())
@@ -75,6 +98,7 @@ impl Metadata {
     /// Err on platforms where it is not available.
     #[inline]
     pub fn modified(&self) -> Result<SystemTime> {
+        self.require(MetadataFields::MTIME, "modification time")?;
         Ok(SystemTime::new(self.0.mtime().0, self.0.mtime().1 as i64))
     }
 
@@ -92,6 +116,7 @@ impl Metadata {
     /// Err on platforms where it is not available.
     #[inline]
     pub fn accessed(&self) -> Result<SystemTime> {
+        self.require(MetadataFields::ATIME, "access time")?;
         Ok(SystemTime::new(self.0.atime().0, self.0.atime().1 as i64))
     }
 
@@ -106,7 +131,155 @@ impl Metadata {
     /// Err on platforms or filesystems where it is not available.
     #[inline]
     pub fn created(&self) -> Result<SystemTime> {
-        Ok(SystemTime::new(self.0.ctime().0, self.0.ctime().1 as i64))
+        self.require(MetadataFields::BTIME, "creation time")?;
+        Ok(SystemTime::new(self.0.btime().0, self.0.btime().1 as i64))
+    }
+
+    /// Returns an error if any field in `fields` was not filled in.
+    fn require(&self, fields: MetadataFields, what: &str) -> Result<()> {
+        if self.fields().contains(fields) {
+            Ok(())
+        } else {
+            Err(Error::new(
+                ErrorKind::Unsupported,
+                format!("{} is not available for the filesystem", what),
+            ))
+        }
+    }
+}
+
+bitflags! {
+    /// Fields of [`Metadata`], used to request specific fields with
+    /// [`MetadataOptions`] and to learn which fields were filled in.
+    pub struct MetadataFields: u32 {
+        /// File type, see [`Metadata::file_type`].
+        const TYPE = libc::STATX_TYPE;
+        /// Permission bits, see [`Metadata::permission`].
+        const MODE = libc::STATX_MODE;
+        /// Number of hard links.
+        const NLINK = libc::STATX_NLINK;
+        /// Owner's user ID.
+        const UID = libc::STATX_UID;
+        /// Owner's group ID.
+        const GID = libc::STATX_GID;
+        /// Last access time, see [`Metadata::accessed`].
+        const ATIME = libc::STATX_ATIME;
+        /// Last modification time, see [`Metadata::modified`].
+        const MTIME = libc::STATX_MTIME;
+        /// Last status change time.
+        const CTIME = libc::STATX_CTIME;
+        /// Inode number.
+        const INO = libc::STATX_INO;
+        /// File size, see [`Metadata::len`].
+        const SIZE = libc::STATX_SIZE;
+        /// Number of allocated blocks.
+        const BLOCKS = libc::STATX_BLOCKS;
+        /// All the fields above, which are what `stat(2)` returns.
+        const BASIC_STATS = libc::STATX_BASIC_STATS;
+        /// Creation time, see [`Metadata::created`].
+        const BTIME = libc::STATX_BTIME;
+        /// Mount ID, see [`StatxExt::mount_id`].
+        const MNT_ID = libc::STATX_MNT_ID;
+    }
+}
+
+/// How attributes are synchronized with the server on network filesystems
+/// when querying metadata.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum MetadataSync {
+    /// Do whatever `stat(2)` does.
+    #[default]
+    AsStat,
+    /// Force the attributes to be synchronized with the server.
+    ForceSync,
+    /// Use whatever is cached locally, which may be out of date.
+    DontSync,
+}
+
+/// Options and flags which can be used to configure how metadata is queried.
+///
+/// Fields that are not requested may still be filled in if they are cheap to
+/// get, use [`StatxExt::fields`] to learn which ones are available. Accessors
+/// returning `Result` fail when their field is not available, the values
+/// returned by the others are unspecified.
+#[derive(Clone, Debug)]
+pub struct MetadataOptions {
+    fields: MetadataFields,
+    sync: MetadataSync,
+    follow_symlinks: bool,
+}
+
+impl MetadataOptions {
+    /// Creates a blank new set of options.
+    ///
+    /// By default, all fields are requested, symlinks are followed and the
+    /// `stat(2)` synchronization behavior is used.
+    pub fn new() -> Self {
+        Self {
+            fields: MetadataFields::all(),
+            sync: MetadataSync::AsStat,
+            follow_symlinks: true,
+        }
+    }
+
+    /// Sets the fields to request.
+    ///
+    /// The kernel may not fill in a field that is not requested, or that the
+    /// filesystem does not support. Then [`Metadata::file_type`],
+    /// [`Metadata::len`], [`Metadata::permission`] and the `st_*` accessors
+    /// return meaningless values for it, check [`StatxExt::fields`] before
+    /// relying on them.
+    pub fn fields(&mut self, fields: MetadataFields) -> &mut Self {
+        self.fields = fields;
+        self
+    }
+
+    /// Sets how attributes are synchronized on network filesystems.
+    pub fn sync(&mut self, sync: MetadataSync) -> &mut Self {
+        self.sync = sync;
+        self
+    }
+
+    /// Sets the option to follow symlinks, which is enabled by default.
+    pub fn follow_symlinks(&mut self, follow_symlinks: bool) -> &mut Self {
+        self.follow_symlinks = follow_symlinks;
+        self
+    }
+
+    /// Queries metadata about `path` with the options specified by self.
+    pub fn metadata<P: AsRef<Path>>(&self, path: P) -> Result<Metadata> {
+        let mut flags = self.at_flags();
+        if !self.follow_symlinks {
+            flags |= AtFlags::AT_SYMLINK_NOFOLLOW;
+        }
+
+        encapsulation::file_stat_at(None, path, flags, self.fields.bits()).map(Metadata)
+    }
+
+    /// Queries metadata about the file referred to by `fd` with the options
+    /// specified by self, `follow_symlinks` is ignored.
+    pub fn fd_metadata<Fd: AsFd>(&self, fd: Fd) -> Result<Metadata> {
+        encapsulation::file_stat_at(
+            Some(fd.as_fd()),
+            "",
+            self.at_flags() | AtFlags::AT_EMPTY_PATH,
+            self.fields.bits(),
+        )
+        .map(Metadata)
+    }
+
+    fn at_flags(&self) -> AtFlags {
+        match self.sync {
+            MetadataSync::AsStat => AtFlags::empty(),
+            MetadataSync::ForceSync => AtFlags::AT_STATX_FORCE_SYNC,
+            MetadataSync::DontSync => AtFlags::AT_STATX_DONT_SYNC,
+        }
+    }
+}
+
+impl Default for MetadataOptions {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -197,6 +370,123 @@ impl MetadataExt for Metadata {
     }
 }
 
+bitflags! {
+    /// Extra file attributes reported by `statx(2)`.
+    pub struct StatxAttributes: u64 {
+        /// The file is compressed by the filesystem.
+        const COMPRESSED = libc::STATX_ATTR_COMPRESSED as u64;
+        /// The file cannot be modified, deleted, renamed or linked to.
+        const IMMUTABLE = libc::STATX_ATTR_IMMUTABLE as u64;
+        /// The file can only be opened in append mode for writing.
+        const APPEND = libc::STATX_ATTR_APPEND as u64;
+        /// The file is not a candidate for backup when a backup program such
+        /// as `dump(8)` is run.
+        const NODUMP = libc::STATX_ATTR_NODUMP as u64;
+        /// The file requires a key to be decrypted by the filesystem.
+        const ENCRYPTED = libc::STATX_ATTR_ENCRYPTED as u64;
+        /// The file is an automount trigger.
+        const AUTOMOUNT = libc::STATX_ATTR_AUTOMOUNT as u64;
+        /// The file is the root of a mount.
+        const MOUNT_ROOT = libc::STATX_ATTR_MOUNT_ROOT as u64;
+        /// The file has fs-verity enabled.
+        const VERITY = libc::STATX_ATTR_VERITY as u64;
+        /// The file is in the DAX (CPU direct access) state.
+        const DAX = libc::STATX_ATTR_DAX as u64;
+    }
+}
+
+/// Linux-specific extensions to [`Metadata`] exposing information that is only
+/// available through `statx(2)`.
+pub trait StatxExt {
+    /// Returns the fields that were filled in.
+    fn fields(&self) -> MetadataFields;
+
+    /// Returns the attributes that are set on the file.
+    ///
+    /// Only attributes supported by the filesystem are included, see
+    /// [`attributes_mask`](StatxExt::attributes_mask).
+    fn attributes(&self) -> StatxAttributes;
+
+    /// Returns the attributes that are supported by the filesystem.
+    fn attributes_mask(&self) -> StatxAttributes;
+
+    /// Returns whether all the attributes in `attr` are set on the file, or
+    /// `None` if any of them is not supported by the filesystem.
+    fn has_attributes(&self, attr: StatxAttributes) -> Option<bool> {
+        if self.attributes_mask().contains(attr) {
+            Some(self.attributes().contains(attr))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the ID of the mount containing the file, which corresponds to
+    /// the first field in `/proc/self/mountinfo`.
+    ///
+    /// Returns `None` if the kernel does not report it (before Linux 5.8).
+    fn mount_id(&self) -> Option<u64>;
+
+    /// Returns the major ID of the device containing the file.
+    fn dev_major(&self) -> u32;
+
+    /// Returns the minor ID of the device containing the file.
+    fn dev_minor(&self) -> u32;
+
+    /// Returns the major ID of the device this file represents, if it is a
+    /// device file.
+    fn rdev_major(&self) -> u32;
+
+    /// Returns the minor ID of the device this file represents, if it is a
+    /// device file.
+    fn rdev_minor(&self) -> u32;
+}
+
+impl StatxExt for Metadata {
+    #[inline]
+    fn fields(&self) -> MetadataFields {
+        MetadataFields::from_bits_truncate(self.0.mask())
+    }
+
+    #[inline]
+    fn attributes(&self) -> StatxAttributes {
+        StatxAttributes::from_bits_truncate(self.0.attributes() & self.0.attributes_mask())
+    }
+
+    #[inline]
+    fn attributes_mask(&self) -> StatxAttributes {
+        StatxAttributes::from_bits_truncate(self.0.attributes_mask())
+    }
+
+    #[inline]
+    fn mount_id(&self) -> Option<u64> {
+        if self.fields().contains(MetadataFields::MNT_ID) {
+            Some(self.0.mnt_id())
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    fn dev_major(&self) -> u32 {
+        self.0.dev().0
+    }
+
+    #[inline]
+    fn dev_minor(&self) -> u32 {
+        self.0.dev().1
+    }
+
+    #[inline]
+    fn rdev_major(&self) -> u32 {
+        self.0.rdev().0
+    }
+
+    #[inline]
+    fn rdev_minor(&self) -> u32 {
+        self.0.rdev().1
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -224,4 +514,119 @@ mod test {
         assert_eq!(stat.st_blksize as u64, metadata.st_blksize());
         assert_eq!(stat.st_blocks as u64, metadata.st_blocks());
     }
+
+    #[test]
+    fn statx_ext() {
+        let stat = nix::sys::stat::stat("/dev/null").unwrap();
+        let metadata = crate::functions::metadata("/dev/null").unwrap();
+
+        assert_eq!(
+            nix::sys::stat::major(stat.st_dev) as u32,
+            metadata.dev_major()
+        );
+        assert_eq!(
+            nix::sys::stat::minor(stat.st_dev) as u32,
+            metadata.dev_minor()
+        );
+        assert_eq!(
+            nix::sys::stat::major(stat.st_rdev) as u32,
+            metadata.rdev_major()
+        );
+        assert_eq!(
+            nix::sys::stat::minor(stat.st_rdev) as u32,
+            metadata.rdev_minor()
+        );
+        assert!(metadata.mount_id().is_some());
+        assert_eq!(
+            metadata.rdev_id(),
+            DeviceId::new(metadata.rdev_major(), metadata.rdev_minor())
+        );
+        assert_eq!(metadata.rdev_id().as_raw(), stat.st_rdev);
+        assert_eq!(metadata.dev_id().as_raw(), stat.st_dev);
+    }
+
+    #[test]
+    fn statx_attributes() {
+        let root = crate::functions::metadata("/").unwrap();
+        let cargo_toml = crate::functions::metadata("Cargo.toml").unwrap();
+
+        if root.attributes_mask().contains(StatxAttributes::MOUNT_ROOT) {
+            assert_eq!(root.has_attributes(StatxAttributes::MOUNT_ROOT), Some(true));
+            assert_eq!(
+                cargo_toml.has_attributes(StatxAttributes::MOUNT_ROOT),
+                Some(false)
+            );
+        }
+        assert!(root.attributes_mask().contains(root.attributes()));
+    }
+
+    #[test]
+    fn metadata_options() {
+        let metadata = MetadataOptions::new()
+            .fields(MetadataFields::SIZE)
+            .sync(MetadataSync::DontSync)
+            .metadata("Cargo.toml")
+            .unwrap();
+        let std_metadata = std::fs::metadata("Cargo.toml").unwrap();
+
+        assert!(metadata.fields().contains(MetadataFields::SIZE));
+        assert_eq!(metadata.len(), std_metadata.len());
+        assert_eq!(
+            metadata.created().is_ok(),
+            metadata.fields().contains(MetadataFields::BTIME)
+        );
+    }
+
+    #[test]
+    fn created() {
+        let metadata = File::open("Cargo.toml").unwrap().metadata().unwrap();
+        let std_metadata = std::fs::metadata("Cargo.toml").unwrap();
+
+        match std_metadata.created() {
+            Ok(created) => {
+                assert_eq!(metadata.created().unwrap(), SystemTime::from(created))
+            }
+            Err(_) => assert_eq!(
+                metadata.created().unwrap_err().kind(),
+                ErrorKind::Unsupported
+            ),
+        }
+    }
+
+    #[test]
+    fn fd_metadata_symlink() {
+        let dir = crate::temp::TempDir::new().unwrap();
+        let link = dir.path().join("link");
+        let target = std::env::current_dir().unwrap().join("Cargo.toml");
+        crate::functions::symlink(target, &link).unwrap();
+
+        let options = MetadataOptions::new();
+        assert!(options.metadata(&link).unwrap().is_file());
+        let metadata = MetadataOptions::new()
+            .follow_symlinks(false)
+            .metadata(&link)
+            .unwrap();
+        assert!(metadata.is_symlink());
+
+        let file = File::open("Cargo.toml").unwrap();
+        assert!(options.fd_metadata(&file).unwrap().is_file());
+    }
+
+    #[test]
+    fn stat_fallback() {
+        let stat = encapsulation::newfstatat(None, "Cargo.toml", AtFlags::empty()).unwrap();
+        let metadata = Metadata(FileStat::Stat(stat));
+        let std_metadata = std::fs::metadata("Cargo.toml").unwrap();
+
+        assert!(metadata.is_file());
+        assert_eq!(metadata.len(), std_metadata.len());
+        assert_eq!(metadata.st_ino(), std_metadata.st_ino());
+        assert!(metadata.modified().is_ok());
+        assert_eq!(
+            metadata.created().unwrap_err().kind(),
+            ErrorKind::Unsupported
+        );
+        assert_eq!(metadata.mount_id(), None);
+        assert!(metadata.attributes_mask().is_empty());
+    }
 }
@@ -0,0 +1,548 @@
+//! Mounting and unmounting filesystems, with the legacy `mount(2)` and the new
+//! mount API (`fsopen(2)`, `fsmount(2)`, `move_mount(2)`, `open_tree(2)` and
+//! `mount_setattr(2)`) of Linux 5.2+.
+//!
+//! All these operations need `CAP_SYS_ADMIN` in the user namespace owning the
+//! mount namespace of the caller.
+
+use crate::backend::encapsulation::{self, FsconfigCmd};
+use bitflags::bitflags;
+use std::{
+    ffi::OsStr,
+    io::Result,
+    os::unix::io::{AsFd, BorrowedFd, OwnedFd},
+    path::Path,
+};
+
+bitflags! {
+    /// Flags of the legacy [`mount`].
+    pub struct MsFlags: u64 {
+        /// Mount read-only.
+        const RDONLY = libc::MS_RDONLY;
+        /// Ignore set-user-ID and set-group-ID bits.
+        const NOSUID = libc::MS_NOSUID;
+        /// Disallow access to device files.
+        const NODEV = libc::MS_NODEV;
+        /// Disallow program execution.
+        const NOEXEC = libc::MS_NOEXEC;
+        /// Make writes synchronous.
+        const SYNCHRONOUS = libc::MS_SYNCHRONOUS;
+        /// Change the flags of an existing mount.
+        const REMOUNT = libc::MS_REMOUNT;
+        /// Make directory changes synchronous.
+        const DIRSYNC = libc::MS_DIRSYNC;
+        /// Do not update access times.
+        const NOATIME = libc::MS_NOATIME;
+        /// Do not update access times of directories.
+        const NODIRATIME = libc::MS_NODIRATIME;
+        /// Create a bind mount of `source`.
+        const BIND = libc::MS_BIND;
+        /// Move the mount at `source` to `target`.
+        const MOVE = libc::MS_MOVE;
+        /// Apply [`BIND`] or a propagation change to the whole subtree.
+        ///
+        /// [`BIND`]: MsFlags::BIND
+        const REC = libc::MS_REC;
+        /// Suppress some kernel warning messages.
+        const SILENT = libc::MS_SILENT;
+        /// Make the mount unbindable.
+        const UNBINDABLE = libc::MS_UNBINDABLE;
+        /// Make the mount private.
+        const PRIVATE = libc::MS_PRIVATE;
+        /// Make the mount a slave of its peer group.
+        const SLAVE = libc::MS_SLAVE;
+        /// Make the mount shared.
+        const SHARED = libc::MS_SHARED;
+        /// Update access times relative to modification or change times.
+        const RELATIME = libc::MS_RELATIME;
+        /// Always update access times.
+        const STRICTATIME = libc::MS_STRICTATIME;
+        /// Only update times in memory.
+        const LAZYTIME = libc::MS_LAZYTIME;
+    }
+}
+
+bitflags! {
+    /// Flags of [`umount`].
+    pub struct UnmountFlags: i32 {
+        /// Force the unmount even if busy, only supported by some network
+        /// filesystems.
+        const FORCE = libc::MNT_FORCE;
+        /// Detach the mount now and clean it up once it is no longer busy.
+        const DETACH = libc::MNT_DETACH;
+        /// Mark the mount as expired, a second call unmounts it if it was not
+        /// used in between.
+        const EXPIRE = libc::MNT_EXPIRE;
+        /// Do not follow `target` if it is a symlink.
+        const NOFOLLOW = libc::UMOUNT_NOFOLLOW;
+    }
+}
+
+bitflags! {
+    /// Attributes of a mount, `MOUNT_ATTR_*` from `<linux/mount.h>`.
+    ///
+    /// Access times are updated relative to modification or change times
+    /// (`relatime`) unless [`NOATIME`] or [`STRICTATIME`] is set.
+    ///
+    /// [`NOATIME`]: MountAttrFlags::NOATIME
+    /// [`STRICTATIME`]: MountAttrFlags::STRICTATIME
+    pub struct MountAttrFlags: u64 {
+        /// Mount read-only.
+        const RDONLY = 0x0000_0001;
+        /// Ignore set-user-ID and set-group-ID bits.
+        const NOSUID = 0x0000_0002;
+        /// Disallow access to device files.
+        const NODEV = 0x0000_0004;
+        /// Disallow program execution.
+        const NOEXEC = 0x0000_0008;
+        /// Do not update access times.
+        const NOATIME = 0x0000_0010;
+        /// Always update access times.
+        const STRICTATIME = 0x0000_0020;
+        /// The bits selecting how access times are updated, which must be
+        /// cleared to change it with [`mount_setattr`].
+        const ATIME = 0x0000_0070;
+        /// Do not update access times of directories.
+        const NODIRATIME = 0x0000_0080;
+        /// Map the owners of the files through a user namespace, see
+        /// [`MountAttr::idmap`].
+        const IDMAP = 0x0010_0000;
+        /// Do not follow symlinks when resolving paths.
+        const NOSYMFOLLOW = 0x0020_0000;
+    }
+}
+
+bitflags! {
+    /// Flags of [`open_tree`].
+    pub struct OpenTreeFlags: u32 {
+        /// Create a detached bind mount of the mount at `path` rather than
+        /// opening it.
+        const CLONE = libc::OPEN_TREE_CLONE;
+        /// With [`CLONE`], clone the whole subtree.
+        ///
+        /// [`CLONE`]: OpenTreeFlags::CLONE
+        const RECURSIVE = libc::AT_RECURSIVE as u32;
+        /// Do not follow `path` if it is a symlink.
+        const NOFOLLOW = libc::AT_SYMLINK_NOFOLLOW as u32;
+        /// Do not trigger an automount at `path`.
+        const NO_AUTOMOUNT = libc::AT_NO_AUTOMOUNT as u32;
+    }
+}
+
+bitflags! {
+    /// Flags of [`move_mount`].
+    pub struct MoveMountFlags: u32 {
+        /// Follow symlinks on the source path.
+        const F_SYMLINKS = 0x0000_0001;
+        /// Follow automounts on the source path.
+        const F_AUTOMOUNTS = 0x0000_0002;
+        /// Follow symlinks on the target path.
+        const T_SYMLINKS = 0x0000_0010;
+        /// Follow automounts on the target path.
+        const T_AUTOMOUNTS = 0x0000_0020;
+        /// Add the target to the peer group of the source, instead of moving.
+        const SET_GROUP = 0x0000_0100;
+    }
+}
+
+/// `MOVE_MOUNT_F_EMPTY_PATH` from `<linux/mount.h>`.
+const MOVE_MOUNT_F_EMPTY_PATH: u32 = 0x0000_0004;
+
+/// Propagation type of a mount, see `mount_namespaces(7)`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum MountPropagation {
+    /// Mount events do not propagate to or from this mount.
+    Private,
+    /// Mount events propagate between this mount and its peers.
+    Shared,
+    /// Mount events propagate from the master peer group to this mount.
+    Slave,
+    /// Private, and this mount can not be bind mounted.
+    Unbindable,
+}
+
+impl MountPropagation {
+    fn bits(self) -> u64 {
+        match self {
+            MountPropagation::Private => libc::MS_PRIVATE,
+            MountPropagation::Shared => libc::MS_SHARED,
+            MountPropagation::Slave => libc::MS_SLAVE,
+            MountPropagation::Unbindable => libc::MS_UNBINDABLE,
+        }
+    }
+}
+
+/// Mounts the filesystem `source` of type `fs_type` on `target` with the
+/// legacy `mount(2)`.
+///
+/// `data` holds the comma-separated filesystem-specific options. `source`,
+/// `fs_type` and `data` are ignored by some operations, e.g., `fs_type` and
+/// `data` with [`MsFlags::BIND`] or a propagation change, and can be given as
+/// `None::<&str>`.
+///
+/// ```no_run
+/// use fs::mount::{mount, MsFlags};
+///
+/// mount(Some("tmpfs"), "/mnt", Some("tmpfs"), MsFlags::NODEV, Some("size=1m"))?;
+/// mount(None::<&str>, "/mnt", None::<&str>, MsFlags::PRIVATE, None::<&str>)?;
+/// # std::io::Result::Ok(())
+/// ```
+pub fn mount<S: AsRef<OsStr>, P: AsRef<Path>, T: AsRef<OsStr>, D: AsRef<OsStr>>(
+    source: Option<S>,
+    target: P,
+    fs_type: Option<T>,
+    flags: MsFlags,
+    data: Option<D>,
+) -> Result<()> {
+    encapsulation::mount(
+        source.as_ref().map(AsRef::as_ref),
+        target,
+        fs_type.as_ref().map(AsRef::as_ref),
+        flags.bits(),
+        data.as_ref().map(AsRef::as_ref),
+    )
+}
+
+/// Unmounts the topmost filesystem mounted on `target`.
+pub fn umount<P: AsRef<Path>>(target: P, flags: UnmountFlags) -> Result<()> {
+    encapsulation::umount2(target, flags.bits())
+}
+
+/// A filesystem context, which is configured and then turned into a mount.
+///
+/// ```no_run
+/// use fs::mount::{move_mount_to, FsContext, MountAttrFlags};
+///
+/// let ctx = FsContext::new("tmpfs")?;
+/// ctx.set_string("size", "16m")?;
+/// ctx.create()?;
+/// let mount = ctx.mount(MountAttrFlags::NODEV)?;
+/// move_mount_to(&mount, "/mnt")?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct FsContext(OwnedFd);
+
+impl FsContext {
+    /// Creates a context for a new filesystem of type `fs_type`, e.g.,
+    /// `tmpfs`.
+    pub fn new<S: AsRef<OsStr>>(fs_type: S) -> Result<Self> {
+        encapsulation::fsopen(fs_type.as_ref()).map(FsContext)
+    }
+
+    /// Sets the boolean option `key`.
+    pub fn set_flag<K: AsRef<OsStr>>(&self, key: K) -> Result<()> {
+        encapsulation::fsconfig(self, FsconfigCmd::SetFlag(key.as_ref()))
+    }
+
+    /// Sets the option `key` to the string `value`.
+    pub fn set_string<K: AsRef<OsStr>, V: AsRef<OsStr>>(&self, key: K, value: V) -> Result<()> {
+        encapsulation::fsconfig(self, FsconfigCmd::SetString(key.as_ref(), value.as_ref()))
+    }
+
+    /// Sets the option `key` to the binary blob `value`.
+    pub fn set_binary<K: AsRef<OsStr>>(&self, key: K, value: &[u8]) -> Result<()> {
+        encapsulation::fsconfig(self, FsconfigCmd::SetBinary(key.as_ref(), value))
+    }
+
+    /// Sets the option `key` to `path`, e.g., the `source` device.
+    pub fn set_path<K: AsRef<OsStr>, P: AsRef<Path>>(&self, key: K, path: P) -> Result<()> {
+        encapsulation::fsconfig(self, FsconfigCmd::SetPath(key.as_ref(), path.as_ref()))
+    }
+
+    /// Sets the option `key` to the file referred to by `fd`.
+    pub fn set_fd<K: AsRef<OsStr>, Fd: AsFd>(&self, key: K, fd: Fd) -> Result<()> {
+        encapsulation::fsconfig(self, FsconfigCmd::SetFd(key.as_ref(), fd.as_fd()))
+    }
+
+    /// Creates the filesystem, or reuses an existing superblock, from the
+    /// options set so far.
+    pub fn create(&self) -> Result<()> {
+        encapsulation::fsconfig(self, FsconfigCmd::Create)
+    }
+
+    /// Creates a detached mount of the filesystem with the attributes `attrs`,
+    /// which can be attached with [`move_mount_to`].
+    ///
+    /// [`create`](FsContext::create) must have been called.
+    pub fn mount(&self, attrs: MountAttrFlags) -> Result<OwnedFd> {
+        encapsulation::fsmount(self, attrs.bits() as u32)
+    }
+}
+
+impl AsFd for FsContext {
+    #[inline]
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.0.as_fd()
+    }
+}
+
+/// Opens the mount at `path`.
+///
+/// With [`OpenTreeFlags::CLONE`], a detached bind mount of `path` is created
+/// instead, which can be changed with [`fd_mount_setattr`] and attached with
+/// [`move_mount_to`]. It is unmounted once the returned fd is closed unless it
+/// was attached.
+pub fn open_tree<P: AsRef<Path>>(path: P, flags: OpenTreeFlags) -> Result<OwnedFd> {
+    encapsulation::open_tree(None, path, flags.bits())
+}
+
+/// Moves the mount at `from` to `to`.
+pub fn move_mount<P: AsRef<Path>, Q: AsRef<Path>>(
+    from: P,
+    to: Q,
+    flags: MoveMountFlags,
+) -> Result<()> {
+    encapsulation::move_mount(None, from, None, to, flags.bits())
+}
+
+/// Attaches the mount referred to by `mount`, returned by [`FsContext::mount`]
+/// or [`open_tree`], on `to`.
+pub fn move_mount_to<Fd: AsFd, P: AsRef<Path>>(mount: Fd, to: P) -> Result<()> {
+    encapsulation::move_mount(Some(mount.as_fd()), "", None, to, MOVE_MOUNT_F_EMPTY_PATH)
+}
+
+/// Changes to the attributes of a mount, applied with [`mount_setattr`] or
+/// [`fd_mount_setattr`].
+///
+/// ```no_run
+/// use fs::mount::{mount_setattr, MountAttr, MountAttrFlags};
+///
+/// mount_setattr("/mnt", true, MountAttr::new().set(MountAttrFlags::RDONLY))?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct MountAttr<'fd> {
+    set: MountAttrFlags,
+    clear: MountAttrFlags,
+    propagation: Option<MountPropagation>,
+    userns: Option<BorrowedFd<'fd>>,
+}
+
+impl<'fd> MountAttr<'fd> {
+    /// Creates a blank new set of changes, which changes nothing.
+    pub fn new() -> Self {
+        Self {
+            set: MountAttrFlags::empty(),
+            clear: MountAttrFlags::empty(),
+            propagation: None,
+            userns: None,
+        }
+    }
+
+    /// Sets the attributes `attrs`.
+    pub fn set(&mut self, attrs: MountAttrFlags) -> &mut Self {
+        self.set |= attrs;
+        self
+    }
+
+    /// Clears the attributes `attrs`.
+    pub fn clear(&mut self, attrs: MountAttrFlags) -> &mut Self {
+        self.clear |= attrs;
+        self
+    }
+
+    /// Changes the propagation type.
+    pub fn propagation(&mut self, propagation: MountPropagation) -> &mut Self {
+        self.propagation = Some(propagation);
+        self
+    }
+
+    /// Makes the mount idmapped, with the ID mappings of the user namespace
+    /// referred to by `userns`, e.g., an fd of `/proc/<pid>/ns/user`.
+    ///
+    /// This can only be done on a detached mount created with
+    /// [`open_tree`], which has not been idmapped yet.
+    pub fn idmap(&mut self, userns: BorrowedFd<'fd>) -> &mut Self {
+        self.set |= MountAttrFlags::IDMAP;
+        self.userns = Some(userns);
+        self
+    }
+}
+
+impl Default for MountAttr<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn setattr_flags(recursive: bool) -> u32 {
+    if recursive {
+        libc::AT_RECURSIVE as u32
+    } else {
+        0
+    }
+}
+
+/// Changes the attributes of the mount at `path`, and of all the mounts
+/// below it if `recursive` is true.
+pub fn mount_setattr<P: AsRef<Path>>(path: P, recursive: bool, attr: &MountAttr<'_>) -> Result<()> {
+    encapsulation::mount_setattr(
+        None,
+        path,
+        setattr_flags(recursive),
+        attr.set.bits(),
+        attr.clear.bits(),
+        attr.propagation.map_or(0, MountPropagation::bits),
+        attr.userns,
+    )
+}
+
+/// Changes the attributes of the mount referred to by `mount`, and of all the
+/// mounts below it if `recursive` is true.
+pub fn fd_mount_setattr<Fd: AsFd>(mount: Fd, recursive: bool, attr: &MountAttr<'_>) -> Result<()> {
+    encapsulation::mount_setattr(
+        Some(mount.as_fd()),
+        "",
+        setattr_flags(recursive) | libc::AT_EMPTY_PATH as u32,
+        attr.set.bits(),
+        attr.clear.bits(),
+        attr.propagation.map_or(0, MountPropagation::bits),
+        attr.userns,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        fs_stats,
+        functions::{create_dir_all, read_to_string, remove_dir, write},
+        FsType,
+    };
+    use nix::sched::{unshare, CloneFlags};
+    use std::io::ErrorKind;
+
+    /// Moves the current test thread into a private mount namespace, in a new
+    /// user namespace if we are unprivileged. Returns false if this is not
+    /// permitted here, in which case the test should be skipped.
+    fn enter_mount_namespace() -> bool {
+        if unshare(CloneFlags::CLONE_NEWNS).is_err()
+            && unshare(CloneFlags::CLONE_NEWUSER | CloneFlags::CLONE_NEWNS).is_err()
+        {
+            return false;
+        }
+
+        // Keep our mounts from propagating back to the parent namespace.
+        mount(
+            None::<&str>,
+            "/",
+            None::<&str>,
+            MsFlags::REC | MsFlags::PRIVATE,
+            None::<&str>,
+        )
+        .is_ok()
+    }
+
+    fn unsupported(e: &std::io::Error) -> bool {
+        matches!(e.raw_os_error(), Some(libc::ENOSYS | libc::EPERM))
+    }
+
+    #[test]
+    fn legacy_mount_umount() {
+        if !enter_mount_namespace() {
+            return;
+        }
+
+        let target = "/tmp/test_legacy_mount_umount";
+        create_dir_all(target).unwrap();
+        mount(
+            Some("tmpfs"),
+            target,
+            Some("tmpfs"),
+            MsFlags::NODEV | MsFlags::NOSUID,
+            Some("size=1m,mode=755"),
+        )
+        .unwrap();
+        assert_eq!(fs_stats(target).unwrap().fs_type(), FsType::Tmpfs);
+
+        umount(target, UnmountFlags::empty()).unwrap();
+        assert_ne!(fs_stats(target).unwrap().fs_type(), FsType::Tmpfs);
+        remove_dir(target).unwrap();
+    }
+
+    #[test]
+    fn new_mount_api() {
+        if !enter_mount_namespace() {
+            return;
+        }
+
+        let ctx = match FsContext::new("tmpfs") {
+            Ok(ctx) => ctx,
+            Err(e) if unsupported(&e) => return,
+            Err(e) => panic!("{}", e),
+        };
+        ctx.set_string("size", "1m").unwrap();
+        ctx.set_flag("noswap")
+            .or_else(|e| match e.kind() {
+                // `noswap` is only known since Linux 6.4.
+                ErrorKind::InvalidInput => Ok(()),
+                _ => Err(e),
+            })
+            .unwrap();
+        assert!(ctx.set_string("no_such_option", "1").is_err());
+        ctx.create().unwrap();
+        let tmpfs = ctx.mount(MountAttrFlags::NODEV).unwrap();
+
+        let target = "/tmp/test_new_mount_api";
+        let bind = "/tmp/test_new_mount_api_bind";
+        create_dir_all(target).unwrap();
+        create_dir_all(bind).unwrap();
+        move_mount_to(&tmpfs, target).unwrap();
+        assert_eq!(fs_stats(target).unwrap().fs_type(), FsType::Tmpfs);
+        write(format!("{}/file", target), "hello").unwrap();
+
+        // A read-only bind mount of the tmpfs.
+        let clone = open_tree(target, OpenTreeFlags::CLONE).unwrap();
+        fd_mount_setattr(&clone, false, MountAttr::new().set(MountAttrFlags::RDONLY)).unwrap();
+        move_mount_to(&clone, bind).unwrap();
+        assert_eq!(read_to_string(format!("{}/file", bind)).unwrap(), "hello");
+        let err = write(format!("{}/file", bind), "bye").unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::EROFS));
+
+        // Make it writable again through its path.
+        mount_setattr(bind, false, MountAttr::new().clear(MountAttrFlags::RDONLY)).unwrap();
+        write(format!("{}/file", bind), "bye").unwrap();
+        assert_eq!(read_to_string(format!("{}/file", target)).unwrap(), "bye");
+
+        umount(bind, UnmountFlags::DETACH).unwrap();
+        umount(target, UnmountFlags::DETACH).unwrap();
+        remove_dir(bind).unwrap();
+        remove_dir(target).unwrap();
+    }
+
+    #[test]
+    fn move_mount_between_dirs() {
+        if !enter_mount_namespace() {
+            return;
+        }
+
+        let from = "/tmp/test_move_mount_from";
+        let to = "/tmp/test_move_mount_to";
+        create_dir_all(from).unwrap();
+        create_dir_all(to).unwrap();
+        mount(
+            Some("tmpfs"),
+            from,
+            Some("tmpfs"),
+            MsFlags::empty(),
+            None::<&str>,
+        )
+        .unwrap();
+
+        match move_mount(from, to, MoveMountFlags::empty()) {
+            Err(e) if unsupported(&e) => {
+                umount(from, UnmountFlags::DETACH).unwrap();
+            }
+            res => {
+                res.unwrap();
+                assert_eq!(fs_stats(to).unwrap().fs_type(), FsType::Tmpfs);
+                assert_ne!(fs_stats(from).unwrap().fs_type(), FsType::Tmpfs);
+                umount(to, UnmountFlags::DETACH).unwrap();
+            }
+        }
+
+        remove_dir(from).unwrap();
+        remove_dir(to).unwrap();
+    }
+}
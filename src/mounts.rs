@@ -0,0 +1,356 @@
+//! The mount table of the current process, parsed from `/proc/self/mountinfo`.
+
+use crate::{
+    functions::{canonicalize, metadata, read},
+    metadata::StatxExt,
+};
+use std::{
+    ffi::{OsStr, OsString},
+    io::{Error, ErrorKind, Result},
+    os::unix::ffi::{OsStrExt, OsStringExt},
+    path::{Path, PathBuf},
+};
+
+const MOUNTINFO: &str = "/proc/self/mountinfo";
+
+/// Propagation settings of a mount, from the optional fields of a
+/// `mountinfo` entry, see `mount_namespaces(7)`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Propagation {
+    /// The peer group this mount is shared in (`shared:N`).
+    pub shared: Option<u64>,
+    /// The peer group this mount is a slave to (`master:N`).
+    pub master: Option<u64>,
+    /// The closest dominant peer group this mount receives propagation from
+    /// (`propagate_from:N`).
+    pub propagate_from: Option<u64>,
+    /// The mount is unbindable (`unbindable`).
+    pub unbindable: bool,
+}
+
+/// An entry of the mount table.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MountInfo {
+    mount_id: u64,
+    parent_id: u64,
+    dev: (u32, u32),
+    root: PathBuf,
+    mount_point: PathBuf,
+    options: String,
+    propagation: Propagation,
+    fs_type: String,
+    source: OsString,
+    super_options: String,
+}
+
+impl MountInfo {
+    /// Returns the unique ID of the mount, which is reported by
+    /// [`StatxExt::mount_id`].
+    #[inline]
+    pub fn mount_id(&self) -> u64 {
+        self.mount_id
+    }
+
+    /// Returns the ID of the parent mount, or of itself for the root of the
+    /// mount namespace.
+    #[inline]
+    pub fn parent_id(&self) -> u64 {
+        self.parent_id
+    }
+
+    /// Returns the major ID of the device of the files in the mount.
+    #[inline]
+    pub fn dev_major(&self) -> u32 {
+        self.dev.0
+    }
+
+    /// Returns the minor ID of the device of the files in the mount.
+    #[inline]
+    pub fn dev_minor(&self) -> u32 {
+        self.dev.1
+    }
+
+    /// Returns the path of the directory in the filesystem which forms the
+    /// root of this mount, `/` unless it is a bind mount.
+    #[inline]
+    pub fn root(&self) -> &Path {
+        self.root.as_path()
+    }
+
+    /// Returns where the filesystem is mounted, relative to the root of the
+    /// process.
+    #[inline]
+    pub fn mount_point(&self) -> &Path {
+        self.mount_point.as_path()
+    }
+
+    /// Returns the per-mount options, e.g., `rw`, `nosuid`, `relatime`.
+    pub fn options(&self) -> impl Iterator<Item = &str> {
+        self.options.split(',')
+    }
+
+    /// Returns true if the per-mount options contain `option`.
+    pub fn has_option(&self, option: &str) -> bool {
+        self.options().any(|opt| opt == option)
+    }
+
+    /// Returns the propagation settings of the mount.
+    #[inline]
+    pub fn propagation(&self) -> &Propagation {
+        &self.propagation
+    }
+
+    /// Returns the filesystem type, e.g., `ext4` or `fuse.sshfs`.
+    #[inline]
+    pub fn fs_type(&self) -> &str {
+        &self.fs_type
+    }
+
+    /// Returns the filesystem-specific source, e.g., `/dev/sda1`, `none` if
+    /// there is no source.
+    #[inline]
+    pub fn source(&self) -> &OsStr {
+        &self.source
+    }
+
+    /// Returns the per-superblock options, e.g., `rw`, `errors=remount-ro`.
+    pub fn super_options(&self) -> impl Iterator<Item = &str> {
+        self.super_options.split(',')
+    }
+
+    /// Parses a line of `/proc/self/mountinfo`.
+    ///
+    /// Paths and the source are taken as they are, they need not be valid
+    /// UTF-8. Invalid UTF-8 in the other fields is replaced with `U+FFFD`.
+    pub fn parse_line<L: AsRef<OsStr>>(line: L) -> Result<MountInfo> {
+        let line = line.as_ref();
+        let invalid = || {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("invalid mountinfo line '{}'", line.to_string_lossy()),
+            )
+        };
+        let mut fields = line.as_bytes().split(|&b| b == b' ');
+        let mut next = || fields.next().ok_or_else(invalid);
+
+        let mount_id = parse_number(next()?).ok_or_else(invalid)?;
+        let parent_id = parse_number(next()?).ok_or_else(invalid)?;
+        let dev = split_once(next()?, b':')
+            .and_then(|(major, minor)| Some((parse_number(major)?, parse_number(minor)?)))
+            .ok_or_else(invalid)?;
+        let root = PathBuf::from(unescape(next()?));
+        let mount_point = PathBuf::from(unescape(next()?));
+        let options = String::from_utf8_lossy(next()?).into_owned();
+
+        let mut propagation = Propagation::default();
+        loop {
+            let field = next()?;
+            if field == b"-" {
+                break;
+            }
+
+            let (tag, value) = split_once(field, b':').unwrap_or((field, b""));
+            let value = || parse_number(value).ok_or_else(invalid);
+            match tag {
+                b"shared" => propagation.shared = Some(value()?),
+                b"master" => propagation.master = Some(value()?),
+                b"propagate_from" => propagation.propagate_from = Some(value()?),
+                b"unbindable" => propagation.unbindable = true,
+                // Optional fields unknown to us should be ignored.
+                _ => {}
+            }
+        }
+
+        let fs_type = unescape(next()?).to_string_lossy().into_owned();
+        let source = unescape(next()?);
+        let super_options = String::from_utf8_lossy(next()?).into_owned();
+
+        Ok(MountInfo {
+            mount_id,
+            parent_id,
+            dev,
+            root,
+            mount_point,
+            options,
+            propagation,
+            fs_type,
+            source,
+            super_options,
+        })
+    }
+}
+
+/// Splits `field` at the first `sep`.
+fn split_once(field: &[u8], sep: u8) -> Option<(&[u8], &[u8])> {
+    let idx = field.iter().position(|&b| b == sep)?;
+    Some((&field[..idx], &field[idx + 1..]))
+}
+
+/// Parses a decimal number.
+fn parse_number<T: std::str::FromStr>(field: &[u8]) -> Option<T> {
+    std::str::from_utf8(field).ok()?.parse().ok()
+}
+
+/// Decodes the octal escapes (e.g., `\040` for a space) used by the kernel
+/// for spaces, tabs, newlines and backslashes.
+fn unescape(bytes: &[u8]) -> OsString {
+    let mut unescaped = Vec::with_capacity(bytes.len());
+
+    let mut idx = 0;
+    while idx < bytes.len() {
+        let octal = bytes
+            .get(idx + 1..idx + 4)
+            .filter(|digits| {
+                bytes[idx] == b'\\' && digits.iter().all(|d| (b'0'..=b'7').contains(d))
+            })
+            .map(|digits| {
+                digits
+                    .iter()
+                    .fold(0_u32, |acc, d| acc * 8 + (d - b'0') as u32)
+            });
+
+        match octal {
+            Some(byte) if byte <= u8::MAX as u32 => {
+                unescaped.push(byte as u8);
+                idx += 4;
+            }
+            _ => {
+                unescaped.push(bytes[idx]);
+                idx += 1;
+            }
+        }
+    }
+
+    OsString::from_vec(unescaped)
+}
+
+/// Parses the contents of a `mountinfo` file, one entry per line.
+pub fn parse_mountinfo<C: AsRef<OsStr>>(contents: C) -> Result<Vec<MountInfo>> {
+    contents
+        .as_ref()
+        .as_bytes()
+        .split(|&b| b == b'\n')
+        .filter(|line| !line.is_empty())
+        .map(|line| MountInfo::parse_line(OsStr::from_bytes(line)))
+        .collect()
+}
+
+/// Returns the mount table of the current process.
+pub fn mounts() -> Result<Vec<MountInfo>> {
+    parse_mountinfo(OsString::from_vec(read(MOUNTINFO)?))
+}
+
+/// Returns the mount containing `path`.
+///
+/// The mount is looked up by the mount ID of `path`, which is exact even
+/// with bind mounts. On kernels that do not report mount IDs (before Linux
+/// 5.8), the mount with the longest mount point that is a prefix of `path` is
+/// used, see [`mount_point_of`].
+pub fn mount_for<P: AsRef<Path>>(path: P) -> Result<MountInfo> {
+    let path = path.as_ref();
+    let mounts = mounts()?;
+
+    match metadata(path)?.mount_id() {
+        Some(mount_id) => mounts
+            .into_iter()
+            .find(|mount| mount.mount_id == mount_id)
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, "mount not found in mountinfo")),
+        None => longest_prefix_mount(mounts, &canonicalize(path)?),
+    }
+}
+
+/// Returns the mount point of the filesystem containing `path`, i.e., the
+/// longest mount point that is a prefix of the canonical form of `path`.
+pub fn mount_point_of<P: AsRef<Path>>(path: P) -> Result<PathBuf> {
+    let path = canonicalize(path)?;
+    longest_prefix_mount(mounts()?, &path).map(|mount| mount.mount_point)
+}
+
+fn longest_prefix_mount(mounts: Vec<MountInfo>, path: &Path) -> Result<MountInfo> {
+    // Mounts stacked on the same mount point are listed in order, the last one
+    // is the visible one, hence `max_by_key` which returns the last maximum.
+    mounts
+        .into_iter()
+        .filter(|mount| path.starts_with(&mount.mount_point))
+        .max_by_key(|mount| mount.mount_point.as_os_str().len())
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, "mount not found in mountinfo"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let line = "36 35 98:0 /mnt1 /mnt\\0402 rw,noatime master:1 shared:7 - ext3 /dev/root rw,errors=continue";
+        let mount = MountInfo::parse_line(line).unwrap();
+
+        assert_eq!(mount.mount_id(), 36);
+        assert_eq!(mount.parent_id(), 35);
+        assert_eq!((mount.dev_major(), mount.dev_minor()), (98, 0));
+        assert_eq!(mount.root(), Path::new("/mnt1"));
+        assert_eq!(mount.mount_point(), Path::new("/mnt 2"));
+        assert!(mount.has_option("noatime"));
+        assert!(!mount.has_option("ro"));
+        assert_eq!(
+            mount.propagation(),
+            &Propagation {
+                shared: Some(7),
+                master: Some(1),
+                propagate_from: None,
+                unbindable: false,
+            }
+        );
+        assert_eq!(mount.fs_type(), "ext3");
+        assert_eq!(mount.source(), "/dev/root");
+        assert_eq!(
+            mount.super_options().collect::<Vec<_>>(),
+            ["rw", "errors=continue"]
+        );
+
+        let line = "23 28 0:22 / /proc rw,relatime unbindable - proc proc rw";
+        assert!(
+            MountInfo::parse_line(line)
+                .unwrap()
+                .propagation()
+                .unbindable
+        );
+        assert!(MountInfo::parse_line("23 28 0:22 / /proc rw").is_err());
+        assert!(MountInfo::parse_line("a 28 0:22 / /proc rw - proc proc rw").is_err());
+
+        let contents = OsStr::from_bytes(
+            b"23 28 0:22 / /proc rw - proc proc rw\n\
+              24 28 0:23 / /mnt/\xff rw - tmpfs tmp\xfe rw\n",
+        );
+        let mounts = parse_mountinfo(contents).unwrap();
+        assert_eq!(mounts.len(), 2);
+        assert_eq!(mounts[1].mount_point().as_os_str().as_bytes(), b"/mnt/\xff");
+        assert_eq!(mounts[1].source().as_bytes(), b"tmp\xfe");
+    }
+
+    #[test]
+    fn unescape_octal() {
+        let unescape = |field: &str| unescape(field.as_bytes());
+        assert_eq!(unescape("a\\040b\\011c\\012d\\134e"), "a b\tc\nd\\e");
+        assert_eq!(unescape("\\04"), "\\04");
+        assert_eq!(unescape("\\777"), "\\777");
+        assert_eq!(unescape("\\xyz"), "\\xyz");
+    }
+
+    #[test]
+    fn lookup() {
+        let mounts = mounts().unwrap();
+        assert!(!mounts.is_empty());
+
+        let proc = mount_for("/proc/self").unwrap();
+        assert_eq!(proc.fs_type(), "proc");
+        assert_eq!(proc.mount_point(), Path::new("/proc"));
+        assert_eq!(mount_point_of("/proc/self").unwrap(), Path::new("/proc"));
+
+        let cargo_toml = mount_for("Cargo.toml").unwrap();
+        assert_eq!(
+            cargo_toml.mount_point(),
+            mount_point_of("Cargo.toml").unwrap()
+        );
+    }
+}
@@ -1,8 +1,23 @@
 //! Stuff that does not belong to `std::fs` or `std::os::unix::fs` but has to be
 //! used in our implementation.
 
+use crate::backend::encapsulation::{self, ClockId};
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    io::Result,
+    time::Duration,
+};
+
+const NSEC_PER_SEC: i64 = 1_000_000_000;
+
 /// A struct similar to the [`std::time::SystemTime`]
 ///
+/// `nsec` is expected to be in range `[0, 1_000_000_000)`, a time before the
+/// Unix epoch is represented with a negative `sec`, e.g., 0.5s before the
+/// epoch is `{ sec: -1, nsec: 500_000_000 }`. [`SystemTime::new`] normalizes
+/// its arguments to this form.
+///
 /// [`std::time::SystemTime`]: https://doc.rust-lang.org/std/time/struct.SystemTime.html
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Default)]
 pub struct SystemTime {
@@ -10,8 +25,302 @@ pub struct SystemTime {
     pub nsec: i64,
 }
 
+/// An anchor in time which can be used to create new `SystemTime` instances or
+/// learn about where in time a `SystemTime` lies, "1970-01-01 00:00:00 UTC".
+pub const UNIX_EPOCH: SystemTime = SystemTime { sec: 0, nsec: 0 };
+
 impl SystemTime {
+    /// Creates a new `SystemTime`, an `nsec` out of range `[0, 1_000_000_000)`
+    /// is carried into `sec`.
+    ///
+    /// # Panics
+    /// Panics if the carry overflows `sec`.
     pub fn new(sec: i64, nsec: i64) -> Self {
-        Self { sec, nsec }
+        SystemTime { sec, nsec }
+            .normalize()
+            .expect("overflow when normalizing SystemTime")
+    }
+
+    /// Returns the same time with `nsec` in `[0, 1_000_000_000)`, or `None`
+    /// if the carry overflows `sec`. The fields are public, so they may be out
+    /// of range.
+    fn normalize(self) -> Option<SystemTime> {
+        Some(SystemTime {
+            sec: self
+                .sec
+                .checked_add(self.nsec.checked_div_euclid(NSEC_PER_SEC)?)?,
+            nsec: self.nsec.checked_rem_euclid(NSEC_PER_SEC)?,
+        })
+    }
+
+    /// Returns the system time corresponding to "now".
+    ///
+    /// This invokes the raw `clock_gettime(CLOCK_REALTIME)` syscall.
+    pub fn now() -> Result<SystemTime> {
+        encapsulation::clock_gettime(ClockId::Realtime)
+            .map(|(sec, nsec)| SystemTime::new(sec, nsec))
+    }
+
+    /// Returns the amount of time elapsed from an earlier point in time.
+    ///
+    /// # Errors
+    /// Returns an `Err` if `earlier` is later than `self`, the error contains
+    /// how far `self` is from `earlier`.
+    pub fn duration_since(
+        &self,
+        earlier: SystemTime,
+    ) -> std::result::Result<Duration, SystemTimeError> {
+        if *self >= earlier {
+            Ok(diff(*self, earlier))
+        } else {
+            Err(SystemTimeError(diff(earlier, *self)))
+        }
+    }
+
+    /// Returns the amount of time elapsed since this system time was created.
+    ///
+    /// # Errors
+    /// Returns an `Err` if the current time can not be retrieved, or `self`
+    /// is later than the current time.
+    pub fn elapsed(&self) -> Result<Duration> {
+        SystemTime::now()?
+            .duration_since(*self)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+
+    /// Returns `Some(t)` where `t` is the time `self + duration` if `t` can be
+    /// represented as `SystemTime`, `None` otherwise.
+    pub fn checked_add(&self, duration: Duration) -> Option<SystemTime> {
+        let this = self.normalize()?;
+        let sec = i64::try_from(duration.as_secs()).ok()?;
+        let mut sec = this.sec.checked_add(sec)?;
+        let mut nsec = this.nsec + duration.subsec_nanos() as i64;
+        if nsec >= NSEC_PER_SEC {
+            nsec -= NSEC_PER_SEC;
+            sec = sec.checked_add(1)?;
+        }
+
+        Some(SystemTime { sec, nsec })
+    }
+
+    /// Returns `Some(t)` where `t` is the time `self - duration` if `t` can be
+    /// represented as `SystemTime`, `None` otherwise.
+    pub fn checked_sub(&self, duration: Duration) -> Option<SystemTime> {
+        let this = self.normalize()?;
+        let sec = i64::try_from(duration.as_secs()).ok()?;
+        let mut sec = this.sec.checked_sub(sec)?;
+        let mut nsec = this.nsec - duration.subsec_nanos() as i64;
+        if nsec < 0 {
+            nsec += NSEC_PER_SEC;
+            sec = sec.checked_sub(1)?;
+        }
+
+        Some(SystemTime { sec, nsec })
+    }
+}
+
+/// Computes `later - earlier`, `later` should not be earlier than `earlier`.
+fn diff(later: SystemTime, earlier: SystemTime) -> Duration {
+    let (sec, nsec) = if later.nsec >= earlier.nsec {
+        (later.sec.abs_diff(earlier.sec), later.nsec - earlier.nsec)
+    } else {
+        (
+            later.sec.abs_diff(earlier.sec) - 1,
+            later.nsec + NSEC_PER_SEC - earlier.nsec,
+        )
+    };
+
+    Duration::new(sec, nsec as u32)
+}
+
+impl From<std::time::SystemTime> for SystemTime {
+    fn from(value: std::time::SystemTime) -> Self {
+        // `std::time::SystemTime` stores normalized `i64` seconds on Linux, so
+        // the results fit in an `i64`, even for `i64::MIN` seconds, which is
+        // `2^63` seconds before the epoch and can not go through a `Duration`
+        // based computation like `checked_sub`.
+        let (sec, nsec) = match value.duration_since(std::time::UNIX_EPOCH) {
+            Ok(after) => (after.as_secs() as i128, after.subsec_nanos() as i64),
+            Err(e) => {
+                let before = e.duration();
+                match before.subsec_nanos() {
+                    0 => (-(before.as_secs() as i128), 0),
+                    nsec => (-(before.as_secs() as i128) - 1, NSEC_PER_SEC - nsec as i64),
+                }
+            }
+        };
+
+        SystemTime {
+            sec: i64::try_from(sec).expect("std::time::SystemTime out of range"),
+            nsec,
+        }
+    }
+}
+
+impl TryFrom<SystemTime> for std::time::SystemTime {
+    type Error = std::io::Error;
+
+    /// Fails if `value` can not be represented as `std::time::SystemTime`.
+    fn try_from(value: SystemTime) -> Result<Self> {
+        let out_of_range =
+            || std::io::Error::new(std::io::ErrorKind::InvalidInput, "SystemTime out of range");
+        let value = value.normalize().ok_or_else(out_of_range)?;
+        let nsec = Duration::from_nanos(value.nsec as u64);
+
+        if value.sec >= 0 {
+            std::time::UNIX_EPOCH.checked_add(Duration::new(value.sec as u64, 0) + nsec)
+        } else {
+            // `-(sec + 1)` does not overflow, `-sec` does for `i64::MIN`.
+            std::time::UNIX_EPOCH
+                .checked_sub(Duration::from_secs((-(value.sec + 1)) as u64))
+                .and_then(|time| time.checked_sub(Duration::from_secs(1)))
+                .and_then(|time| time.checked_add(nsec))
+        }
+        .ok_or_else(out_of_range)
+    }
+}
+
+/// An error returned from the `duration_since` method on `SystemTime`, used to
+/// learn how far in the opposite direction a system time lies.
+#[derive(Clone, Debug)]
+pub struct SystemTimeError(Duration);
+
+impl SystemTimeError {
+    /// Returns the positive duration which represents how far forward the
+    /// second system time was from the first.
+    #[inline]
+    pub fn duration(&self) -> Duration {
+        self.0
+    }
+}
+
+impl Display for SystemTimeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "second time provided was later than self")
+    }
+}
+
+impl Error for SystemTimeError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn new_normalizes_nsec() {
+        assert_eq!(
+            SystemTime::new(0, -1),
+            SystemTime {
+                sec: -1,
+                nsec: 999_999_999
+            }
+        );
+        assert_eq!(
+            SystemTime::new(1, 1_500_000_000),
+            SystemTime {
+                sec: 2,
+                nsec: 500_000_000
+            }
+        );
+        assert!(SystemTime::new(0, -1) < UNIX_EPOCH);
+    }
+
+    #[test]
+    fn now() {
+        let now = SystemTime::now().unwrap();
+        let std_now = SystemTime::from(std::time::SystemTime::now());
+
+        assert!(std_now >= now);
+        assert!(std_now.duration_since(now).unwrap() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn duration_since() {
+        let earlier = SystemTime::new(-1, 500_000_000);
+        let later = SystemTime::new(1, 0);
+
+        assert_eq!(
+            later.duration_since(earlier).unwrap(),
+            Duration::from_millis(1500)
+        );
+        assert_eq!(
+            earlier.duration_since(later).unwrap_err().duration(),
+            Duration::from_millis(1500)
+        );
+    }
+
+    #[test]
+    fn checked_add_sub() {
+        let time = SystemTime::new(0, 600_000_000);
+        let duration = Duration::from_millis(700);
+
+        assert_eq!(
+            time.checked_add(duration),
+            Some(SystemTime::new(1, 300_000_000))
+        );
+        assert_eq!(
+            time.checked_sub(duration),
+            Some(SystemTime::new(-1, 900_000_000))
+        );
+        assert_eq!(
+            SystemTime::new(i64::MAX, 0).checked_add(Duration::from_secs(1)),
+            None
+        );
+        assert_eq!(
+            SystemTime::new(i64::MIN, 0).checked_sub(Duration::from_secs(1)),
+            None
+        );
+
+        // Fields out of range, which `new` would have normalized.
+        let time = SystemTime {
+            sec: 0,
+            nsec: 2_500_000_000,
+        };
+        assert_eq!(
+            time.checked_add(duration),
+            Some(SystemTime::new(3, 200_000_000))
+        );
+        assert_eq!(
+            time.checked_sub(duration),
+            Some(SystemTime::new(1, 800_000_000))
+        );
+        let time = SystemTime {
+            sec: i64::MAX,
+            nsec: NSEC_PER_SEC,
+        };
+        assert_eq!(time.checked_add(Duration::ZERO), None);
+        assert_eq!(time.checked_sub(Duration::ZERO), None);
+    }
+
+    #[test]
+    fn std_conversions() {
+        let std_time = std::time::UNIX_EPOCH - Duration::from_millis(500);
+        let time = SystemTime::from(std_time);
+        assert_eq!(time, SystemTime::new(-1, 500_000_000));
+
+        assert_eq!(std::time::SystemTime::try_from(time).unwrap(), std_time);
+
+        // The extremes of `std::time::SystemTime`.
+        let min = std::time::UNIX_EPOCH - Duration::from_secs(1 << 63);
+        let time = SystemTime::from(min);
+        assert_eq!(time, SystemTime::new(i64::MIN, 0));
+        assert_eq!(std::time::SystemTime::try_from(time).unwrap(), min);
+
+        let max = std::time::UNIX_EPOCH + Duration::new(i64::MAX as u64, 999_999_999);
+        let time = SystemTime::from(max);
+        assert_eq!(time, SystemTime::new(i64::MAX, 999_999_999));
+        assert_eq!(std::time::SystemTime::try_from(time).unwrap(), max);
+
+        let time = SystemTime::from(min + Duration::from_nanos(1));
+        assert_eq!(time, SystemTime::new(i64::MIN, 1));
+
+        let time = SystemTime {
+            sec: i64::MAX,
+            nsec: NSEC_PER_SEC,
+        };
+        assert_eq!(
+            std::time::SystemTime::try_from(time).unwrap_err().kind(),
+            std::io::ErrorKind::InvalidInput
+        );
     }
 }
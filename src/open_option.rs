@@ -5,11 +5,12 @@
 //   To finish tests, you need to implement `Read/Write` on `File` first.
 
 use crate::{
-    backend::encapsulation::{open, Flags, Mode},
+    backend::encapsulation::{fchmod, open, Flags, Mode},
     file::File,
+    umask::get_umask,
 };
 use std::{
-    io::{Error, Result},
+    io::{Error, ErrorKind, Result},
     os::unix::fs::OpenOptionsExt,
     path::Path,
 };
@@ -41,6 +42,7 @@ pub struct OpenOptions {
     // system-specific
     custom_flags: i32,
     mode: libc::mode_t,
+    exact_mode: bool,
 }
 
 impl OpenOptions {
@@ -126,6 +128,7 @@ impl OpenOptions {
             // system-specific
             custom_flags: 0,
             mode: 0o666,
+            exact_mode: false,
         }
     }
 
@@ -217,14 +220,47 @@ impl OpenOptions {
         self
     }
 
+    /// Sets the option to give a newly created file exactly the mode set with
+    /// `OpenOptionsExt::mode`, regardless of the umask of the process.
+    ///
+    /// The mode is changed with `fchmod(2)` right after the creation when the
+    /// umask removes some of its bits, files that already exist are left
+    /// untouched. When `.create(true)` is set, the file is first opened with
+    /// `O_EXCL` to know whether it is created, so a file created through a
+    /// dangling symbolic link keeps the masked mode.
+    pub fn exact_mode(&mut self, exact_mode: bool) -> &mut Self {
+        self.exact_mode = exact_mode;
+        self
+    }
+
     /// Opens a file at path with the options specified by self.
     pub fn open<P: AsRef<Path>>(&self, path: P) -> Result<File> {
         let mut flag = Flags::from_bits(libc::O_CLOEXEC).unwrap();
         flag |= Flags::from_bits(self.get_access_mode()?).unwrap();
         flag |= Flags::from_bits(self.get_creation_mode()?).unwrap();
         flag |= Flags::from_bits(self.custom_flags as libc::c_int & !libc::O_ACCMODE).unwrap();
+        let mode = Mode::from_bits_truncate(self.mode);
 
-        let fd = open(path, flag, Mode::from_bits_truncate(self.mode))?;
+        if !self.exact_mode || !flag.contains(Flags::O_CREAT) || mode.bits() & get_umask() == 0 {
+            let fd = open(path, flag, mode)?;
+            return Ok(File { fd });
+        }
+
+        let path = path.as_ref();
+        let fd = match open(path, flag | Flags::O_EXCL, mode) {
+            Ok(fd) => fd,
+            // `.create(true)`: the file exists, open it without creating it.
+            Err(e) if e.kind() == ErrorKind::AlreadyExists && !flag.contains(Flags::O_EXCL) => {
+                let fd = match open(path, flag - Flags::O_CREAT, mode) {
+                    // A dangling symbolic link, or a file removed in between.
+                    Err(e) if e.kind() == ErrorKind::NotFound => open(path, flag, mode)?,
+                    res => res?,
+                };
+                return Ok(File { fd });
+            }
+            Err(e) => return Err(e),
+        };
+        fchmod(&fd, mode)?;
         Ok(File { fd })
     }
 }
@@ -277,4 +313,32 @@ mod test {
 
         assert_eq!(std_error, my_fs_error);
     }
+
+    #[test]
+    fn exact_mode() {
+        use std::os::{linux::fs::MetadataExt, unix::fs::PermissionsExt};
+
+        let path = "/tmp/test_open_option_exact_mode";
+        let _ = crate::functions::remove_file(path);
+        let open = || {
+            OpenOptions::new()
+                .write(true)
+                .create(true)
+                .exact_mode(true)
+                .mode(0o666)
+                .open(path)
+        };
+
+        open().unwrap();
+        let metadata = crate::functions::metadata(path).unwrap();
+        assert_eq!(metadata.st_mode() & 0o7777, 0o666);
+
+        // An existing file keeps its mode.
+        crate::functions::set_permissions(path, crate::Permissions::from_mode(0o600)).unwrap();
+        open().unwrap();
+        let metadata = crate::functions::metadata(path).unwrap();
+        assert_eq!(metadata.st_mode() & 0o7777, 0o600);
+
+        crate::functions::remove_file(path).unwrap();
+    }
 }
@@ -1,5 +1,13 @@
-use crate::backend::encapsulation::{self, Mode};
-use std::os::unix::fs::PermissionsExt;
+use crate::{
+    backend::encapsulation::{self, Mode},
+    umask::get_umask,
+};
+use std::{
+    fmt,
+    io::{Error, ErrorKind, Result},
+    os::unix::fs::PermissionsExt,
+    str::FromStr,
+};
 
 /// Representation of the various permissions on a file.
 #[derive(Clone, PartialEq, Eq, Debug)]
@@ -41,6 +49,185 @@ impl Permissions {
     }
 }
 
+/// The bits `chmod(1)` can change: permissions, setuid, setgid and sticky.
+const MODE_BITS: u32 = 0o7777;
+
+/// Bits affected by each class of users in a symbolic mode.
+const USER_BITS: u32 = libc::S_ISUID | 0o700;
+const GROUP_BITS: u32 = libc::S_ISGID | 0o070;
+const OTHER_BITS: u32 = libc::S_ISVTX | 0o007;
+
+fn invalid_mode(mode: &str) -> Error {
+    Error::new(ErrorKind::InvalidInput, format!("invalid mode '{}'", mode))
+}
+
+impl Permissions {
+    /// Applies a symbolic mode with the semantics of `chmod(1)`, e.g.,
+    /// `u+rwx,g-w,o=rX`, or an octal one, e.g., `755`.
+    ///
+    /// A symbolic mode is a comma-separated list of clauses, each made of the
+    /// classes of users it applies to (`u`, `g`, `o` or `a`), followed by one
+    /// or more operations: `+`, `-` or `=` then permissions among `r`, `w`,
+    /// `x`, `X`, `s` and `t`, or one of `u`, `g` and `o` to copy the
+    /// permissions of that class. `X` sets the execute bits for directories and
+    /// for files which are executable by a class already, which can only be
+    /// known if these permissions hold the file type, as the ones returned by
+    /// [`Metadata::permission`] do.
+    ///
+    /// When no class is given, the clause applies to all of them, but does not
+    /// add or remove the bits set in the umask of the process, so that `+w` is
+    /// the same as `u+w` with the usual umask `022`.
+    ///
+    /// [`Metadata::permission`]: crate::Metadata::permission
+    pub fn apply_symbolic(&mut self, mode: &str) -> Result<()> {
+        self.apply_symbolic_with_umask(mode, get_umask())
+    }
+
+    /// Same as [`apply_symbolic`](Permissions::apply_symbolic), with `umask`
+    /// instead of the umask of the process.
+    pub fn apply_symbolic_with_umask(&mut self, mode: &str, umask: u32) -> Result<()> {
+        if mode.bytes().all(|b| b.is_ascii_digit()) {
+            let octal: Permissions = mode.parse()?;
+            self.set_mode((self.mode() & !MODE_BITS) | octal.mode());
+            return Ok(());
+        }
+
+        let mut new_mode = self.mode();
+        let is_dir = new_mode & libc::S_IFMT == libc::S_IFDIR;
+        for clause in mode.split(',') {
+            let mut chars = clause.chars().peekable();
+
+            let mut who = 0;
+            while let Some(class) = chars.next_if(|c| "ugoa".contains(*c)) {
+                who |= match class {
+                    'u' => USER_BITS,
+                    'g' => GROUP_BITS,
+                    'o' => OTHER_BITS,
+                    _ => MODE_BITS,
+                };
+            }
+            // Without a class, the bits set in the umask are neither added nor
+            // removed, but `=` still clears them.
+            let (cleared, affected) = if who == 0 {
+                (MODE_BITS, MODE_BITS & !umask)
+            } else {
+                (who, who)
+            };
+
+            if chars.peek().is_none() {
+                return Err(invalid_mode(mode));
+            }
+            while let Some(op) = chars.next() {
+                if !"+-=".contains(op) {
+                    return Err(invalid_mode(mode));
+                }
+
+                let mut perms = 0;
+                match chars.peek() {
+                    Some(&class @ ('u' | 'g' | 'o')) => {
+                        chars.next();
+                        let shift = match class {
+                            'u' => 6,
+                            'g' => 3,
+                            _ => 0,
+                        };
+                        perms = ((new_mode >> shift) & 0o7) * 0o111;
+                    }
+                    _ => {
+                        while let Some(perm) = chars.next_if(|c| "rwxXst".contains(*c)) {
+                            perms |= match perm {
+                                'r' => 0o444,
+                                'w' => 0o222,
+                                'x' => 0o111,
+                                'X' if is_dir || new_mode & 0o111 != 0 => 0o111,
+                                'X' => 0,
+                                's' => libc::S_ISUID | libc::S_ISGID,
+                                _ => libc::S_ISVTX,
+                            };
+                        }
+                    }
+                }
+
+                let value = perms & affected;
+                new_mode = match op {
+                    '+' => new_mode | value,
+                    '-' => new_mode & !value,
+                    _ => (new_mode & !cleared) | value,
+                };
+            }
+        }
+
+        self.set_mode(new_mode);
+        Ok(())
+    }
+}
+
+/// Parses an octal mode, e.g., `644` or `0755`, which may include the setuid,
+/// setgid and sticky bits, e.g., `4755`.
+impl FromStr for Permissions {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s.is_empty() || !s.bytes().all(|b| (b'0'..=b'7').contains(&b)) {
+            return Err(invalid_mode(s));
+        }
+
+        match u32::from_str_radix(s, 8) {
+            Ok(mode) if mode <= MODE_BITS => Ok(Permissions::from_mode(mode)),
+            _ => Err(invalid_mode(s)),
+        }
+    }
+}
+
+/// Formats the permissions like `ls -l`, e.g., `-rwsr-xr-T`.
+///
+/// The first character is the file type if these permissions hold it, as the
+/// ones returned by [`Metadata::permission`] do, `-` otherwise.
+///
+/// [`Metadata::permission`]: crate::Metadata::permission
+impl fmt::Display for Permissions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mode = self.mode();
+        // `from_mode()` accepts any value, do not rely on the type bits being
+        // valid.
+        let type_char = match mode & libc::S_IFMT {
+            0 | libc::S_IFREG => '-',
+            libc::S_IFDIR => 'd',
+            libc::S_IFLNK => 'l',
+            libc::S_IFIFO => 'p',
+            libc::S_IFSOCK => 's',
+            libc::S_IFCHR => 'c',
+            libc::S_IFBLK => 'b',
+            _ => '?',
+        };
+        write!(f, "{}", type_char)?;
+
+        let classes = [
+            (6, libc::S_ISUID, 's'),
+            (3, libc::S_ISGID, 's'),
+            (0, libc::S_ISVTX, 't'),
+        ];
+        for (shift, special, special_char) in classes {
+            let bits = (mode >> shift) & 0o7;
+            let exec = match (bits & 0o1 != 0, mode & special != 0) {
+                (true, true) => special_char,
+                (false, true) => special_char.to_ascii_uppercase(),
+                (true, false) => 'x',
+                (false, false) => '-',
+            };
+            write!(
+                f,
+                "{}{}{}",
+                if bits & 0o4 != 0 { 'r' } else { '-' },
+                if bits & 0o2 != 0 { 'w' } else { '-' },
+                exec
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
 impl PermissionsExt for Permissions {
     #[inline]
     fn mode(&self) -> u32 {
@@ -57,3 +244,80 @@ impl PermissionsExt for Permissions {
         Permissions(unsafe { Mode::from_bits_unchecked(mode) })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn apply(mode: u32, symbolic: &str) -> u32 {
+        let mut perms = Permissions::from_mode(mode);
+        perms.apply_symbolic_with_umask(symbolic, 0o022).unwrap();
+        perms.mode()
+    }
+
+    #[test]
+    fn symbolic() {
+        assert_eq!(apply(0o644, "u+x"), 0o744);
+        assert_eq!(apply(0o777, "u+rwx,g-w,o=rX"), 0o755);
+        assert_eq!(apply(0o644, "u+rwx,g-w,o=rX"), 0o745);
+        assert_eq!(apply(0o644, "a-r"), 0o200);
+        assert_eq!(apply(0o600, "go=u"), 0o666);
+        assert_eq!(apply(0o640, "o=g-w"), 0o644);
+        assert_eq!(apply(0o755, "u+s,+t"), 0o5755);
+        assert_eq!(apply(0o755, "g+s,o+s"), 0o2755);
+        assert_eq!(apply(0o6755, "ug-s"), 0o755);
+        assert_eq!(apply(0o600, "u=rw,go="), 0o600);
+        assert_eq!(apply(0o4755, "644"), 0o644);
+
+        // `X` only affects directories and files executable by someone.
+        assert_eq!(apply(0o644, "a+X"), 0o644);
+        assert_eq!(apply(0o744, "a+X"), 0o755);
+        assert_eq!(apply(libc::S_IFDIR | 0o644, "a+X"), libc::S_IFDIR | 0o755);
+
+        // Without a class, the bits in the umask are not added or removed.
+        assert_eq!(apply(0o444, "+w"), 0o644);
+        assert_eq!(apply(0o666, "-w"), 0o466);
+        assert_eq!(apply(0o666, "=r"), 0o444);
+
+        for invalid in ["", "u", "u+q", "z+r", "u+r,", "8", "77777"] {
+            let mut perms = Permissions::from_mode(0o644);
+            assert_eq!(
+                perms
+                    .apply_symbolic_with_umask(invalid, 0o022)
+                    .unwrap_err()
+                    .kind(),
+                ErrorKind::InvalidInput,
+                "{}",
+                invalid
+            );
+            assert_eq!(perms.mode(), 0o644);
+        }
+    }
+
+    #[test]
+    fn octal() {
+        assert_eq!("644".parse::<Permissions>().unwrap().mode(), 0o644);
+        assert_eq!("0755".parse::<Permissions>().unwrap().mode(), 0o755);
+        assert_eq!("4755".parse::<Permissions>().unwrap().mode(), 0o4755);
+        assert!("".parse::<Permissions>().is_err());
+        assert!("0o755".parse::<Permissions>().is_err());
+        assert!("789".parse::<Permissions>().is_err());
+        assert!("17777".parse::<Permissions>().is_err());
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(Permissions::from_mode(0o644).to_string(), "-rw-r--r--");
+        assert_eq!(Permissions::from_mode(0o4755).to_string(), "-rwsr-xr-x");
+        assert_eq!(Permissions::from_mode(0o5754).to_string(), "-rwsr-xr-T");
+        assert_eq!(Permissions::from_mode(0o2745).to_string(), "-rwxr-Sr-x");
+        assert_eq!(
+            Permissions::from_mode(libc::S_IFDIR | 0o1777).to_string(),
+            "drwxrwxrwt"
+        );
+
+        let metadata = crate::functions::symlink_metadata("/dev/null").unwrap();
+        assert!(metadata.permission().to_string().starts_with('c'));
+        assert_eq!(Permissions::from_mode(0o030644).to_string(), "?rw-r--r--");
+    }
+}
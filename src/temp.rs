@@ -0,0 +1,491 @@
+//! Temporary files and directories that are removed once they go out of scope.
+
+use crate::{
+    backend::encapsulation::{self, RenameFlags},
+    dirbuilder::DirBuilder,
+    file::File,
+    functions::{remove_dir_all, remove_file, rename},
+    open_option::OpenOptions,
+};
+use std::{
+    env,
+    ffi::{OsStr, OsString},
+    fmt,
+    io::{Error, ErrorKind, Read, Result, Seek, SeekFrom, Write},
+    mem::ManuallyDrop,
+    os::unix::{
+        fs::{DirBuilderExt, OpenOptionsExt},
+        io::{AsFd, AsRawFd, BorrowedFd, RawFd},
+    },
+    path::{Path, PathBuf},
+    ptr,
+};
+
+/// How many times we try to pick a new name when the chosen one already exists.
+const NUM_RETRIES: usize = 16;
+
+/// Returns the default directory where temporary files and directories are
+/// created.
+///
+/// This is the value of the `TMPDIR` environment variable if it is set and not
+/// empty, `/tmp` otherwise.
+pub fn temp_dir() -> PathBuf {
+    match env::var_os("TMPDIR") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from("/tmp"),
+    }
+}
+
+/// Fills `buf` with random bytes.
+fn fill_random(buf: &mut [u8]) -> Result<()> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match encapsulation::getrandom(&mut buf[filled..]) {
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(())
+}
+
+/// A builder used to create temporary files and directories with custom names
+/// and locations.
+///
+/// Names are formed by `prefix`, some random bytes encoded in hex and `suffix`.
+#[derive(Debug, Clone)]
+pub struct Builder {
+    dir: Option<PathBuf>,
+    prefix: OsString,
+    suffix: OsString,
+    rand_bytes: usize,
+    mode: Option<u32>,
+}
+
+impl Builder {
+    /// Creates a new builder with the default settings.
+    ///
+    /// Temporary entries are created in [`temp_dir`], named with prefix `.tmp`,
+    /// no suffix and 8 random bytes.
+    pub fn new() -> Self {
+        Self {
+            dir: None,
+            prefix: OsString::from(".tmp"),
+            suffix: OsString::new(),
+            rand_bytes: 8,
+            mode: None,
+        }
+    }
+
+    /// Sets the directory in which the temporary entry will be created.
+    pub fn dir<P: AsRef<Path>>(&mut self, dir: P) -> &mut Self {
+        self.dir = Some(dir.as_ref().to_owned());
+        self
+    }
+
+    /// Sets the prefix of the name.
+    pub fn prefix<S: AsRef<OsStr>>(&mut self, prefix: S) -> &mut Self {
+        self.prefix = prefix.as_ref().to_owned();
+        self
+    }
+
+    /// Sets the suffix of the name.
+    pub fn suffix<S: AsRef<OsStr>>(&mut self, suffix: S) -> &mut Self {
+        self.suffix = suffix.as_ref().to_owned();
+        self
+    }
+
+    /// Sets the number of random bytes used in the name.
+    pub fn rand_bytes(&mut self, rand_bytes: usize) -> &mut Self {
+        self.rand_bytes = rand_bytes;
+        self
+    }
+
+    /// Sets the permission bits used to create the temporary entry, the
+    /// process umask applies.
+    ///
+    /// If not set, `0o600` is used for files and `0o700` for directories.
+    pub fn mode(&mut self, mode: u32) -> &mut Self {
+        self.mode = Some(mode);
+        self
+    }
+
+    /// Creates a new temporary file in read-write mode.
+    pub fn tempfile(&self) -> Result<TempFile> {
+        self.create_with(|path| {
+            let file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create_new(true)
+                .mode(self.mode.unwrap_or(0o600))
+                .open(path)?;
+            Ok(TempFile {
+                file,
+                path: path.to_owned(),
+            })
+        })
+    }
+
+    /// Creates a new temporary directory.
+    pub fn tempdir(&self) -> Result<TempDir> {
+        self.create_with(|path| {
+            DirBuilder::new()
+                .mode(self.mode.unwrap_or(0o700))
+                .create(path)?;
+            Ok(TempDir {
+                path: Some(path.to_owned()),
+            })
+        })
+    }
+
+    fn random_path(&self) -> Result<PathBuf> {
+        let mut bytes = vec![0_u8; self.rand_bytes];
+        fill_random(&mut bytes)?;
+
+        let mut name = self.prefix.clone();
+        for byte in bytes {
+            name.push(format!("{:02x}", byte));
+        }
+        name.push(&self.suffix);
+
+        Ok(match self.dir {
+            Some(ref dir) => dir.join(name),
+            None => temp_dir().join(name),
+        })
+    }
+
+    // `create` is expected to fail with `ErrorKind::AlreadyExists` if `path`
+    // exists, in which case another name will be tried.
+    fn create_with<T, F>(&self, create: F) -> Result<T>
+    where
+        F: Fn(&Path) -> Result<T>,
+    {
+        for _ in 0..NUM_RETRIES {
+            let path = self.random_path()?;
+            match create(&path) {
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+                res => return res,
+            }
+        }
+
+        Err(Error::new(
+            ErrorKind::AlreadyExists,
+            "too many temporary entries exist",
+        ))
+    }
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A named temporary file, it will be removed when dropped.
+#[derive(Debug)]
+pub struct TempFile {
+    file: File,
+    path: PathBuf,
+}
+
+impl TempFile {
+    /// Creates a new temporary file in [`temp_dir`].
+    #[inline]
+    pub fn new() -> Result<TempFile> {
+        Builder::new().tempfile()
+    }
+
+    /// Creates a new temporary file in `dir`.
+    #[inline]
+    pub fn new_in<P: AsRef<Path>>(dir: P) -> Result<TempFile> {
+        Builder::new().dir(dir).tempfile()
+    }
+
+    /// Returns the path of this temporary file.
+    #[inline]
+    pub fn path(&self) -> &Path {
+        self.path.as_path()
+    }
+
+    /// Returns a reference to the underlying file.
+    #[inline]
+    pub fn as_file(&self) -> &File {
+        &self.file
+    }
+
+    /// Returns a mutable reference to the underlying file.
+    #[inline]
+    pub fn as_file_mut(&mut self) -> &mut File {
+        &mut self.file
+    }
+
+    /// Moves this temporary file to `path` so that it won't be removed, the
+    /// underlying file is returned.
+    ///
+    /// If `path` already exists, it will be replaced. `path` must be on the
+    /// same filesystem as this temporary file.
+    ///
+    /// # Errors
+    /// If the file can not be moved, it is returned in the [`PersistError`],
+    /// so that the caller can retry or keep its contents.
+    pub fn persist<P: AsRef<Path>>(self, path: P) -> std::result::Result<File, PersistError> {
+        match rename(&self.path, path) {
+            Ok(()) => Ok(self.into_file()),
+            Err(error) => Err(PersistError { error, file: self }),
+        }
+    }
+
+    /// Similar to [`persist`], but fails with `ErrorKind::AlreadyExists` if
+    /// `path` exists.
+    ///
+    /// [`persist`]: TempFile::persist
+    ///
+    /// # Errors
+    /// If the file can not be moved, it is returned in the [`PersistError`].
+    pub fn persist_noclobber<P: AsRef<Path>>(
+        self,
+        path: P,
+    ) -> std::result::Result<File, PersistError> {
+        match encapsulation::renameat2(None, &self.path, None, path, RenameFlags::RENAME_NOREPLACE)
+        {
+            Ok(()) => Ok(self.into_file()),
+            Err(error) => Err(PersistError { error, file: self }),
+        }
+    }
+
+    /// Returns the underlying file without removing it.
+    fn into_file(self) -> File {
+        // Skip our `Drop` implementation, and drop/move the fields by hand.
+        let mut this = ManuallyDrop::new(self);
+        drop(std::mem::take(&mut this.path));
+        unsafe { ptr::read(&this.file) }
+    }
+}
+
+/// The error returned when a [`TempFile`] can not be persisted, which holds
+/// the temporary file back.
+///
+/// Converting it into an [`std::io::Error`] drops, hence removes, the
+/// temporary file.
+#[derive(Debug)]
+pub struct PersistError {
+    error: Error,
+    file: TempFile,
+}
+
+impl PersistError {
+    /// Returns the error that made persisting fail.
+    #[inline]
+    pub fn error(&self) -> &Error {
+        &self.error
+    }
+
+    /// Returns the temporary file, which still exists.
+    #[inline]
+    pub fn into_temp_file(self) -> TempFile {
+        self.file
+    }
+}
+
+impl fmt::Display for PersistError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "failed to persist {:?}: {}", self.file.path, self.error)
+    }
+}
+
+impl std::error::Error for PersistError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl From<PersistError> for Error {
+    fn from(e: PersistError) -> Self {
+        e.error
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = remove_file(&self.path);
+    }
+}
+
+impl AsFd for TempFile {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.file.as_fd()
+    }
+}
+
+impl AsRawFd for TempFile {
+    fn as_raw_fd(&self) -> RawFd {
+        self.file.as_raw_fd()
+    }
+}
+
+impl Read for TempFile {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        self.file.read(buf)
+    }
+}
+
+impl Write for TempFile {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Seek for TempFile {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        self.file.seek(pos)
+    }
+}
+
+/// A temporary directory, it will be removed along with its contents when
+/// dropped.
+#[derive(Debug)]
+pub struct TempDir {
+    // `None` after `into_path()` or `close()`
+    path: Option<PathBuf>,
+}
+
+impl TempDir {
+    /// Creates a new temporary directory in [`temp_dir`].
+    #[inline]
+    pub fn new() -> Result<TempDir> {
+        Builder::new().tempdir()
+    }
+
+    /// Creates a new temporary directory in `dir`.
+    #[inline]
+    pub fn new_in<P: AsRef<Path>>(dir: P) -> Result<TempDir> {
+        Builder::new().dir(dir).tempdir()
+    }
+
+    /// Returns the path of this temporary directory.
+    #[inline]
+    pub fn path(&self) -> &Path {
+        self.path
+            .as_deref()
+            .expect("path is only taken when consumed")
+    }
+
+    /// Consumes the `TempDir` without removing the directory, the path to it
+    /// is returned.
+    pub fn into_path(mut self) -> PathBuf {
+        self.path.take().expect("path is only taken when consumed")
+    }
+
+    /// Removes the directory and its contents, unlike dropping, errors are
+    /// reported.
+    pub fn close(mut self) -> Result<()> {
+        let path = self.path.take().expect("path is only taken when consumed");
+        remove_dir_all(path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        if let Some(ref path) = self.path {
+            let _ = remove_dir_all(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::functions::{metadata, read, try_exists};
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn tempfile() {
+        let mut file = TempFile::new().unwrap();
+        let path = file.path().to_owned();
+        assert!(path.starts_with(temp_dir()));
+        assert_eq!(metadata(&path).unwrap().permission().mode() & 0o777, 0o600);
+
+        file.write_all(b"hello").unwrap();
+        drop(file);
+        assert!(!try_exists(path).unwrap());
+    }
+
+    #[test]
+    fn tempfile_persist() {
+        let dir = TempDir::new().unwrap();
+        let target = dir.path().join("persisted");
+
+        let mut file = TempFile::new_in(dir.path()).unwrap();
+        file.write_all(b"hello").unwrap();
+        let temp_path = file.path().to_owned();
+        file.persist(&target).unwrap();
+
+        assert!(!try_exists(temp_path).unwrap());
+        assert_eq!(read(&target).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn tempfile_persist_noclobber() {
+        let dir = TempDir::new().unwrap();
+        let target = dir.path().join("persisted");
+        File::create(&target).unwrap();
+
+        let file = TempFile::new_in(dir.path()).unwrap();
+        let temp_path = file.path().to_owned();
+        let err = file.persist_noclobber(&target).unwrap_err();
+        assert_eq!(err.error().kind(), ErrorKind::AlreadyExists);
+
+        // The temporary file is kept until the error is dropped.
+        assert!(try_exists(&temp_path).unwrap());
+        let file = err.into_temp_file();
+        assert_eq!(file.path(), temp_path);
+        file.persist(dir.path().join("other")).unwrap();
+        assert!(!try_exists(&temp_path).unwrap());
+
+        let file = TempFile::new_in(dir.path()).unwrap();
+        let temp_path = file.path().to_owned();
+        let err: Error = file.persist_noclobber(&target).unwrap_err().into();
+        assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+        assert!(!try_exists(temp_path).unwrap());
+    }
+
+    #[test]
+    fn builder_name() {
+        let dir = Builder::new()
+            .prefix("prefix_")
+            .suffix("_suffix")
+            .rand_bytes(4)
+            .tempdir()
+            .unwrap();
+        let name = dir.path().file_name().unwrap().to_str().unwrap();
+
+        assert!(name.starts_with("prefix_"));
+        assert!(name.ends_with("_suffix"));
+        assert_eq!(name.len(), "prefix_".len() + 8 + "_suffix".len());
+    }
+
+    #[test]
+    fn tempdir() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().to_owned();
+        assert_eq!(metadata(&path).unwrap().permission().mode() & 0o777, 0o700);
+
+        File::create(path.join("file")).unwrap();
+        crate::functions::create_dir_all(path.join("dir1/dir2")).unwrap();
+        drop(dir);
+        assert!(!try_exists(path).unwrap());
+    }
+
+    #[test]
+    fn tempdir_into_path() {
+        let path = TempDir::new().unwrap().into_path();
+        assert!(try_exists(&path).unwrap());
+
+        crate::functions::remove_dir(path).unwrap();
+    }
+}
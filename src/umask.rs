@@ -0,0 +1,103 @@
+use crate::{
+    backend::encapsulation::{umask, Mode},
+    functions::read_to_string,
+};
+
+/// Returns the file mode creation mask of the calling thread.
+///
+/// The mask is read from `/proc/thread-self/status`, so that it is not
+/// modified, even for a moment. Before Linux 4.7, which does not report it
+/// there, it is set then restored with `umask(2)`.
+pub fn get_umask() -> u32 {
+    read_to_string("/proc/thread-self/status")
+        .ok()
+        .and_then(|status| {
+            let line = status.lines().find(|line| line.starts_with("Umask:"))?;
+            u32::from_str_radix(line["Umask:".len()..].trim(), 8).ok()
+        })
+        .unwrap_or_else(|| {
+            let mask = umask(Mode::S_IWGRP | Mode::S_IWOTH);
+            umask(mask);
+            mask.bits()
+        })
+}
+
+/// Sets the file mode creation mask to `mask`, and returns the previous one.
+///
+/// Only the permission bits of `mask` are used. The mask is shared by all
+/// the threads of the process, unless they are created with `CLONE_FS`
+/// unset.
+pub fn set_umask(mask: u32) -> u32 {
+    umask(Mode::from_bits_truncate(mask & 0o777)).bits()
+}
+
+/// Sets the file mode creation mask for as long as it is alive, the previous
+/// mask is restored when it is dropped.
+///
+/// ```no_run
+/// use fs::{get_umask, UmaskGuard};
+///
+/// {
+///     let _guard = UmaskGuard::new(0o077);
+///     assert_eq!(get_umask(), 0o077);
+///     // Files created here are private to their owner.
+/// }
+/// ```
+#[derive(Debug)]
+#[must_use = "the previous umask is restored as soon as the guard is dropped"]
+pub struct UmaskGuard {
+    previous: u32,
+}
+
+impl UmaskGuard {
+    /// Sets the file mode creation mask to `mask`.
+    pub fn new(mask: u32) -> Self {
+        Self {
+            previous: set_umask(mask),
+        }
+    }
+
+    /// Returns the mask which is restored when the guard is dropped.
+    pub fn previous(&self) -> u32 {
+        self.previous
+    }
+}
+
+impl Drop for UmaskGuard {
+    fn drop(&mut self) {
+        set_umask(self.previous);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use nix::sched::{unshare, CloneFlags};
+    use std::thread;
+
+    #[test]
+    fn guard_restores_umask() {
+        // Other tests create files concurrently, give this thread its own
+        // umask.
+        thread::spawn(|| {
+            unshare(CloneFlags::CLONE_FS).unwrap();
+
+            let initial = get_umask();
+            assert_eq!(
+                initial,
+                nix::sys::stat::umask(nix::sys::stat::Mode::from_bits(initial).unwrap()).bits()
+            );
+            {
+                let guard = UmaskGuard::new(0o027);
+                assert_eq!(guard.previous(), initial);
+                assert_eq!(get_umask(), 0o027);
+
+                assert_eq!(set_umask(0o7077), 0o027);
+                assert_eq!(get_umask(), 0o077);
+            }
+            assert_eq!(get_umask(), initial);
+        })
+        .join()
+        .unwrap();
+    }
+}
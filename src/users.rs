@@ -0,0 +1,478 @@
+//! User and group databases, parsed from `/etc/passwd` and `/etc/group`.
+//!
+//! Only these files are read, users and groups from other sources of
+//! `nsswitch.conf(5)`, e.g., LDAP, are unknown. [`UserDb::with_root`] reads
+//! the databases of another system, e.g., a chroot.
+//!
+//! ```no_run
+//! use fs::users::{self, UserDb};
+//!
+//! users::chown_by_name("/var/www/index.html", "www-data", "www-data")?;
+//! let uid = UserDb::with_root("/srv/chroot").uid_by_name("nobody")?;
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use crate::functions::{chown, read};
+use std::{
+    io::{Error, ErrorKind, Result},
+    path::{Path, PathBuf},
+};
+
+const PASSWD: &str = "etc/passwd";
+const GROUP: &str = "etc/group";
+
+/// An entry of `/etc/passwd`, see `passwd(5)`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct User {
+    name: String,
+    uid: u32,
+    gid: u32,
+    gecos: String,
+    home_dir: PathBuf,
+    shell: PathBuf,
+}
+
+impl User {
+    /// Returns the login name.
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the user ID.
+    #[inline]
+    pub fn uid(&self) -> u32 {
+        self.uid
+    }
+
+    /// Returns the ID of the primary group.
+    #[inline]
+    pub fn gid(&self) -> u32 {
+        self.gid
+    }
+
+    /// Returns the comment field, usually the full name of the user.
+    #[inline]
+    pub fn gecos(&self) -> &str {
+        &self.gecos
+    }
+
+    /// Returns the home directory.
+    #[inline]
+    pub fn home_dir(&self) -> &Path {
+        self.home_dir.as_path()
+    }
+
+    /// Returns the login shell, which may be empty.
+    #[inline]
+    pub fn shell(&self) -> &Path {
+        self.shell.as_path()
+    }
+
+    /// Parses a line of `/etc/passwd`, e.g.,
+    /// `root:x:0:0:root:/root:/bin/bash`.
+    pub fn parse_line(line: &str) -> Result<User> {
+        let fields: Vec<&str> = line.split(':').collect();
+        let [name, _password, uid, gid, gecos, home_dir, shell] = fields[..] else {
+            return Err(invalid_line("passwd", line));
+        };
+
+        Ok(User {
+            name: parse_name(name).ok_or_else(|| invalid_line("passwd", line))?,
+            uid: uid.parse().map_err(|_| invalid_line("passwd", line))?,
+            gid: gid.parse().map_err(|_| invalid_line("passwd", line))?,
+            gecos: gecos.to_owned(),
+            home_dir: PathBuf::from(home_dir),
+            shell: PathBuf::from(shell),
+        })
+    }
+}
+
+/// An entry of `/etc/group`, see `group(5)`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Group {
+    name: String,
+    gid: u32,
+    members: Vec<String>,
+}
+
+impl Group {
+    /// Returns the group name.
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the group ID.
+    #[inline]
+    pub fn gid(&self) -> u32 {
+        self.gid
+    }
+
+    /// Returns the names of the users which have the group as a
+    /// supplementary group. Users which have it as their primary group are
+    /// usually not listed.
+    pub fn members(&self) -> impl Iterator<Item = &str> {
+        self.members.iter().map(String::as_str)
+    }
+
+    /// Parses a line of `/etc/group`, e.g., `sudo:x:27:alice,bob`.
+    pub fn parse_line(line: &str) -> Result<Group> {
+        let fields: Vec<&str> = line.split(':').collect();
+        let [name, _password, gid, members] = fields[..] else {
+            return Err(invalid_line("group", line));
+        };
+
+        Ok(Group {
+            name: parse_name(name).ok_or_else(|| invalid_line("group", line))?,
+            gid: gid.parse().map_err(|_| invalid_line("group", line))?,
+            members: members
+                .split(',')
+                .filter(|member| !member.is_empty())
+                .map(str::to_owned)
+                .collect(),
+        })
+    }
+}
+
+fn invalid_line(database: &str, line: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("invalid {} line '{}'", database, line),
+    )
+}
+
+fn parse_name(name: &str) -> Option<String> {
+    (!name.is_empty()).then(|| name.to_owned())
+}
+
+/// Returns the lines of a database which hold an entry, skipping comments
+/// and the `+`/`-` lines of NIS compat mode.
+fn entries(contents: &str) -> impl Iterator<Item = &str> {
+    contents
+        .lines()
+        .filter(|line| !line.is_empty() && !line.starts_with(['#', '+', '-']))
+}
+
+/// Parses the contents of a `passwd` file, malformed lines are skipped as
+/// the C library does.
+pub fn parse_passwd(contents: &str) -> Vec<User> {
+    entries(contents)
+        .filter_map(|line| User::parse_line(line).ok())
+        .collect()
+}
+
+/// Parses the contents of a `group` file, malformed lines are skipped as the
+/// C library does.
+pub fn parse_group(contents: &str) -> Vec<Group> {
+    entries(contents)
+        .filter_map(|line| Group::parse_line(line).ok())
+        .collect()
+}
+
+/// The user and group databases of a system, read from `etc/passwd` and
+/// `etc/group` under its root directory.
+///
+/// The files are read again by every lookup, so that changes are seen.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UserDb {
+    root: PathBuf,
+}
+
+impl UserDb {
+    /// Returns the databases of the running system, under `/`.
+    pub fn new() -> Self {
+        Self::with_root("/")
+    }
+
+    /// Returns the databases of the system whose root directory is `root`.
+    pub fn with_root<P: AsRef<Path>>(root: P) -> Self {
+        Self {
+            root: root.as_ref().to_owned(),
+        }
+    }
+
+    /// Returns the root directory the databases are read under.
+    #[inline]
+    pub fn root(&self) -> &Path {
+        self.root.as_path()
+    }
+
+    fn read(&self, database: &str) -> Result<String> {
+        let contents = read(self.root.join(database))?;
+        Ok(String::from_utf8_lossy(&contents).into_owned())
+    }
+
+    /// Returns all the users.
+    pub fn users(&self) -> Result<Vec<User>> {
+        Ok(parse_passwd(&self.read(PASSWD)?))
+    }
+
+    /// Returns all the groups.
+    pub fn groups(&self) -> Result<Vec<Group>> {
+        Ok(parse_group(&self.read(GROUP)?))
+    }
+
+    /// Returns the first user named `name`.
+    pub fn user_by_name(&self, name: &str) -> Result<Option<User>> {
+        Ok(self.users()?.into_iter().find(|user| user.name == name))
+    }
+
+    /// Returns the first user with the ID `uid`.
+    pub fn user_by_uid(&self, uid: u32) -> Result<Option<User>> {
+        Ok(self.users()?.into_iter().find(|user| user.uid == uid))
+    }
+
+    /// Returns the first group named `name`.
+    pub fn group_by_name(&self, name: &str) -> Result<Option<Group>> {
+        Ok(self.groups()?.into_iter().find(|group| group.name == name))
+    }
+
+    /// Returns the first group with the ID `gid`.
+    pub fn group_by_gid(&self, gid: u32) -> Result<Option<Group>> {
+        Ok(self.groups()?.into_iter().find(|group| group.gid == gid))
+    }
+
+    /// Returns the ID of the user named `name`.
+    pub fn uid_by_name(&self, name: &str) -> Result<Option<u32>> {
+        Ok(self.user_by_name(name)?.map(|user| user.uid))
+    }
+
+    /// Returns the name of the user with the ID `uid`.
+    pub fn name_by_uid(&self, uid: u32) -> Result<Option<String>> {
+        Ok(self.user_by_uid(uid)?.map(|user| user.name))
+    }
+
+    /// Returns the ID of the group named `name`.
+    pub fn gid_by_name(&self, name: &str) -> Result<Option<u32>> {
+        Ok(self.group_by_name(name)?.map(|group| group.gid))
+    }
+
+    /// Returns the name of the group with the ID `gid`.
+    pub fn name_by_gid(&self, gid: u32) -> Result<Option<String>> {
+        Ok(self.group_by_gid(gid)?.map(|group| group.name))
+    }
+
+    /// Returns the groups of the user named `user`: its primary group first,
+    /// then the groups which list it as a member, like `id(1)`.
+    ///
+    /// A primary group without an entry in the group database is still
+    /// returned, named after its ID and without members, as `id(1)` prints
+    /// it.
+    ///
+    /// Returns `None` if there is no such user.
+    pub fn groups_of(&self, user: &str) -> Result<Option<Vec<Group>>> {
+        let Some(user) = self.user_by_name(user)? else {
+            return Ok(None);
+        };
+
+        let (mut primary, others): (Vec<Group>, Vec<Group>) = self
+            .groups()?
+            .into_iter()
+            .filter(|group| group.gid == user.gid || group.members().any(|m| m == user.name))
+            .partition(|group| group.gid == user.gid);
+        primary.truncate(1);
+        if primary.is_empty() {
+            primary.push(Group {
+                name: user.gid.to_string(),
+                gid: user.gid,
+                members: Vec::new(),
+            });
+        }
+        primary.extend(others);
+
+        Ok(Some(primary))
+    }
+
+    /// Returns true if the user named `user` belongs to the group named
+    /// `group`, as its primary group or as a member.
+    pub fn is_member(&self, user: &str, group: &str) -> Result<bool> {
+        Ok(self
+            .groups_of(user)?
+            .is_some_and(|groups| groups.iter().any(|g| g.name == group)))
+    }
+
+    /// Changes the owner and group of `path` to the user `user` and the group
+    /// `group` of the databases. `path` is not relative to the root of the
+    /// databases.
+    ///
+    /// Like `chown(1)`, a name which is not found but is a number is used as
+    /// the ID. An empty name leaves the owner or the group unchanged, and
+    /// unknown names are reported as `NotFound`.
+    pub fn chown_by_name<P: AsRef<Path>>(&self, path: P, user: &str, group: &str) -> Result<()> {
+        let uid = match user {
+            "" => None,
+            name => Some(resolve(self.uid_by_name(name)?, name, "user")?),
+        };
+        let gid = match group {
+            "" => None,
+            name => Some(resolve(self.gid_by_name(name)?, name, "group")?),
+        };
+
+        chown(path, uid, gid)
+    }
+}
+
+impl Default for UserDb {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn resolve(id: Option<u32>, name: &str, kind: &str) -> Result<u32> {
+    id.or_else(|| name.parse().ok())
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, format!("unknown {} '{}'", kind, name)))
+}
+
+/// Returns the ID of the user named `name` in `/etc/passwd`.
+#[inline]
+pub fn uid_by_name(name: &str) -> Result<Option<u32>> {
+    UserDb::new().uid_by_name(name)
+}
+
+/// Returns the name of the user with the ID `uid` in `/etc/passwd`.
+#[inline]
+pub fn name_by_uid(uid: u32) -> Result<Option<String>> {
+    UserDb::new().name_by_uid(uid)
+}
+
+/// Returns the ID of the group named `name` in `/etc/group`.
+#[inline]
+pub fn gid_by_name(name: &str) -> Result<Option<u32>> {
+    UserDb::new().gid_by_name(name)
+}
+
+/// Returns the name of the group with the ID `gid` in `/etc/group`.
+#[inline]
+pub fn name_by_gid(gid: u32) -> Result<Option<String>> {
+    UserDb::new().name_by_gid(gid)
+}
+
+/// Returns the groups of the user named `user`, see [`UserDb::groups_of`].
+#[inline]
+pub fn groups_of(user: &str) -> Result<Option<Vec<Group>>> {
+    UserDb::new().groups_of(user)
+}
+
+/// Changes the owner and group of `path` by name, see
+/// [`UserDb::chown_by_name`].
+#[inline]
+pub fn chown_by_name<P: AsRef<Path>>(path: P, user: &str, group: &str) -> Result<()> {
+    UserDb::new().chown_by_name(path, user, group)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::functions::{create_dir_all, metadata, remove_dir_all, write};
+    use std::os::linux::fs::MetadataExt;
+
+    const PASSWD_CONTENTS: &str = "\
+# comment
+root:x:0:0:root:/root:/bin/bash
+alice:x:1000:1000:Alice,,,:/home/alice:/bin/zsh
++@netgroup::::::
+broken:x:abc:0::/:
+bob:x:1001:100::/home/bob:
+carol:x:1002:4242::/home/carol:
+";
+    const GROUP_CONTENTS: &str = "\
+root:x:0:
+users:x:100:
+alice:x:1000:
+wheel:x:10:alice,bob
+audio:x:29:bob
+";
+
+    #[test]
+    fn parse() {
+        let user = User::parse_line("alice:x:1000:1000:Alice,,,:/home/alice:/bin/zsh").unwrap();
+        assert_eq!(user.name(), "alice");
+        assert_eq!((user.uid(), user.gid()), (1000, 1000));
+        assert_eq!(user.gecos(), "Alice,,,");
+        assert_eq!(user.home_dir(), Path::new("/home/alice"));
+        assert_eq!(user.shell(), Path::new("/bin/zsh"));
+        assert!(User::parse_line("alice:x:1000:1000").is_err());
+        assert!(User::parse_line(":x:1000:1000::/:").is_err());
+
+        let group = Group::parse_line("wheel:x:10:alice,bob").unwrap();
+        assert_eq!((group.name(), group.gid()), ("wheel", 10));
+        assert_eq!(group.members().collect::<Vec<_>>(), ["alice", "bob"]);
+        assert_eq!(
+            Group::parse_line("users:x:100:").unwrap().members().count(),
+            0
+        );
+        assert!(Group::parse_line("users:x:-1:").is_err());
+
+        let users = parse_passwd(PASSWD_CONTENTS);
+        assert_eq!(
+            users.iter().map(User::name).collect::<Vec<_>>(),
+            ["root", "alice", "bob", "carol"]
+        );
+    }
+
+    #[test]
+    fn root_prefix() {
+        let root = "/tmp/test_users_root_prefix";
+        create_dir_all(format!("{}/etc", root)).unwrap();
+        write(format!("{}/etc/passwd", root), PASSWD_CONTENTS).unwrap();
+        write(format!("{}/etc/group", root), GROUP_CONTENTS).unwrap();
+        let db = UserDb::with_root(root);
+
+        assert_eq!(db.uid_by_name("bob").unwrap(), Some(1001));
+        assert_eq!(db.uid_by_name("broken").unwrap(), None);
+        assert_eq!(db.name_by_uid(1000).unwrap().as_deref(), Some("alice"));
+        assert_eq!(db.gid_by_name("wheel").unwrap(), Some(10));
+        assert_eq!(db.name_by_gid(29).unwrap().as_deref(), Some("audio"));
+
+        let groups = db.groups_of("bob").unwrap().unwrap();
+        assert_eq!(
+            groups.iter().map(Group::name).collect::<Vec<_>>(),
+            ["users", "wheel", "audio"]
+        );
+        let groups = db.groups_of("carol").unwrap().unwrap();
+        assert_eq!(
+            groups
+                .iter()
+                .map(|g| (g.name(), g.gid()))
+                .collect::<Vec<_>>(),
+            [("4242", 4242)]
+        );
+        assert!(db.groups_of("nobody").unwrap().is_none());
+        assert!(db.is_member("alice", "alice").unwrap());
+        assert!(db.is_member("alice", "wheel").unwrap());
+        assert!(!db.is_member("alice", "audio").unwrap());
+
+        let file = format!("{}/file", root);
+        write(&file, "").unwrap();
+        assert_eq!(
+            db.chown_by_name(&file, "nobody", "").unwrap_err().kind(),
+            ErrorKind::NotFound
+        );
+
+        // Giving a file away needs CAP_CHOWN.
+        if nix::unistd::geteuid().is_root() {
+            db.chown_by_name(&file, "bob", "audio").unwrap();
+            let stat = metadata(&file).unwrap();
+            assert_eq!((stat.st_uid(), stat.st_gid()), (1001, 29));
+
+            db.chown_by_name(&file, "alice", "").unwrap();
+            let stat = metadata(&file).unwrap();
+            assert_eq!((stat.st_uid(), stat.st_gid()), (1000, 29));
+
+            db.chown_by_name(&file, "", "4242").unwrap();
+            assert_eq!(metadata(&file).unwrap().st_gid(), 4242);
+        }
+
+        remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn system_databases() {
+        assert_eq!(uid_by_name("root").unwrap(), Some(0));
+        assert_eq!(name_by_uid(0).unwrap().as_deref(), Some("root"));
+        assert_eq!(gid_by_name("root").unwrap(), Some(0));
+        assert_eq!(name_by_gid(0).unwrap().as_deref(), Some("root"));
+        assert_eq!(groups_of("root").unwrap().unwrap()[0].gid(), 0);
+    }
+}
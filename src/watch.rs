@@ -0,0 +1,1112 @@
+//! Watching files and directories for changes with inotify, see `inotify(7)`,
+//! or by polling their metadata with [`PollWatcher`] where inotify does not
+//! see the changes, e.g., on NFS, FUSE or procfs.
+//!
+//! ```no_run
+//! use fs::watch::{Watcher, WatchMask};
+//! use std::time::Duration;
+//!
+//! let mut watcher = Watcher::new()?;
+//! watcher.debounce(Some(Duration::from_millis(100)));
+//! watcher.add_recursive("/etc/worker", WatchMask::CLOSE_WRITE | WatchMask::MOVE)?;
+//! loop {
+//!     for event in watcher.wait(None)? {
+//!         println!("{:?}", event);
+//!     }
+//! }
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use crate::{
+    backend::encapsulation::{self, InotifyEvent},
+    functions::read_dir,
+    metadata::{MetadataFields, MetadataOptions},
+};
+use bitflags::bitflags;
+use std::{
+    collections::HashMap,
+    io::{ErrorKind, Result},
+    os::{
+        linux::fs::MetadataExt,
+        unix::io::{AsFd, BorrowedFd, OwnedFd},
+    },
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
+};
+
+bitflags! {
+    /// Events to watch for, and flags changing how a path is watched.
+    pub struct WatchMask: u32 {
+        /// A file was read.
+        const ACCESS = libc::IN_ACCESS;
+        /// A file was written.
+        const MODIFY = libc::IN_MODIFY;
+        /// Metadata changed, e.g., permissions, timestamps or extended
+        /// attributes.
+        const ATTRIB = libc::IN_ATTRIB;
+        /// A file opened for writing was closed.
+        const CLOSE_WRITE = libc::IN_CLOSE_WRITE;
+        /// A file not opened for writing was closed.
+        const CLOSE_NOWRITE = libc::IN_CLOSE_NOWRITE;
+        /// A file was opened.
+        const OPEN = libc::IN_OPEN;
+        /// A file was moved out of a watched directory.
+        const MOVED_FROM = libc::IN_MOVED_FROM;
+        /// A file was moved into a watched directory.
+        const MOVED_TO = libc::IN_MOVED_TO;
+        /// A file was created in a watched directory.
+        const CREATE = libc::IN_CREATE;
+        /// A file was deleted from a watched directory.
+        const DELETE = libc::IN_DELETE;
+        /// The watched path itself was deleted.
+        const DELETE_SELF = libc::IN_DELETE_SELF;
+        /// The watched path itself was moved.
+        const MOVE_SELF = libc::IN_MOVE_SELF;
+        /// [`CLOSE_WRITE`](WatchMask::CLOSE_WRITE) and
+        /// [`CLOSE_NOWRITE`](WatchMask::CLOSE_NOWRITE).
+        const CLOSE = libc::IN_CLOSE;
+        /// [`MOVED_FROM`](WatchMask::MOVED_FROM) and
+        /// [`MOVED_TO`](WatchMask::MOVED_TO).
+        const MOVE = libc::IN_MOVE;
+        /// All the events above.
+        const ALL_EVENTS = libc::IN_ALL_EVENTS;
+
+        /// Fail if the path is not a directory.
+        const ONLYDIR = libc::IN_ONLYDIR;
+        /// Do not follow the path if it is a symlink.
+        const DONT_FOLLOW = libc::IN_DONT_FOLLOW;
+        /// Stop reporting events about files once they are unlinked.
+        const EXCL_UNLINK = libc::IN_EXCL_UNLINK;
+        /// Remove the watch after its first event.
+        const ONESHOT = libc::IN_ONESHOT;
+    }
+}
+
+/// What happened to the path of an [`Event`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum EventKind {
+    Access,
+    Modify,
+    Attrib,
+    CloseWrite,
+    CloseNoWrite,
+    Open,
+    Create,
+    Delete,
+    DeleteSelf,
+    MoveSelf,
+    /// The path was moved out of the watched directories.
+    MovedFrom,
+    /// The path was moved in from outside of the watched directories.
+    MovedTo,
+    /// The path was renamed from `from`, both being watched.
+    Renamed {
+        from: PathBuf,
+    },
+    /// The filesystem containing the path was unmounted.
+    Unmount,
+    /// The kernel event queue overflowed and events were lost, the path is
+    /// empty. The watched directories should be rescanned.
+    Overflow,
+}
+
+/// A change to a watched path.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Event {
+    /// What happened.
+    pub kind: EventKind,
+    /// The path the event is about, made of the watched path and the name of
+    /// the file in it.
+    pub path: PathBuf,
+    /// The path is a directory.
+    pub is_dir: bool,
+}
+
+impl EventKind {
+    fn from_mask(mask: u32) -> Option<Self> {
+        let kinds = [
+            (libc::IN_ACCESS, EventKind::Access),
+            (libc::IN_MODIFY, EventKind::Modify),
+            (libc::IN_ATTRIB, EventKind::Attrib),
+            (libc::IN_CLOSE_WRITE, EventKind::CloseWrite),
+            (libc::IN_CLOSE_NOWRITE, EventKind::CloseNoWrite),
+            (libc::IN_OPEN, EventKind::Open),
+            (libc::IN_CREATE, EventKind::Create),
+            (libc::IN_DELETE, EventKind::Delete),
+            (libc::IN_DELETE_SELF, EventKind::DeleteSelf),
+            (libc::IN_MOVE_SELF, EventKind::MoveSelf),
+            (libc::IN_MOVED_FROM, EventKind::MovedFrom),
+            (libc::IN_MOVED_TO, EventKind::MovedTo),
+            (libc::IN_UNMOUNT, EventKind::Unmount),
+            (libc::IN_Q_OVERFLOW, EventKind::Overflow),
+        ];
+
+        kinds
+            .into_iter()
+            .find(|(bit, _)| mask & bit != 0)
+            .map(|(_, kind)| kind)
+    }
+}
+
+#[derive(Debug)]
+struct Watch {
+    path: PathBuf,
+    mask: WatchMask,
+    recursive: bool,
+}
+
+/// Size of the buffer events are read into, large enough for many events with
+/// a name of `NAME_MAX` bytes.
+const BUF_SIZE: usize = 64 * 1024;
+
+/// Watches paths for changes with an inotify instance.
+///
+/// Events are read with [`read_events`](Watcher::read_events), which does not
+/// block, or [`wait`](Watcher::wait). The fd of the instance, returned by
+/// [`as_fd`](AsFd::as_fd), becomes readable when events are pending.
+#[derive(Debug)]
+pub struct Watcher {
+    fd: OwnedFd,
+    watches: HashMap<libc::c_int, Watch>,
+    debounce: Option<Duration>,
+}
+
+impl Watcher {
+    /// Creates a watcher without any watched path.
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            fd: encapsulation::inotify_init1()?,
+            watches: HashMap::new(),
+            debounce: None,
+        })
+    }
+
+    /// Sets the debounce window of [`wait`](Watcher::wait), disabled by
+    /// default.
+    ///
+    /// With a window, `wait` keeps collecting events until none arrived for
+    /// the duration of the window, then collapses repeated events into one,
+    /// e.g., the many [`EventKind::Modify`] of a file being written.
+    pub fn debounce(&mut self, window: Option<Duration>) -> &mut Self {
+        self.debounce = window;
+        self
+    }
+
+    /// Watches `path`, which can be a file or a directory, for the events in
+    /// `mask`.
+    ///
+    /// Watching a path again replaces its mask.
+    pub fn add<P: AsRef<Path>>(&mut self, path: P, mask: WatchMask) -> Result<()> {
+        self.add_watch(path.as_ref(), mask, false)
+    }
+
+    /// Watches the directory `path` and all the directories below it for the
+    /// events in `mask`.
+    ///
+    /// Directories created or moved in later are watched as well. Symlinks to
+    /// directories are not followed.
+    pub fn add_recursive<P: AsRef<Path>>(&mut self, path: P, mask: WatchMask) -> Result<()> {
+        self.add_watch(path.as_ref(), mask | WatchMask::ONLYDIR, true)?;
+        self.add_subdirs(path.as_ref(), mask)
+    }
+
+    /// Stops watching `path`, and the directories below it if it was added
+    /// with [`add_recursive`](Watcher::add_recursive).
+    pub fn remove<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let (wd, recursive) = self
+            .watches
+            .iter()
+            .find(|(_, watch)| watch.path == path)
+            .map(|(wd, watch)| (*wd, watch.recursive))
+            .ok_or_else(|| std::io::Error::new(ErrorKind::NotFound, "path is not watched"))?;
+
+        if recursive {
+            self.remove_below(path);
+        }
+        self.watches.remove(&wd);
+        encapsulation::inotify_rm_watch(&self.fd, wd)
+    }
+
+    /// Returns the events which are pending, without blocking.
+    pub fn read_events(&mut self) -> Result<Vec<Event>> {
+        let mut raw = Vec::new();
+        let mut buf = vec![0_u8; BUF_SIZE];
+        loop {
+            match encapsulation::read(&self.fd, &mut buf) {
+                Ok(num_read) => raw.extend(encapsulation::parse_inotify_events(&buf[..num_read])),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+
+        self.translate(raw)
+    }
+
+    /// Waits for events for at most `timeout`, or forever if it is `None`,
+    /// and returns them. The returned events are empty on timeout.
+    ///
+    /// With a [`debounce`](Watcher::debounce) window, this returns once no
+    /// event arrived for the duration of the window.
+    pub fn wait(&mut self, timeout: Option<Duration>) -> Result<Vec<Event>> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let mut events = self.read_events()?;
+        while events.is_empty() {
+            let remaining =
+                deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+            if remaining == Some(Duration::ZERO) || !self.poll(remaining)? {
+                return Ok(events);
+            }
+            events = self.read_events()?;
+        }
+
+        if let Some(window) = self.debounce {
+            while self.poll(Some(window))? {
+                events.extend(self.read_events()?);
+            }
+
+            // Only repeats, so that the order of different events is kept.
+            events.dedup();
+        }
+
+        Ok(events)
+    }
+
+    /// Waits until events are pending, returns false on timeout.
+    fn poll(&self, timeout: Option<Duration>) -> Result<bool> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            let remaining =
+                deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+            let timeout_ms = remaining.map_or(-1, |remaining| {
+                // Round up, so that we do not spin on sub-millisecond timeouts.
+                let ms = remaining.as_nanos().div_ceil(1_000_000);
+                ms.min(libc::c_int::MAX as u128) as libc::c_int
+            });
+
+            match encapsulation::poll_readable(&self.fd, timeout_ms) {
+                // Interrupted by a signal, wait for the rest of the timeout.
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                res => return res,
+            }
+        }
+    }
+
+    fn add_watch(&mut self, path: &Path, mask: WatchMask, recursive: bool) -> Result<()> {
+        let mut kernel_mask = mask;
+        if recursive {
+            // Needed to watch new directories and keep track of renamed ones.
+            kernel_mask |= WatchMask::CREATE | WatchMask::MOVE;
+        }
+
+        let wd = encapsulation::inotify_add_watch(&self.fd, path, kernel_mask.bits())?;
+        self.watches.insert(
+            wd,
+            Watch {
+                path: path.to_owned(),
+                mask,
+                recursive,
+            },
+        );
+        Ok(())
+    }
+
+    fn add_subdirs(&mut self, dir: &Path, mask: WatchMask) -> Result<()> {
+        let entries = match read_dir(dir) {
+            Ok(entries) => entries,
+            // The directory may be gone already.
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+
+        for entry in entries {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                let path = entry.path();
+                match self.add_watch(&path, mask | WatchMask::ONLYDIR, true) {
+                    Ok(()) => self.add_subdirs(&path, mask)?,
+                    Err(e) if e.kind() == ErrorKind::NotFound => {}
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Stops watching the directories strictly below `path`.
+    fn remove_below(&mut self, path: &Path) {
+        let below: Vec<libc::c_int> = self
+            .watches
+            .iter()
+            .filter(|(_, watch)| watch.path != path && watch.path.starts_with(path))
+            .map(|(wd, _)| *wd)
+            .collect();
+
+        for wd in below {
+            self.watches.remove(&wd);
+            // The watch may already be gone with its directory.
+            let _ = encapsulation::inotify_rm_watch(&self.fd, wd);
+        }
+    }
+
+    fn translate(&mut self, raw: Vec<InotifyEvent>) -> Result<Vec<Event>> {
+        // Events with whether they are wanted by the mask of their watch.
+        let mut events: Vec<(Event, bool)> = Vec::with_capacity(raw.len());
+        // Indexes of the `MovedFrom` events waiting for their `MovedTo`.
+        let mut moved_from: HashMap<u32, usize> = HashMap::new();
+        let mut new_dirs = Vec::new();
+
+        for raw_event in raw {
+            if raw_event.mask & libc::IN_Q_OVERFLOW != 0 {
+                let overflow = Event {
+                    kind: EventKind::Overflow,
+                    path: PathBuf::new(),
+                    is_dir: false,
+                };
+                events.push((overflow, true));
+                continue;
+            }
+            if raw_event.mask & libc::IN_IGNORED != 0 {
+                self.watches.remove(&raw_event.wd);
+                continue;
+            }
+            // Events of removed watches may still be queued.
+            let Some(watch) = self.watches.get(&raw_event.wd) else {
+                continue;
+            };
+            let Some(kind) = EventKind::from_mask(raw_event.mask) else {
+                continue;
+            };
+
+            let path = match raw_event.name {
+                Some(name) => watch.path.join(name),
+                None => watch.path.clone(),
+            };
+            let is_dir = raw_event.mask & libc::IN_ISDIR != 0;
+            let wanted = raw_event.mask & watch.mask.bits() & libc::IN_ALL_EVENTS != 0;
+            if watch.recursive
+                && is_dir
+                && raw_event.mask & (libc::IN_CREATE | libc::IN_MOVED_TO) != 0
+            {
+                new_dirs.push((path.clone(), watch.mask));
+            }
+
+            match kind {
+                EventKind::MovedFrom => {
+                    moved_from.insert(raw_event.cookie, events.len());
+                    events.push((Event { kind, path, is_dir }, wanted));
+                }
+                EventKind::MovedTo if moved_from.contains_key(&raw_event.cookie) => {
+                    let idx = moved_from.remove(&raw_event.cookie).unwrap();
+                    let from_wanted = events[idx].1;
+                    let from = std::mem::take(&mut events[idx].0.path);
+                    if is_dir {
+                        self.rename_watches(&from, &path);
+                    }
+                    events[idx] = (
+                        Event {
+                            kind: EventKind::Renamed { from },
+                            path,
+                            is_dir,
+                        },
+                        from_wanted || wanted,
+                    );
+                }
+                _ => events.push((Event { kind, path, is_dir }, wanted)),
+            }
+        }
+
+        // Directories moved out of the watched ones are not watched anymore.
+        for idx in moved_from.into_values() {
+            let (event, _) = &events[idx];
+            if event.is_dir {
+                let path = event.path.clone();
+                self.remove_below(&path);
+                if let Some((wd, _)) = self.watches.iter().find(|(_, watch)| watch.path == path) {
+                    let wd = *wd;
+                    self.watches.remove(&wd);
+                    let _ = encapsulation::inotify_rm_watch(&self.fd, wd);
+                }
+            }
+        }
+
+        for (dir, mask) in new_dirs {
+            // Already watched if it was renamed within the watched ones.
+            if !self.watches.values().any(|watch| watch.path == dir) {
+                match self.add_watch(&dir, mask | WatchMask::ONLYDIR, true) {
+                    Ok(()) => self.add_subdirs(&dir, mask)?,
+                    Err(e) if e.kind() == ErrorKind::NotFound => {}
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+
+        Ok(events
+            .into_iter()
+            .filter(|(_, wanted)| *wanted)
+            .map(|(event, _)| event)
+            .collect())
+    }
+
+    /// Updates the paths of the watches at or below `from` after it was renamed
+    /// to `to`.
+    fn rename_watches(&mut self, from: &Path, to: &Path) {
+        for watch in self.watches.values_mut() {
+            if let Ok(rest) = watch.path.strip_prefix(from) {
+                watch.path = if rest.as_os_str().is_empty() {
+                    to.to_owned()
+                } else {
+                    to.join(rest)
+                };
+            }
+        }
+    }
+}
+
+impl AsFd for Watcher {
+    #[inline]
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
+/// The state of a file compared between two scans of a [`PollWatcher`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct FileState {
+    dev: u64,
+    ino: u64,
+    size: u64,
+    mtime: (i64, i64),
+    ctime: (i64, i64),
+    is_dir: bool,
+}
+
+/// The effective interval of an adaptive [`PollWatcher`] is at least this many
+/// times the duration of its last scan.
+const ADAPTIVE_FACTOR: u32 = 10;
+
+/// Watches paths for changes by comparing snapshots of their metadata.
+///
+/// Each scan queries the inode number, size, modification and status change
+/// times of every watched file with `statx(2)`. Files whose inode moved to
+/// another path between two scans are reported as [`EventKind::Renamed`],
+/// other changes as [`EventKind::Create`], [`EventKind::Modify`] or
+/// [`EventKind::Delete`]. Directories are never reported as modified.
+///
+/// Changes made and undone between two scans are missed, as well as
+/// modifications which keep the size and the timestamps.
+#[derive(Debug)]
+pub struct PollWatcher {
+    roots: Vec<(PathBuf, bool)>,
+    snapshot: HashMap<PathBuf, FileState>,
+    interval: Duration,
+    adaptive: bool,
+    last_scan: Option<(Instant, Duration)>,
+}
+
+impl PollWatcher {
+    /// Creates a watcher without any watched path, scanning every `interval`.
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            roots: Vec::new(),
+            snapshot: HashMap::new(),
+            interval,
+            adaptive: false,
+            last_scan: None,
+        }
+    }
+
+    /// Sets whether the interval adapts to the size of the watched trees,
+    /// disabled by default.
+    ///
+    /// When enabled, the interval is stretched so that scanning takes at most
+    /// a tenth of the time.
+    pub fn adaptive(&mut self, adaptive: bool) -> &mut Self {
+        self.adaptive = adaptive;
+        self
+    }
+
+    /// Returns the interval between two scans, which is longer than the one
+    /// given to [`new`](PollWatcher::new) when [`adaptive`] and the last scan
+    /// was slow.
+    ///
+    /// [`adaptive`]: PollWatcher::adaptive
+    pub fn interval(&self) -> Duration {
+        match self.last_scan {
+            Some((_, took)) if self.adaptive => self.interval.max(took * ADAPTIVE_FACTOR),
+            _ => self.interval,
+        }
+    }
+
+    /// Watches `path`, and the entries of the directory if it is one.
+    pub fn add<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        self.add_root(path.as_ref(), false)
+    }
+
+    /// Watches `path` and all the files below it. Symlinks to directories are
+    /// not followed.
+    pub fn add_recursive<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        self.add_root(path.as_ref(), true)
+    }
+
+    /// Stops watching `path`.
+    pub fn remove<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let idx = self
+            .roots
+            .iter()
+            .position(|(root, _)| root == path)
+            .ok_or_else(|| std::io::Error::new(ErrorKind::NotFound, "path is not watched"))?;
+        self.roots.remove(idx);
+
+        // Keep what is still watched through the other roots, as it was at
+        // the last scan, so that the changes since are still reported.
+        let roots = &self.roots;
+        self.snapshot.retain(|path, _| {
+            roots
+                .iter()
+                .any(|(root, recursive)| covers(root, *recursive, path))
+        });
+        Ok(())
+    }
+
+    /// Scans the watched paths now and returns the changes since the previous
+    /// scan.
+    pub fn scan(&mut self) -> Result<Vec<Event>> {
+        let start = Instant::now();
+        let snapshot = self.snapshot_roots()?;
+        self.last_scan = Some((start, start.elapsed()));
+
+        let old = std::mem::replace(&mut self.snapshot, snapshot);
+        Ok(diff(&old, &self.snapshot))
+    }
+
+    /// Scans the watched paths every [`interval`](PollWatcher::interval) until
+    /// there are changes, for at most `timeout`, or forever if it is `None`.
+    /// The returned events are empty on timeout.
+    pub fn wait(&mut self, timeout: Option<Duration>) -> Result<Vec<Event>> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            let next_scan = self
+                .last_scan
+                .map_or_else(Instant::now, |(start, _)| start + self.interval());
+            let wake_up = deadline.map_or(next_scan, |deadline| deadline.min(next_scan));
+            thread::sleep(wake_up.saturating_duration_since(Instant::now()));
+
+            if wake_up == next_scan {
+                let events = self.scan()?;
+                if !events.is_empty() {
+                    return Ok(events);
+                }
+            }
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return Ok(Vec::new());
+            }
+        }
+    }
+
+    fn snapshot_roots(&self) -> Result<HashMap<PathBuf, FileState>> {
+        let mut snapshot = HashMap::new();
+        for (root, recursive) in self.roots.iter() {
+            match snapshot_tree(root, *recursive, &mut snapshot) {
+                // Reported as removed, and as created if it comes back.
+                Err(e) if e.kind() == ErrorKind::NotFound => {}
+                res => res?,
+            }
+        }
+        Ok(snapshot)
+    }
+
+    fn add_root(&mut self, path: &Path, recursive: bool) -> Result<()> {
+        // Fail early if the path can not be watched.
+        snapshot_tree(path, recursive, &mut self.snapshot)?;
+        self.roots.push((path.to_owned(), recursive));
+        Ok(())
+    }
+}
+
+/// Returns true if `path` is scanned by [`snapshot_tree`] for `root`.
+fn covers(root: &Path, recursive: bool, path: &Path) -> bool {
+    if recursive {
+        path.starts_with(root)
+    } else {
+        path == root || path.parent() == Some(root)
+    }
+}
+
+/// Adds the state of `path` to `snapshot`, and of the files below it if it is
+/// a directory, down to its entries only unless `recursive`.
+///
+/// Files removed while scanning are skipped, but not `path` itself.
+fn snapshot_tree(
+    path: &Path,
+    recursive: bool,
+    snapshot: &mut HashMap<PathBuf, FileState>,
+) -> Result<()> {
+    if !snapshot_file(path, snapshot)? {
+        return Ok(());
+    }
+
+    for entry in read_dir(path)? {
+        let entry = entry?;
+        let res = if recursive {
+            snapshot_tree(&entry.path(), true, snapshot)
+        } else {
+            snapshot_file(&entry.path(), snapshot).map(drop)
+        };
+        match res {
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            res => res?,
+        }
+    }
+
+    Ok(())
+}
+
+/// Adds the state of `path` to `snapshot`, returns true if it is a directory.
+fn snapshot_file(path: &Path, snapshot: &mut HashMap<PathBuf, FileState>) -> Result<bool> {
+    let metadata = MetadataOptions::new()
+        .fields(
+            MetadataFields::TYPE
+                | MetadataFields::INO
+                | MetadataFields::SIZE
+                | MetadataFields::MTIME
+                | MetadataFields::CTIME,
+        )
+        .follow_symlinks(false)
+        .metadata(path)?;
+
+    let is_dir = metadata.is_dir();
+    snapshot.insert(
+        path.to_owned(),
+        FileState {
+            dev: metadata.st_dev(),
+            ino: metadata.st_ino(),
+            size: metadata.st_size(),
+            mtime: (metadata.st_mtime(), metadata.st_mtime_nsec()),
+            ctime: (metadata.st_ctime(), metadata.st_ctime_nsec()),
+            is_dir,
+        },
+    );
+    Ok(is_dir)
+}
+
+/// Returns the changes from `old` to `new`, sorted by path within each kind.
+fn diff(old: &HashMap<PathBuf, FileState>, new: &HashMap<PathBuf, FileState>) -> Vec<Event> {
+    let mut removed: Vec<(&PathBuf, &FileState)> = old
+        .iter()
+        .filter(|(path, _)| !new.contains_key(*path))
+        .collect();
+    let mut created: Vec<(&PathBuf, &FileState)> = new
+        .iter()
+        .filter(|(path, _)| !old.contains_key(*path))
+        .collect();
+    removed.sort_by_key(|(path, _)| *path);
+    created.sort_by_key(|(path, _)| *path);
+
+    // A removed inode showing up at a new path was renamed.
+    let mut renamed: Vec<(PathBuf, PathBuf, bool)> = Vec::new();
+    created.retain(|(to, state)| {
+        let from = removed.iter().position(|(_, old_state)| {
+            (old_state.dev, old_state.ino, old_state.is_dir) == (state.dev, state.ino, state.is_dir)
+        });
+        match from {
+            Some(idx) => {
+                renamed.push((removed.remove(idx).0.clone(), (*to).clone(), state.is_dir));
+                false
+            }
+            None => true,
+        }
+    });
+
+    // The entries of a renamed directory are renamed along with it.
+    let renamed_dirs: Vec<(PathBuf, PathBuf)> = renamed
+        .iter()
+        .filter(|(_, _, is_dir)| *is_dir)
+        .map(|(from, to, _)| (from.clone(), to.clone()))
+        .collect();
+    renamed.retain(|(from, to, _)| {
+        !renamed_dirs.iter().any(|(from_dir, to_dir)| {
+            from != from_dir
+                && matches!(
+                    (from.strip_prefix(from_dir), to.strip_prefix(to_dir)),
+                    (Ok(from_rest), Ok(to_rest)) if from_rest == to_rest
+                )
+        })
+    });
+
+    let mut modified: Vec<(&PathBuf, &FileState)> = new
+        .iter()
+        .filter(|(path, state)| {
+            !state.is_dir && old.get(*path).is_some_and(|old_state| old_state != *state)
+        })
+        .collect();
+    modified.sort_by_key(|(path, _)| *path);
+
+    let event = |kind, path: &PathBuf, state: &FileState| Event {
+        kind,
+        path: path.clone(),
+        is_dir: state.is_dir,
+    };
+    let mut events = Vec::new();
+    events.extend(
+        removed
+            .into_iter()
+            .map(|(path, state)| event(EventKind::Delete, path, state)),
+    );
+    events.extend(renamed.into_iter().map(|(from, path, is_dir)| Event {
+        kind: EventKind::Renamed { from },
+        path,
+        is_dir,
+    }));
+    events.extend(
+        created
+            .into_iter()
+            .map(|(path, state)| event(EventKind::Create, path, state)),
+    );
+    events.extend(
+        modified
+            .into_iter()
+            .map(|(path, state)| event(EventKind::Modify, path, state)),
+    );
+    events
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::functions::{
+        create_dir, create_dir_all, remove_dir_all, remove_file, rename, write,
+    };
+
+    fn event(kind: EventKind, path: &str, is_dir: bool) -> Event {
+        Event {
+            kind,
+            path: PathBuf::from(path),
+            is_dir,
+        }
+    }
+
+    fn fresh_dir(dir: &str) {
+        let _ = remove_dir_all(dir);
+        create_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn create_modify_delete() {
+        let dir = "/tmp/test_watch_create_modify_delete";
+        fresh_dir(dir);
+        let file = format!("{}/file", dir);
+
+        let mut watcher = Watcher::new().unwrap();
+        watcher
+            .add(
+                dir,
+                WatchMask::CREATE | WatchMask::MODIFY | WatchMask::DELETE,
+            )
+            .unwrap();
+        assert!(watcher.read_events().unwrap().is_empty());
+
+        write(&file, "hello").unwrap();
+        remove_file(&file).unwrap();
+        assert_eq!(
+            watcher.wait(Some(Duration::from_secs(5))).unwrap(),
+            [
+                event(EventKind::Create, &file, false),
+                event(EventKind::Modify, &file, false),
+                event(EventKind::Delete, &file, false),
+            ]
+        );
+
+        watcher.remove(dir).unwrap();
+        write(&file, "hello").unwrap();
+        assert!(watcher
+            .wait(Some(Duration::from_millis(10)))
+            .unwrap()
+            .is_empty());
+        remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rename_pairing() {
+        let dir = "/tmp/test_watch_rename_pairing";
+        fresh_dir(dir);
+        let outside = "/tmp/test_watch_rename_pairing_outside";
+        write(format!("{}/a", dir), "").unwrap();
+        write(format!("{}/c", dir), "").unwrap();
+
+        let mut watcher = Watcher::new().unwrap();
+        watcher.add(dir, WatchMask::MOVE).unwrap();
+
+        rename(format!("{}/a", dir), format!("{}/b", dir)).unwrap();
+        rename(format!("{}/c", dir), outside).unwrap();
+        assert_eq!(
+            watcher.read_events().unwrap(),
+            [
+                Event {
+                    kind: EventKind::Renamed {
+                        from: PathBuf::from(format!("{}/a", dir))
+                    },
+                    path: PathBuf::from(format!("{}/b", dir)),
+                    is_dir: false,
+                },
+                event(EventKind::MovedFrom, &format!("{}/c", dir), false),
+            ]
+        );
+
+        remove_file(outside).unwrap();
+        remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn recursive() {
+        let dir = "/tmp/test_watch_recursive";
+        fresh_dir(dir);
+        create_dir(format!("{}/old", dir)).unwrap();
+
+        let mut watcher = Watcher::new().unwrap();
+        watcher.add_recursive(dir, WatchMask::CREATE).unwrap();
+
+        write(format!("{}/old/file", dir), "").unwrap();
+        create_dir(format!("{}/new", dir)).unwrap();
+        assert_eq!(
+            watcher.read_events().unwrap(),
+            [
+                event(EventKind::Create, &format!("{}/old/file", dir), false),
+                event(EventKind::Create, &format!("{}/new", dir), true),
+            ]
+        );
+
+        // `new` is watched once its creation was read, and keeps being
+        // watched under its new name.
+        rename(format!("{}/new", dir), format!("{}/renamed", dir)).unwrap();
+        assert!(watcher.read_events().unwrap().is_empty());
+        write(format!("{}/renamed/file", dir), "").unwrap();
+        assert_eq!(
+            watcher.read_events().unwrap(),
+            [event(
+                EventKind::Create,
+                &format!("{}/renamed/file", dir),
+                false
+            )]
+        );
+
+        remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn debounce() {
+        let dir = "/tmp/test_watch_debounce";
+        fresh_dir(dir);
+        let file = format!("{}/file", dir);
+        write(&file, "").unwrap();
+
+        let mut watcher = Watcher::new().unwrap();
+        watcher
+            .debounce(Some(Duration::from_millis(50)))
+            .add(&file, WatchMask::MODIFY)
+            .unwrap();
+        for _ in 0..3 {
+            write(&file, "hello").unwrap();
+        }
+
+        assert_eq!(
+            watcher.wait(Some(Duration::from_secs(5))).unwrap(),
+            [event(EventKind::Modify, &file, false)]
+        );
+        remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn debounce_keeps_order() {
+        let dir = "/tmp/test_watch_debounce_keeps_order";
+        fresh_dir(dir);
+        let file = format!("{}/file", dir);
+
+        let mut watcher = Watcher::new().unwrap();
+        watcher
+            .debounce(Some(Duration::from_millis(50)))
+            .add(dir, WatchMask::CREATE | WatchMask::DELETE)
+            .unwrap();
+        write(&file, "").unwrap();
+        remove_file(&file).unwrap();
+        write(&file, "").unwrap();
+
+        assert_eq!(
+            watcher.wait(Some(Duration::from_secs(5))).unwrap(),
+            [
+                event(EventKind::Create, &file, false),
+                event(EventKind::Delete, &file, false),
+                event(EventKind::Create, &file, false),
+            ]
+        );
+        remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn wait_interrupted() {
+        use nix::sys::{
+            pthread::{pthread_kill, pthread_self},
+            signal::{sigaction, SaFlags, SigAction, SigHandler, SigSet, Signal},
+        };
+
+        extern "C" fn ignore(_: libc::c_int) {}
+
+        let dir = "/tmp/test_watch_wait_interrupted";
+        fresh_dir(dir);
+        let file = format!("{}/file", dir);
+
+        let action = SigAction::new(
+            SigHandler::Handler(ignore),
+            SaFlags::empty(),
+            SigSet::empty(),
+        );
+        unsafe { sigaction(Signal::SIGUSR1, &action) }.unwrap();
+
+        let mut watcher = Watcher::new().unwrap();
+        watcher.add(dir, WatchMask::CREATE).unwrap();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let waiter = std::thread::spawn(move || {
+            sender.send(pthread_self()).unwrap();
+            watcher.wait(None).unwrap()
+        });
+
+        // The signal interrupts `poll(2)`, which must not end the wait.
+        let thread = receiver.recv().unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        pthread_kill(thread, Signal::SIGUSR1).unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        write(&file, "").unwrap();
+
+        assert_eq!(
+            waiter.join().unwrap(),
+            [event(EventKind::Create, &file, false)]
+        );
+        remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn poll_create_modify_delete() {
+        let dir = "/tmp/test_poll_watch_create_modify_delete";
+        fresh_dir(dir);
+        let old = format!("{}/old", dir);
+        let new = format!("{}/new", dir);
+        write(&old, "").unwrap();
+
+        let mut watcher = PollWatcher::new(Duration::from_millis(10));
+        watcher.add(dir).unwrap();
+        assert!(watcher.scan().unwrap().is_empty());
+
+        write(&new, "").unwrap();
+        write(&old, "hello").unwrap();
+        assert_eq!(
+            watcher.scan().unwrap(),
+            [
+                event(EventKind::Create, &new, false),
+                event(EventKind::Modify, &old, false),
+            ]
+        );
+
+        remove_file(&old).unwrap();
+        assert_eq!(
+            watcher.wait(Some(Duration::from_secs(5))).unwrap(),
+            [event(EventKind::Delete, &old, false)]
+        );
+        assert!(watcher
+            .wait(Some(Duration::from_millis(30)))
+            .unwrap()
+            .is_empty());
+
+        watcher.remove(dir).unwrap();
+        remove_dir_all(dir).unwrap();
+        assert!(watcher.scan().unwrap().is_empty());
+    }
+
+    #[test]
+    fn poll_remove_keeps_other_changes() {
+        let dir = "/tmp/test_poll_watch_remove_keeps_other_changes";
+        fresh_dir(dir);
+        let (first, second) = (format!("{}/first", dir), format!("{}/second", dir));
+        create_dir(&first).unwrap();
+        create_dir_all(format!("{}/sub", second)).unwrap();
+
+        let mut watcher = PollWatcher::new(Duration::from_millis(10));
+        watcher.add(&first).unwrap();
+        watcher.add_recursive(&second).unwrap();
+
+        let file = format!("{}/sub/file", second);
+        write(&file, "").unwrap();
+        write(format!("{}/file", first), "").unwrap();
+        watcher.remove(&first).unwrap();
+        assert_eq!(
+            watcher.scan().unwrap(),
+            [event(EventKind::Create, &file, false)]
+        );
+
+        remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn poll_rename() {
+        let dir = "/tmp/test_poll_watch_rename";
+        fresh_dir(dir);
+        create_dir(format!("{}/sub", dir)).unwrap();
+        write(format!("{}/sub/file", dir), "").unwrap();
+        write(format!("{}/a", dir), "").unwrap();
+
+        let mut watcher = PollWatcher::new(Duration::from_millis(10));
+        watcher.add_recursive(dir).unwrap();
+
+        rename(format!("{}/a", dir), format!("{}/b", dir)).unwrap();
+        rename(format!("{}/sub", dir), format!("{}/moved", dir)).unwrap();
+        write(format!("{}/c", dir), "").unwrap();
+
+        let renamed = |from: &str, to: &str, is_dir| Event {
+            kind: EventKind::Renamed {
+                from: PathBuf::from(format!("{}/{}", dir, from)),
+            },
+            path: PathBuf::from(format!("{}/{}", dir, to)),
+            is_dir,
+        };
+        // `moved/file` is implied by the rename of its directory.
+        assert_eq!(
+            watcher.scan().unwrap(),
+            [
+                renamed("a", "b", false),
+                renamed("sub", "moved", true),
+                event(EventKind::Create, &format!("{}/c", dir), false),
+            ]
+        );
+
+        remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn poll_adaptive_interval() {
+        let mut watcher = PollWatcher::new(Duration::from_nanos(1));
+        watcher.add_recursive("src").unwrap();
+        watcher.scan().unwrap();
+        assert_eq!(watcher.interval(), Duration::from_nanos(1));
+
+        watcher.adaptive(true);
+        assert!(watcher.interval() > Duration::from_nanos(1));
+    }
+
+    #[test]
+    fn overflow() {
+        let mut watcher = Watcher::new().unwrap();
+        let raw = InotifyEvent {
+            wd: -1,
+            mask: libc::IN_Q_OVERFLOW,
+            cookie: 0,
+            name: None,
+        };
+        assert_eq!(
+            watcher.translate(vec![raw]).unwrap(),
+            [event(EventKind::Overflow, "", false)]
+        );
+    }
+}
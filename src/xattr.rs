@@ -0,0 +1,209 @@
+//! Extended attributes.
+
+use crate::backend::encapsulation::{self, XattrFlags};
+use bitflags::bitflags;
+use std::{
+    ffi::{OsStr, OsString},
+    io::Result,
+    os::unix::ffi::OsStringExt,
+    path::Path,
+};
+
+bitflags! {
+    /// Flags used when setting an extended attribute.
+    ///
+    /// With no flags, the attribute is created if it does not exist, and
+    /// replaced otherwise.
+    pub struct SetXattrFlags: i32 {
+        /// Fail with `ErrorKind::AlreadyExists` if the attribute already exists.
+        const CREATE = libc::XATTR_CREATE;
+        /// Fail if the attribute does not exist.
+        const REPLACE = libc::XATTR_REPLACE;
+    }
+}
+
+impl From<SetXattrFlags> for XattrFlags {
+    fn from(value: SetXattrFlags) -> Self {
+        XattrFlags::from_bits_truncate(value.bits())
+    }
+}
+
+/// Calls `f` with a buffer large enough to hold its output, which is returned.
+///
+/// `f` is first called with an empty buffer to learn the needed size. If the
+/// value grows in the meantime, `f` fails with `ERANGE` and we retry with a
+/// larger buffer.
+pub(crate) fn read_growing<F>(f: F) -> Result<Vec<u8>>
+where
+    F: Fn(&mut [u8]) -> Result<usize>,
+{
+    let mut buf = Vec::new();
+    loop {
+        let size = f(&mut [])?;
+        // Leave some room for the value to grow.
+        buf.resize(size.max(buf.len() * 2) + 64, 0);
+
+        match f(&mut buf) {
+            Ok(len) => {
+                buf.truncate(len);
+                return Ok(buf);
+            }
+            Err(e) if e.raw_os_error() == Some(libc::ERANGE) => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// An iterator over the names of the extended attributes of a file.
+///
+/// This struct is returned by [`list_xattr`], [`list_symlink_xattr`] and
+/// [`File::list_xattr`].
+///
+/// [`File::list_xattr`]: crate::File::list_xattr
+#[derive(Debug, Clone)]
+pub struct XattrNames {
+    // names separated by NUL bytes, as returned by `listxattr(2)`
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl XattrNames {
+    pub(crate) fn new(buf: Vec<u8>) -> Self {
+        Self { buf, pos: 0 }
+    }
+}
+
+impl Iterator for XattrNames {
+    type Item = OsString;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = &self.buf[self.pos..];
+        if rest.is_empty() {
+            return None;
+        }
+
+        let len = rest.iter().position(|b| *b == 0).unwrap_or(rest.len());
+        self.pos += len + 1;
+        Some(OsString::from_vec(rest[..len].to_vec()))
+    }
+}
+
+/// Retrieves the value of the extended attribute `name` of a file, following
+/// symlinks.
+///
+/// # Errors
+/// Fails with `ENODATA` if the attribute does not exist.
+pub fn get_xattr<P: AsRef<Path>, N: AsRef<OsStr>>(path: P, name: N) -> Result<Vec<u8>> {
+    let (path, name) = (path.as_ref(), name.as_ref());
+    read_growing(|buf| encapsulation::getxattr(path, name, buf))
+}
+
+/// Same as [`get_xattr`], but does not follow symlinks.
+pub fn get_symlink_xattr<P: AsRef<Path>, N: AsRef<OsStr>>(path: P, name: N) -> Result<Vec<u8>> {
+    let (path, name) = (path.as_ref(), name.as_ref());
+    read_growing(|buf| encapsulation::lgetxattr(path, name, buf))
+}
+
+/// Sets the value of the extended attribute `name` of a file, following
+/// symlinks.
+pub fn set_xattr<P: AsRef<Path>, N: AsRef<OsStr>, V: AsRef<[u8]>>(
+    path: P,
+    name: N,
+    value: V,
+    flags: SetXattrFlags,
+) -> Result<()> {
+    encapsulation::setxattr(path, name, value.as_ref(), flags.into())
+}
+
+/// Same as [`set_xattr`], but does not follow symlinks.
+pub fn set_symlink_xattr<P: AsRef<Path>, N: AsRef<OsStr>, V: AsRef<[u8]>>(
+    path: P,
+    name: N,
+    value: V,
+    flags: SetXattrFlags,
+) -> Result<()> {
+    encapsulation::lsetxattr(path, name, value.as_ref(), flags.into())
+}
+
+/// Returns an iterator over the names of the extended attributes of a file,
+/// following symlinks.
+pub fn list_xattr<P: AsRef<Path>>(path: P) -> Result<XattrNames> {
+    let path = path.as_ref();
+    read_growing(|buf| encapsulation::listxattr(path, buf)).map(XattrNames::new)
+}
+
+/// Same as [`list_xattr`], but does not follow symlinks.
+pub fn list_symlink_xattr<P: AsRef<Path>>(path: P) -> Result<XattrNames> {
+    let path = path.as_ref();
+    read_growing(|buf| encapsulation::llistxattr(path, buf)).map(XattrNames::new)
+}
+
+/// Removes the extended attribute `name` of a file, following symlinks.
+pub fn remove_xattr<P: AsRef<Path>, N: AsRef<OsStr>>(path: P, name: N) -> Result<()> {
+    encapsulation::removexattr(path, name)
+}
+
+/// Same as [`remove_xattr`], but does not follow symlinks.
+pub fn remove_symlink_xattr<P: AsRef<Path>, N: AsRef<OsStr>>(path: P, name: N) -> Result<()> {
+    encapsulation::lremovexattr(path, name)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{functions::symlink, temp::TempDir, File};
+    use std::io::ErrorKind;
+
+    #[test]
+    fn xattr() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("file");
+        File::create(&path).unwrap();
+
+        set_xattr(&path, "user.a", b"1", SetXattrFlags::CREATE).unwrap();
+        set_xattr(&path, "user.b", vec![0xff; 4000], SetXattrFlags::empty()).unwrap();
+        assert_eq!(
+            set_xattr(&path, "user.a", b"2", SetXattrFlags::CREATE)
+                .unwrap_err()
+                .kind(),
+            ErrorKind::AlreadyExists
+        );
+
+        assert_eq!(get_xattr(&path, "user.a").unwrap(), b"1");
+        assert_eq!(get_xattr(&path, "user.b").unwrap(), vec![0xff; 4000]);
+        let names = list_xattr(&path).unwrap().collect::<Vec<_>>();
+        assert!(names.contains(&OsString::from("user.a")));
+        assert!(names.contains(&OsString::from("user.b")));
+
+        remove_xattr(&path, "user.a").unwrap();
+        assert_eq!(
+            get_xattr(&path, "user.a").unwrap_err().raw_os_error(),
+            Some(libc::ENODATA)
+        );
+    }
+
+    #[test]
+    fn symlink_xattr() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("file");
+        let link = dir.path().join("link");
+        File::create(&path).unwrap();
+        symlink(&path, &link).unwrap();
+
+        set_xattr(&link, "user.a", b"1", SetXattrFlags::empty()).unwrap();
+        assert_eq!(get_xattr(&path, "user.a").unwrap(), b"1");
+        assert!(get_symlink_xattr(&link, "user.a").is_err());
+        assert!(set_symlink_xattr(&link, "user.a", b"1", SetXattrFlags::empty()).is_err());
+        assert!(remove_symlink_xattr(&link, "user.a").is_err());
+        assert!(!list_symlink_xattr(&link)
+            .unwrap()
+            .any(|name| name == "user.a"));
+    }
+
+    #[test]
+    fn xattr_names() {
+        let names = XattrNames::new(b"user.a\0security.b\0".to_vec()).collect::<Vec<_>>();
+        assert_eq!(names, ["user.a", "security.b"]);
+        assert_eq!(XattrNames::new(Vec::new()).count(), 0);
+    }
+}